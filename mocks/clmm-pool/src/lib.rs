@@ -0,0 +1,606 @@
+//! A minimal concentrated-liquidity pool, for testing the arbitrage program's
+//! CLMM quoting locally
+//!
+//! Unlike a real CLMM it has no positions: a pool is created with its price
+//! and active liquidity, and its tick arrays with every initialized tick's
+//! sqrt price and net liquidity already worked out. Swaps then walk those
+//! ticks exactly as a real pool would
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account, Mint},
+};
+
+/// The first byte of a pool account
+pub const POOL_KIND: u8 = 1;
+
+/// The first byte of a tick array account
+pub const TICK_ARRAY_KIND: u8 = 2;
+
+/// The most initialized ticks a tick array can hold
+pub const MAX_TICKS_PER_ARRAY: usize = 32;
+
+/// `1.0` in Q64.64
+const Q64: u128 = 1 << 64;
+
+/// Instructions for the mock pool
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum MockClmmInstruction {
+    /// Creates a pool PDA for two mints
+    ///
+    /// Accounts:
+    /// * Payer
+    /// * Pool
+    /// * Mint A
+    /// * Mint B
+    /// * Vault A (the pool's token account for mint A)
+    /// * Vault B (the pool's token account for mint B)
+    /// * System Program
+    InitializePool {
+        fee_bps: u16,
+        sqrt_price_x64: u128,
+        liquidity: u128,
+        tick_current: i32,
+    },
+    /// Creates one of a pool's tick array PDAs
+    ///
+    /// Accounts:
+    /// * Payer
+    /// * Pool
+    /// * Tick Array
+    /// * System Program
+    InitializeTickArray { id: u8, ticks: Vec<Tick> },
+    /// Swaps the whole of `amount_in` for the other asset
+    ///
+    /// Accounts:
+    /// * Pool
+    /// * Authority
+    /// * Authority's Token Account to pay from
+    /// * Authority's Token Account to receive into
+    /// * Pool's Vault to pay into
+    /// * Pool's Vault to receive from
+    /// * Mint paid
+    /// * Mint received
+    /// * Token Program
+    /// * [Tick Arrays]
+    Swap {
+        amount_in: u64,
+        minimum_amount_out: u64,
+    },
+}
+
+/// A pool's state
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ClmmPool {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+    pub bump: u8,
+    /// The pool's fee, in basis points of the amount paid
+    pub fee_bps: u16,
+    /// The square root of the price - token B per token A - in Q64.64
+    pub sqrt_price_x64: u128,
+    /// The liquidity active in the current range
+    pub liquidity: u128,
+    /// The index of the tick at or below the current price
+    pub tick_current: i32,
+}
+
+impl ClmmPool {
+    pub const SEED_PREFIX: &'static [u8] = b"clmm_pool";
+    pub const LEN: usize = 1 + 32 * 4 + 1 + 2 + 16 + 16 + 4;
+}
+
+/// A set of a pool's initialized ticks, sorted by index
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct TickArray {
+    pub pool: Pubkey,
+    pub ticks: Vec<Tick>,
+}
+
+impl TickArray {
+    pub const SEED_PREFIX: &'static [u8] = b"tick_array";
+    pub const LEN: usize = 1 + 32 + (4 + MAX_TICKS_PER_ARRAY * Tick::LEN);
+}
+
+/// An initialized tick
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug)]
+pub struct Tick {
+    pub index: i32,
+    /// The square root of the tick's price, in Q64.64
+    pub sqrt_price_x64: u128,
+    /// The liquidity added when the price crosses the tick moving up - and
+    /// removed moving down
+    pub liquidity_net: i128,
+}
+
+impl Tick {
+    const LEN: usize = 4 + 16 + 16;
+}
+
+entrypoint!(process);
+
+/// Processor
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match MockClmmInstruction::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?
+    {
+        MockClmmInstruction::InitializePool {
+            fee_bps,
+            sqrt_price_x64,
+            liquidity,
+            tick_current,
+        } => process_initialize_pool(
+            program_id,
+            accounts,
+            fee_bps,
+            sqrt_price_x64,
+            liquidity,
+            tick_current,
+        ),
+        MockClmmInstruction::InitializeTickArray { id, ticks } => {
+            process_initialize_tick_array(program_id, accounts, id, ticks)
+        }
+        MockClmmInstruction::Swap {
+            amount_in,
+            minimum_amount_out,
+        } => process_swap(program_id, accounts, amount_in, minimum_amount_out),
+    }
+}
+
+fn process_initialize_pool(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fee_bps: u16,
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    tick_current: i32,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let pool_info = next_account_info(accounts_iter)?;
+    let mint_a = next_account_info(accounts_iter)?;
+    let mint_b = next_account_info(accounts_iter)?;
+    let vault_a = next_account_info(accounts_iter)?;
+    let vault_b = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    let (pool_address, bump) = Pubkey::find_program_address(
+        &[
+            ClmmPool::SEED_PREFIX,
+            mint_a.key.as_ref(),
+            mint_b.key.as_ref(),
+        ],
+        program_id,
+    );
+    if !pool_info.key.eq(&pool_address) || fee_bps > 10_000 || sqrt_price_x64 == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    for (vault, mint) in [(vault_a, mint_a), (vault_b, mint_b)] {
+        let vault = unpack_token_account(vault)?;
+        if !vault.owner.eq(pool_info.key) || !vault.mint.eq(mint.key) {
+            msg!("Vaults must be the pool's token accounts for its mints");
+            return Err(ProgramError::InvalidArgument);
+        }
+    }
+    create_account(
+        payer,
+        pool_info,
+        system_program,
+        program_id,
+        ClmmPool::LEN,
+        &[
+            ClmmPool::SEED_PREFIX,
+            mint_a.key.as_ref(),
+            mint_b.key.as_ref(),
+            &[bump],
+        ],
+    )?;
+    save(
+        pool_info,
+        POOL_KIND,
+        &ClmmPool {
+            mint_a: *mint_a.key,
+            mint_b: *mint_b.key,
+            vault_a: *vault_a.key,
+            vault_b: *vault_b.key,
+            bump,
+            fee_bps,
+            sqrt_price_x64,
+            liquidity,
+            tick_current,
+        },
+    )
+}
+
+fn process_initialize_tick_array(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    id: u8,
+    ticks: Vec<Tick>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let pool_info = next_account_info(accounts_iter)?;
+    let tick_array_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    load::<ClmmPool>(program_id, pool_info, POOL_KIND)?;
+    let (tick_array_address, bump) = Pubkey::find_program_address(
+        &[TickArray::SEED_PREFIX, pool_info.key.as_ref(), &[id]],
+        program_id,
+    );
+    if !tick_array_info.key.eq(&tick_array_address) || ticks.len() > MAX_TICKS_PER_ARRAY {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if ticks
+        .windows(2)
+        .any(|w| w[0].index >= w[1].index || w[0].sqrt_price_x64 >= w[1].sqrt_price_x64)
+    {
+        msg!("Ticks must be sorted by index and price");
+        return Err(ProgramError::InvalidArgument);
+    }
+    create_account(
+        payer,
+        tick_array_info,
+        system_program,
+        program_id,
+        TickArray::LEN,
+        &[
+            TickArray::SEED_PREFIX,
+            pool_info.key.as_ref(),
+            &[id],
+            &[bump],
+        ],
+    )?;
+    save(
+        tick_array_info,
+        TICK_ARRAY_KIND,
+        &TickArray {
+            pool: *pool_info.key,
+            ticks,
+        },
+    )
+}
+
+fn process_swap(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let pool_info = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    let user_pay = next_account_info(accounts_iter)?;
+    let user_receive = next_account_info(accounts_iter)?;
+    let pool_pay = next_account_info(accounts_iter)?;
+    let pool_receive = next_account_info(accounts_iter)?;
+    let pay_mint = next_account_info(accounts_iter)?;
+    let receive_mint = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    let mut pool = load::<ClmmPool>(program_id, pool_info, POOL_KIND)?;
+    let a_to_b = if pool_pay.key.eq(&pool.vault_a) && pool_receive.key.eq(&pool.vault_b) {
+        true
+    } else if pool_pay.key.eq(&pool.vault_b) && pool_receive.key.eq(&pool.vault_a) {
+        false
+    } else {
+        msg!("Vaults are not the pool's");
+        return Err(ProgramError::InvalidArgument);
+    };
+    let mut ticks = vec![];
+    for tick_array_info in accounts_iter {
+        let tick_array = load::<TickArray>(program_id, tick_array_info, TICK_ARRAY_KIND)?;
+        if !tick_array.pool.eq(pool_info.key) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        ticks.extend(tick_array.ticks);
+    }
+    ticks.sort_by_key(|t| t.index);
+
+    let amount_out = swap(&mut pool, &ticks, a_to_b, amount_in).ok_or_else(|| {
+        msg!("Not enough initialized ticks to fill the swap");
+        ProgramError::InvalidArgument
+    })?;
+    if amount_out < minimum_amount_out {
+        msg!("Slippage: {} < {}", amount_out, minimum_amount_out);
+        return Err(ProgramError::InvalidArgument);
+    }
+    msg!("Swapped {} for {}", amount_in, amount_out);
+
+    invoke(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            user_pay.key,
+            pay_mint.key,
+            pool_pay.key,
+            authority.key,
+            &[],
+            amount_in,
+            unpack_mint_decimals(pay_mint)?,
+        )?,
+        &[
+            user_pay.clone(),
+            pay_mint.clone(),
+            pool_pay.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+    )?;
+    invoke_signed(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            pool_receive.key,
+            receive_mint.key,
+            user_receive.key,
+            pool_info.key,
+            &[],
+            amount_out,
+            unpack_mint_decimals(receive_mint)?,
+        )?,
+        &[
+            pool_receive.clone(),
+            receive_mint.clone(),
+            user_receive.clone(),
+            pool_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[
+            ClmmPool::SEED_PREFIX,
+            pool.mint_a.as_ref(),
+            pool.mint_b.as_ref(),
+            &[pool.bump],
+        ]],
+    )?;
+    save(pool_info, POOL_KIND, &pool)
+}
+
+/// Swaps against the pool's ticks, moving its price and active liquidity,
+/// and returns the amount received
+fn swap(pool: &mut ClmmPool, ticks: &[Tick], a_to_b: bool, amount_in: u64) -> Option<u64> {
+    let mut remaining = amount_in as u128 * (10_000 - pool.fee_bps as u128) / 10_000;
+    let mut received = 0u128;
+    while remaining > 0 {
+        let next = if a_to_b {
+            ticks.iter().rev().find(|t| t.index <= pool.tick_current)?
+        } else {
+            ticks.iter().find(|t| t.index > pool.tick_current)?
+        };
+        let target = next.sqrt_price_x64;
+        let (max_in, max_out) = if a_to_b {
+            (
+                amount_a_delta(target, pool.sqrt_price_x64, pool.liquidity, true)?,
+                amount_b_delta(target, pool.sqrt_price_x64, pool.liquidity, false)?,
+            )
+        } else {
+            (
+                amount_b_delta(pool.sqrt_price_x64, target, pool.liquidity, true)?,
+                amount_a_delta(pool.sqrt_price_x64, target, pool.liquidity, false)?,
+            )
+        };
+        if remaining >= max_in {
+            remaining -= max_in;
+            received = received.checked_add(max_out)?;
+            pool.sqrt_price_x64 = target;
+            if a_to_b {
+                pool.liquidity =
+                    u128::try_from((pool.liquidity as i128).checked_sub(next.liquidity_net)?)
+                        .ok()?;
+                pool.tick_current = next.index.checked_sub(1)?;
+            } else {
+                pool.liquidity =
+                    u128::try_from((pool.liquidity as i128).checked_add(next.liquidity_net)?)
+                        .ok()?;
+                pool.tick_current = next.index;
+            }
+        } else {
+            let new_sqrt_price = if a_to_b {
+                let numerator = pool.liquidity.checked_mul(Q64)?;
+                div_ceil(
+                    numerator,
+                    (numerator / pool.sqrt_price_x64).checked_add(remaining)?,
+                )?
+            } else {
+                pool.sqrt_price_x64
+                    .checked_add(mul_div(remaining, Q64, pool.liquidity)?)?
+            };
+            received = received.checked_add(if a_to_b {
+                amount_b_delta(new_sqrt_price, pool.sqrt_price_x64, pool.liquidity, false)?
+            } else {
+                amount_a_delta(pool.sqrt_price_x64, new_sqrt_price, pool.liquidity, false)?
+            })?;
+            pool.sqrt_price_x64 = new_sqrt_price;
+            remaining = 0;
+        }
+    }
+    u64::try_from(received).ok()
+}
+
+fn amount_a_delta(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> Option<u128> {
+    if liquidity == 0 || lower == upper {
+        return Some(0);
+    }
+    let numerator = liquidity.checked_mul(Q64)?;
+    let delta = upper.checked_sub(lower)?;
+    if round_up {
+        div_ceil(mul_div_ceil(numerator, delta, upper)?, lower)
+    } else {
+        mul_div(numerator, delta, upper)?.checked_div(lower)
+    }
+}
+
+fn amount_b_delta(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> Option<u128> {
+    let delta = upper.checked_sub(lower)?;
+    if round_up {
+        mul_div_ceil(liquidity, delta, Q64)
+    } else {
+        mul_div(liquidity, delta, Q64)
+    }
+}
+
+fn div_ceil(a: u128, b: u128) -> Option<u128> {
+    let quotient = a.checked_div(b)?;
+    Some(if quotient * b == a {
+        quotient
+    } else {
+        quotient + 1
+    })
+}
+
+fn mul_div_ceil(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    let quotient = mul_div(a, b, denominator)?;
+    if widening_mul(quotient, denominator) == widening_mul(a, b) {
+        Some(quotient)
+    } else {
+        quotient.checked_add(1)
+    }
+}
+
+fn mul_div(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let (hi, lo) = widening_mul(a, b);
+    if hi == 0 {
+        return Some(lo / denominator);
+    }
+    if hi >= denominator {
+        return None;
+    }
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+    let mid = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (lo_lo & MASK) | (mid << 64);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+fn unpack_token_account(info: &AccountInfo) -> Result<Account, ProgramError> {
+    let data = info.data.borrow();
+    Ok(StateWithExtensions::<Account>::unpack(&data)?.base)
+}
+
+fn unpack_mint_decimals(info: &AccountInfo) -> Result<u8, ProgramError> {
+    let data = info.data.borrow();
+    if data.len() == Mint::LEN {
+        return Ok(Mint::unpack(&data)?.decimals);
+    }
+    Ok(StateWithExtensions::<Mint>::unpack(&data)?.base.decimals)
+}
+
+fn create_account<'a>(
+    payer: &AccountInfo<'a>,
+    new_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            new_account.key,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[payer.clone(), new_account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )
+}
+
+fn load<T: BorshDeserialize>(
+    program_id: &Pubkey,
+    info: &AccountInfo,
+    kind: u8,
+) -> Result<T, ProgramError> {
+    let data = info.data.borrow();
+    if !info.owner.eq(program_id) || data.first() != Some(&kind) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    T::deserialize(&mut &data[1..]).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+fn save<T: BorshSerialize>(info: &AccountInfo, kind: u8, value: &T) -> ProgramResult {
+    let mut data = info.data.borrow_mut();
+    let mut data: &mut [u8] = &mut data;
+    kind.serialize(&mut data)
+        .and_then(|_| value.serialize(&mut data))
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_moves_pool() {
+        // The arbitrage program's own vector: 300,000 of B at L = 1,000,000
+        // fills to the tick at 1.1, doubles L crossing it, and moves on to
+        // 1.2 for 90,909 + 151,515 of A
+        let mut pool = ClmmPool {
+            mint_a: Pubkey::default(),
+            mint_b: Pubkey::default(),
+            vault_a: Pubkey::default(),
+            vault_b: Pubkey::default(),
+            bump: 0,
+            fee_bps: 0,
+            sqrt_price_x64: Q64,
+            liquidity: 1_000_000,
+            tick_current: 0,
+        };
+        let ticks = [
+            Tick {
+                index: 100,
+                sqrt_price_x64: Q64 + Q64 / 10,
+                liquidity_net: 1_000_000,
+            },
+            Tick {
+                index: 200,
+                sqrt_price_x64: 2 * Q64,
+                liquidity_net: 0,
+            },
+        ];
+        assert_eq!(swap(&mut pool, &ticks, false, 300_000), Some(242_424));
+        assert_eq!(pool.liquidity, 2_000_000);
+        assert_eq!(pool.tick_current, 100);
+        assert_eq!(pool.sqrt_price_x64, Q64 + 2 * (Q64 / 10));
+        // Selling it all back down runs out of ticks below 1.0
+        assert_eq!(swap(&mut pool, &ticks, true, u64::MAX / 2), None);
+    }
+}
@@ -0,0 +1,511 @@
+//! A minimal central limit order book, for testing the arbitrage program's
+//! order-book venue locally
+//!
+//! There are no makers: the market's authority writes price levels straight
+//! into its bid and ask slabs, and funds its vaults to back them. Takers then
+//! fill against those levels with immediate-or-cancel orders, as they would
+//! on a real book
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account, Mint},
+};
+
+/// The first byte of a market account
+pub const MARKET_KIND: u8 = 1;
+
+/// The first byte of a slab account
+pub const SLAB_KIND: u8 = 2;
+
+/// The most price levels a slab can hold
+pub const MAX_LEVELS: usize = 32;
+
+/// Instructions for the mock order book
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum MockOrderBookInstruction {
+    /// Creates a market PDA for a base and quote mint, along with its bid and
+    /// ask slab PDAs
+    ///
+    /// Accounts:
+    /// * Authority (Payer)
+    /// * Market
+    /// * Bids
+    /// * Asks
+    /// * Base Mint
+    /// * Quote Mint
+    /// * Base Vault (the market's token account for the base mint)
+    /// * Quote Vault (the market's token account for the quote mint)
+    /// * System Program
+    InitializeMarket { base_lot_size: u64, fee_bps: u16 },
+    /// Replaces one side of the book
+    ///
+    /// Accounts:
+    /// * Authority
+    /// * Market
+    /// * Slab (Bids or Asks)
+    SetLevels { levels: Vec<Level> },
+    /// Fills as much of `amount_in` as the book allows, taking only what was
+    /// filled
+    ///
+    /// Paying the base mint sells into the bids, paying the quote mint buys
+    /// from the asks
+    ///
+    /// Accounts:
+    /// * Market
+    /// * Owner
+    /// * Owner's Token Account to pay from
+    /// * Owner's Token Account to receive into
+    /// * Market's Vault to pay into
+    /// * Market's Vault to receive from
+    /// * Mint paid
+    /// * Mint received
+    /// * Token Program
+    /// * Bids
+    /// * Asks
+    PlaceIocOrder {
+        amount_in: u64,
+        minimum_amount_out: u64,
+    },
+}
+
+/// A market's state
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Market {
+    pub authority: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub bump: u8,
+    /// How many base atoms make up one lot - the unit orders are sized in
+    pub base_lot_size: u64,
+    /// The taker fee, in basis points of the amount received
+    pub fee_bps: u16,
+}
+
+impl Market {
+    pub const SEED_PREFIX: &'static [u8] = b"market";
+    pub const LEN: usize = 1 + 32 * 7 + 1 + 8 + 2;
+}
+
+/// One side of a market's book, best price first
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Slab {
+    pub market: Pubkey,
+    pub is_bids: bool,
+    pub levels: Vec<Level>,
+}
+
+impl Slab {
+    pub const BIDS_SEED_PREFIX: &'static [u8] = b"bids";
+    pub const ASKS_SEED_PREFIX: &'static [u8] = b"asks";
+    pub const LEN: usize = 1 + 32 + 1 + (4 + MAX_LEVELS * Level::LEN);
+}
+
+/// A price level
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug)]
+pub struct Level {
+    /// Quote atoms per lot
+    pub price: u64,
+    /// Lots available at this price
+    pub quantity: u64,
+}
+
+impl Level {
+    const LEN: usize = 8 + 8;
+}
+
+entrypoint!(process);
+
+/// Processor
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match MockOrderBookInstruction::try_from_slice(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?
+    {
+        MockOrderBookInstruction::InitializeMarket {
+            base_lot_size,
+            fee_bps,
+        } => process_initialize_market(program_id, accounts, base_lot_size, fee_bps),
+        MockOrderBookInstruction::SetLevels { levels } => {
+            process_set_levels(program_id, accounts, levels)
+        }
+        MockOrderBookInstruction::PlaceIocOrder {
+            amount_in,
+            minimum_amount_out,
+        } => process_place_ioc_order(program_id, accounts, amount_in, minimum_amount_out),
+    }
+}
+
+fn process_initialize_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    base_lot_size: u64,
+    fee_bps: u16,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let market_info = next_account_info(accounts_iter)?;
+    let bids_info = next_account_info(accounts_iter)?;
+    let asks_info = next_account_info(accounts_iter)?;
+    let base_mint = next_account_info(accounts_iter)?;
+    let quote_mint = next_account_info(accounts_iter)?;
+    let base_vault = next_account_info(accounts_iter)?;
+    let quote_vault = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    let (market_address, bump) = Pubkey::find_program_address(
+        &[
+            Market::SEED_PREFIX,
+            base_mint.key.as_ref(),
+            quote_mint.key.as_ref(),
+        ],
+        program_id,
+    );
+    if !market_info.key.eq(&market_address) || base_lot_size == 0 || fee_bps > 10_000 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    for (vault, mint) in [(base_vault, base_mint), (quote_vault, quote_mint)] {
+        let vault = unpack_token_account(vault)?;
+        if !vault.owner.eq(market_info.key) || !vault.mint.eq(mint.key) {
+            msg!("Vaults must be the market's token accounts for its mints");
+            return Err(ProgramError::InvalidArgument);
+        }
+    }
+    create_account(
+        authority,
+        market_info,
+        system_program,
+        program_id,
+        Market::LEN,
+        &[
+            Market::SEED_PREFIX,
+            base_mint.key.as_ref(),
+            quote_mint.key.as_ref(),
+            &[bump],
+        ],
+    )?;
+    for (slab_info, seed_prefix, is_bids) in [
+        (bids_info, Slab::BIDS_SEED_PREFIX, true),
+        (asks_info, Slab::ASKS_SEED_PREFIX, false),
+    ] {
+        let (slab_address, slab_bump) =
+            Pubkey::find_program_address(&[seed_prefix, market_info.key.as_ref()], program_id);
+        if !slab_info.key.eq(&slab_address) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        create_account(
+            authority,
+            slab_info,
+            system_program,
+            program_id,
+            Slab::LEN,
+            &[seed_prefix, market_info.key.as_ref(), &[slab_bump]],
+        )?;
+        save(
+            slab_info,
+            SLAB_KIND,
+            &Slab {
+                market: *market_info.key,
+                is_bids,
+                levels: vec![],
+            },
+        )?;
+    }
+    save(
+        market_info,
+        MARKET_KIND,
+        &Market {
+            authority: *authority.key,
+            base_mint: *base_mint.key,
+            quote_mint: *quote_mint.key,
+            base_vault: *base_vault.key,
+            quote_vault: *quote_vault.key,
+            bids: *bids_info.key,
+            asks: *asks_info.key,
+            bump,
+            base_lot_size,
+            fee_bps,
+        },
+    )
+}
+
+fn process_set_levels(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    levels: Vec<Level>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let market_info = next_account_info(accounts_iter)?;
+    let slab_info = next_account_info(accounts_iter)?;
+
+    let market = load::<Market>(program_id, market_info, MARKET_KIND)?;
+    if !authority.is_signer || !authority.key.eq(&market.authority) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let mut slab = load::<Slab>(program_id, slab_info, SLAB_KIND)?;
+    if !slab.market.eq(market_info.key) || levels.len() > MAX_LEVELS {
+        return Err(ProgramError::InvalidArgument);
+    }
+    // Best price first: highest bid, lowest ask
+    if levels.windows(2).any(|w| {
+        if slab.is_bids {
+            w[0].price <= w[1].price
+        } else {
+            w[0].price >= w[1].price
+        }
+    }) {
+        msg!("Levels must be sorted best price first");
+        return Err(ProgramError::InvalidArgument);
+    }
+    slab.levels = levels;
+    save(slab_info, SLAB_KIND, &slab)
+}
+
+fn process_place_ioc_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let market_info = next_account_info(accounts_iter)?;
+    let owner = next_account_info(accounts_iter)?;
+    let owner_pay = next_account_info(accounts_iter)?;
+    let owner_receive = next_account_info(accounts_iter)?;
+    let vault_pay = next_account_info(accounts_iter)?;
+    let vault_receive = next_account_info(accounts_iter)?;
+    let pay_mint = next_account_info(accounts_iter)?;
+    let receive_mint = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let bids_info = next_account_info(accounts_iter)?;
+    let asks_info = next_account_info(accounts_iter)?;
+
+    let market = load::<Market>(program_id, market_info, MARKET_KIND)?;
+    let selling_base = if vault_pay.key.eq(&market.base_vault)
+        && vault_receive.key.eq(&market.quote_vault)
+    {
+        true
+    } else if vault_pay.key.eq(&market.quote_vault) && vault_receive.key.eq(&market.base_vault) {
+        false
+    } else {
+        msg!("Vaults are not the market's");
+        return Err(ProgramError::InvalidArgument);
+    };
+    // Selling base takes from the bids, buying it takes from the asks
+    let slab_info = if selling_base { bids_info } else { asks_info };
+    if !slab_info.key.eq(if selling_base {
+        &market.bids
+    } else {
+        &market.asks
+    }) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let mut slab = load::<Slab>(program_id, slab_info, SLAB_KIND)?;
+
+    let (amount_paid, amount_out) = fill(&mut slab.levels, &market, selling_base, amount_in)
+        .ok_or(ProgramError::InvalidArgument)?;
+    let fee = amount_out as u128 * market.fee_bps as u128 / 10_000;
+    let amount_out = amount_out - fee as u64;
+    if amount_out < minimum_amount_out {
+        msg!("Slippage: {} < {}", amount_out, minimum_amount_out);
+        return Err(ProgramError::InvalidArgument);
+    }
+    msg!("Filled {} of {} for {}", amount_paid, amount_in, amount_out);
+
+    invoke(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            owner_pay.key,
+            pay_mint.key,
+            vault_pay.key,
+            owner.key,
+            &[],
+            amount_paid,
+            unpack_mint_decimals(pay_mint)?,
+        )?,
+        &[
+            owner_pay.clone(),
+            pay_mint.clone(),
+            vault_pay.clone(),
+            owner.clone(),
+            token_program.clone(),
+        ],
+    )?;
+    invoke_signed(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            vault_receive.key,
+            receive_mint.key,
+            owner_receive.key,
+            market_info.key,
+            &[],
+            amount_out,
+            unpack_mint_decimals(receive_mint)?,
+        )?,
+        &[
+            vault_receive.clone(),
+            receive_mint.clone(),
+            owner_receive.clone(),
+            market_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[
+            Market::SEED_PREFIX,
+            market.base_mint.as_ref(),
+            market.quote_mint.as_ref(),
+            &[market.bump],
+        ]],
+    )?;
+    save(slab_info, SLAB_KIND, &slab)
+}
+
+/// Walks the levels best price first, consuming what's filled, and returns
+/// how much was paid and how much is received before fees
+fn fill(
+    levels: &mut Vec<Level>,
+    market: &Market,
+    selling_base: bool,
+    amount_in: u64,
+) -> Option<(u64, u64)> {
+    let mut remaining = amount_in;
+    let mut received = 0u64;
+    for level in levels.iter_mut() {
+        // Whole lots only
+        let affordable = if selling_base {
+            remaining / market.base_lot_size
+        } else {
+            remaining / level.price.max(1)
+        };
+        let lots = affordable.min(level.quantity);
+        if lots == 0 {
+            break;
+        }
+        level.quantity -= lots;
+        if selling_base {
+            remaining -= lots.checked_mul(market.base_lot_size)?;
+            received = received.checked_add(lots.checked_mul(level.price)?)?;
+        } else {
+            remaining -= lots.checked_mul(level.price)?;
+            received = received.checked_add(lots.checked_mul(market.base_lot_size)?)?;
+        }
+    }
+    levels.retain(|level| level.quantity > 0);
+    Some((amount_in - remaining, received))
+}
+
+fn unpack_token_account(info: &AccountInfo) -> Result<Account, ProgramError> {
+    let data = info.data.borrow();
+    Ok(StateWithExtensions::<Account>::unpack(&data)?.base)
+}
+
+fn unpack_mint_decimals(info: &AccountInfo) -> Result<u8, ProgramError> {
+    let data = info.data.borrow();
+    if data.len() == Mint::LEN {
+        return Ok(Mint::unpack(&data)?.decimals);
+    }
+    Ok(StateWithExtensions::<Mint>::unpack(&data)?.base.decimals)
+}
+
+fn create_account<'a>(
+    payer: &AccountInfo<'a>,
+    new_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            new_account.key,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[payer.clone(), new_account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )
+}
+
+fn load<T: BorshDeserialize>(
+    program_id: &Pubkey,
+    info: &AccountInfo,
+    kind: u8,
+) -> Result<T, ProgramError> {
+    let data = info.data.borrow();
+    if !info.owner.eq(program_id) || data.first() != Some(&kind) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    T::deserialize(&mut &data[1..]).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+fn save<T: BorshSerialize>(info: &AccountInfo, kind: u8, value: &T) -> ProgramResult {
+    let mut data = info.data.borrow_mut();
+    let mut data: &mut [u8] = &mut data;
+    kind.serialize(&mut data)
+        .and_then(|_| value.serialize(&mut data))
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(base_lot_size: u64) -> Market {
+        Market {
+            authority: Pubkey::default(),
+            base_mint: Pubkey::default(),
+            quote_mint: Pubkey::default(),
+            base_vault: Pubkey::default(),
+            quote_vault: Pubkey::default(),
+            bids: Pubkey::default(),
+            asks: Pubkey::default(),
+            bump: 0,
+            base_lot_size,
+            fee_bps: 0,
+        }
+    }
+
+    #[test]
+    fn test_fill_consumes_levels() {
+        let mut levels = vec![
+            Level {
+                price: 100,
+                quantity: 2,
+            },
+            Level {
+                price: 110,
+                quantity: 5,
+            },
+        ];
+        // 2 lots at 100 then 3 at 110, leaving 2 at 110 resting
+        assert_eq!(fill(&mut levels, &market(10), false, 530), Some((530, 50)));
+        assert_eq!(levels.len(), 1);
+        assert_eq!((levels[0].price, levels[0].quantity), (110, 2));
+        // Selling only fills whole lots
+        let mut bids = vec![Level {
+            price: 90,
+            quantity: 5,
+        }];
+        assert_eq!(fill(&mut bids, &market(10), true, 35), Some((30, 270)));
+        assert_eq!(bids[0].quantity, 2);
+        // An empty book fills nothing
+        assert_eq!(fill(&mut vec![], &market(10), true, 35), Some((0, 0)));
+    }
+}
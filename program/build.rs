@@ -0,0 +1,230 @@
+//! Generates typed CPI builders from the Anchor IDLs the app keeps in
+//! `app/src/idl`, so a swap program upgrade that renames or drops an account
+//! fails the build rather than a leg of a trade
+//!
+//! Each IDL becomes a module of `$OUT_DIR`, holding a module per instruction
+//! we CPI into with its discriminator, an `Accounts` struct naming every
+//! account in the order the instruction expects them, and an `Args` struct
+//! encoding its data
+//!
+//! An IDL the generator can't handle fails the build with a message saying
+//! why, rather than a panic
+use std::{env, fmt::Write, fs, path::Path, process};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// The IDLs to generate builders for, relative to the workspace root, and
+/// the instructions in each that we CPI into
+const IDLS: &[(&str, &[&str])] = &[("app/src/idl/swap_program.json", &["swap"])];
+
+fn main() {
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("..");
+    let out_dir = env::var("OUT_DIR").unwrap();
+    for (idl_path, instructions) in IDLS {
+        let path = root.join(idl_path);
+        println!("cargo:rerun-if-changed={}", path.display());
+        let generated = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|idl| serde_json::from_str::<Value>(&idl).map_err(|e| e.to_string()))
+            .and_then(|idl| {
+                let name = idl["name"].as_str().ok_or("IDL has no name")?.to_string();
+                Ok((name, generate(&idl, instructions)?))
+            })
+            .and_then(|(name, code)| {
+                fs::write(Path::new(&out_dir).join(format!("{}.rs", name)), code)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = generated {
+            println!("cargo:warning={}: {}", idl_path, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Generates the builders for the named instructions in an IDL
+fn generate(idl: &Value, instructions: &[&str]) -> Result<String, String> {
+    let mut code = String::new();
+    writeln!(code, "use borsh::BorshSerialize;").unwrap();
+    writeln!(code, "use solana_program::account_info::AccountInfo;").unwrap();
+    let idl_instructions = idl["instructions"]
+        .as_array()
+        .ok_or("IDL has no instructions")?;
+    for name in instructions {
+        let ix = idl_instructions
+            .iter()
+            .find(|ix| ix["name"].as_str() == Some(name))
+            .ok_or_else(|| format!("IDL has no `{}` instruction", name))?;
+        generate_instruction(&mut code, ix)?;
+    }
+    Ok(code)
+}
+
+/// Generates the module for one instruction
+fn generate_instruction(code: &mut String, ix: &Value) -> Result<(), String> {
+    let name = str_field(ix, "name", "An instruction")?;
+    let what = format!("Instruction `{}`", name);
+    let module = snake_case(name);
+    let discriminator = &Sha256::digest(format!("global:{}", module).as_bytes())[..8];
+
+    writeln!(code).unwrap();
+    write_docs(code, "", &ix["docs"], &what)?;
+    writeln!(code, "pub mod {} {{", module).unwrap();
+    writeln!(code, "    use super::*;").unwrap();
+    writeln!(code).unwrap();
+    writeln!(code, "    /// Anchor's sighash for `{}`", module).unwrap();
+    writeln!(
+        code,
+        "    pub const DISCRIMINATOR: [u8; 8] = {:?};",
+        discriminator
+    )
+    .unwrap();
+
+    let accounts = array_field(ix, "accounts", &what)?;
+    let account_names = accounts
+        .iter()
+        .map(|account| str_field(account, "name", &format!("An account of `{}`", name)))
+        .collect::<Result<Vec<_>, _>>()?;
+    writeln!(code).unwrap();
+    writeln!(code, "    /// The accounts `{}` expects", module).unwrap();
+    writeln!(code, "    pub struct Accounts<'a, 'b> {{").unwrap();
+    for (account, account_name) in accounts.iter().zip(&account_names) {
+        if account.get("accounts").is_some() {
+            return Err(format!(
+                "`{}` has nested account groups, which aren't supported",
+                name
+            ));
+        }
+        write_docs(
+            code,
+            "        ",
+            &account["docs"],
+            &format!("Account `{}` of `{}`", account_name, name),
+        )?;
+        writeln!(
+            code,
+            "        pub {}: &'a AccountInfo<'b>,",
+            snake_case(account_name)
+        )
+        .unwrap();
+    }
+    writeln!(code, "    }}").unwrap();
+    writeln!(code).unwrap();
+    writeln!(code, "    impl<'b> Accounts<'_, 'b> {{").unwrap();
+    writeln!(
+        code,
+        "        /// The accounts, in the order the instruction expects them"
+    )
+    .unwrap();
+    writeln!(
+        code,
+        "        pub fn to_account_infos(&self) -> Vec<AccountInfo<'b>> {{"
+    )
+    .unwrap();
+    writeln!(code, "            vec![").unwrap();
+    for account_name in &account_names {
+        writeln!(
+            code,
+            "                self.{}.to_owned(),",
+            snake_case(account_name)
+        )
+        .unwrap();
+    }
+    writeln!(code, "            ]").unwrap();
+    writeln!(code, "        }}").unwrap();
+    writeln!(code, "    }}").unwrap();
+
+    writeln!(code).unwrap();
+    writeln!(code, "    /// The arguments `{}` expects", module).unwrap();
+    writeln!(code, "    #[derive(BorshSerialize)]").unwrap();
+    writeln!(code, "    pub struct Args {{").unwrap();
+    for arg in array_field(ix, "args", &what)? {
+        let arg_name = str_field(arg, "name", &format!("An argument of `{}`", name))?;
+        let ty = rust_type(&arg["type"])
+            .ok_or_else(|| format!("`{}` has an unsupported type of `{}`", name, arg_name))?;
+        writeln!(code, "        pub {}: {},", snake_case(arg_name), ty).unwrap();
+    }
+    writeln!(code, "    }}").unwrap();
+    writeln!(code).unwrap();
+    writeln!(code, "    impl Args {{").unwrap();
+    writeln!(
+        code,
+        "        /// The instruction data: the discriminator, then the arguments"
+    )
+    .unwrap();
+    writeln!(code, "        pub fn data(&self) -> Vec<u8> {{").unwrap();
+    writeln!(code, "            let mut data = DISCRIMINATOR.to_vec();").unwrap();
+    writeln!(code, "            self.serialize(&mut data)").unwrap();
+    writeln!(
+        code,
+        "                .expect(\"Serializing into a `Vec` can't fail\");"
+    )
+    .unwrap();
+    writeln!(code, "            data").unwrap();
+    writeln!(code, "        }}").unwrap();
+    writeln!(code, "    }}").unwrap();
+    writeln!(code, "}}").unwrap();
+    Ok(())
+}
+
+/// Writes an IDL item's docs - if it has any - as doc comments
+fn write_docs(code: &mut String, indent: &str, docs: &Value, what: &str) -> Result<(), String> {
+    for line in docs.as_array().into_iter().flatten() {
+        let line = line
+            .as_str()
+            .ok_or_else(|| format!("{} has a doc line that isn't a string", what))?;
+        writeln!(code, "{}/// {}", indent, line).unwrap();
+    }
+    Ok(())
+}
+
+/// Reads a string field of an IDL item, naming the item if it's missing
+fn str_field<'v>(item: &'v Value, field: &str, what: &str) -> Result<&'v str, String> {
+    item[field]
+        .as_str()
+        .ok_or_else(|| format!("{} has no `{}` string", what, field))
+}
+
+/// Reads an array field of an IDL item, naming the item if it's missing
+fn array_field<'v>(item: &'v Value, field: &str, what: &str) -> Result<&'v Vec<Value>, String> {
+    item[field]
+        .as_array()
+        .ok_or_else(|| format!("{} has no `{}` array", what, field))
+}
+
+/// Maps an IDL type to the Rust type Borsh encodes the same way
+fn rust_type(ty: &Value) -> Option<String> {
+    if let Some(vec) = ty.get("vec") {
+        return rust_type(vec).map(|t| format!("Vec<{}>", t));
+    }
+    if let Some(option) = ty.get("option") {
+        return rust_type(option).map(|t| format!("Option<{}>", t));
+    }
+    Some(
+        match ty.as_str()? {
+            "publicKey" => "solana_program::pubkey::Pubkey",
+            "string" => "String",
+            "bytes" => "Vec<u8>",
+            primitive @ ("bool" | "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64"
+            | "u128" | "i128") => primitive,
+            _ => return None,
+        }
+        .to_string(),
+    )
+}
+
+/// `camelCase` to `snake_case`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if !snake.is_empty() {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
@@ -1,14 +1,15 @@
 //! Arbitrage opportunity spotting and trade placement
 use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, msg,
-    program::invoke, pubkey::Pubkey,
+    program::invoke_signed, program_error::ProgramError, pubkey::Pubkey,
 };
 
 use crate::{
     error::ArbitrageProgramError,
-    partial_state::{ArbitrageMintInfo, ArbitrageTokenAccountInfo},
-    swap::determine_swap_receive,
+    partial_state::{ArbitrageMintInfo, ArbitrageTokenAccountInfo, PartialTokenAccountState},
+    state::{pair_key, AssetCaps, FeeSplit, PairCooldowns, StrategyParams, Venue, VenueLayout},
     util::{ArbitrageEvaluateOption, ToAccountMeta},
+    venue::{self, SwapLeg, SwapVenue},
 };
 
 /// Args for the `try_arbitrage` algorithm
@@ -16,8 +17,22 @@ pub struct TryArbitrageArgs<'a, 'b> {
     pub token_accounts_user: Vec<ArbitrageTokenAccountInfo<'a, 'b>>,
     pub token_accounts_swap_1: Vec<ArbitrageTokenAccountInfo<'a, 'b>>,
     pub token_accounts_swap_2: Vec<ArbitrageTokenAccountInfo<'a, 'b>>,
+    /// The treasury's token accounts, if the config takes a treasury fee
+    pub token_accounts_treasury: Option<Vec<ArbitrageTokenAccountInfo<'a, 'b>>>,
+    /// The keeper's token accounts, if the config takes a keeper fee and the
+    /// keeper isn't already the trader
+    pub token_accounts_keeper: Option<Vec<ArbitrageTokenAccountInfo<'a, 'b>>>,
+    /// How much of each user token account may be spent, if trading as a
+    /// delegate
+    pub allowances: Option<Vec<u64>>,
     pub mints: Vec<ArbitrageMintInfo<'a, 'b>>,
-    pub payer: &'a AccountInfo<'b>,
+    pub mint_caps: Vec<AssetCaps>,
+    /// The owner of the user token accounts, who authorizes both swaps -
+    /// either the payer or a vault
+    pub authority: &'a AccountInfo<'b>,
+    /// The authority's PDA signer seeds, if it's an account of this program
+    /// rather than the payer
+    pub authority_seeds: Option<&'a [&'a [u8]]>,
     pub token_program: &'a AccountInfo<'b>,
     pub system_program: &'a AccountInfo<'b>,
     pub associated_token_program: &'a AccountInfo<'b>,
@@ -25,130 +40,293 @@ pub struct TryArbitrageArgs<'a, 'b> {
     pub swap_2_program: &'a AccountInfo<'b>,
     pub swap_1_pool: &'a AccountInfo<'b>,
     pub swap_2_pool: &'a AccountInfo<'b>,
+    pub swap_1_venue: &'a Venue,
+    pub swap_2_venue: &'a Venue,
+    /// Swap #1's layout, if its venue is registered
+    pub swap_1_layout: Option<&'a VenueLayout>,
+    /// Swap #2's layout, if its venue is registered
+    pub swap_2_layout: Option<&'a VenueLayout>,
+    /// Any further accounts Swap #1's venue needs
+    pub swap_1_venue_accounts: &'a [AccountInfo<'b>],
+    /// Any further accounts Swap #2's venue needs
+    pub swap_2_venue_accounts: &'a [AccountInfo<'b>],
+    pub fee_split: &'a FeeSplit,
+    pub cooldowns: &'a PairCooldowns,
+    pub cooldown_slots: u64,
+    pub slot: u64,
+    pub strategy: &'a StrategyParams,
     pub temperature: u8,
 }
 
+/// The result of an executed round trip
+pub struct TradeOutcome {
+    /// The asset paid into the first leg and received back from the second
+    pub mint: Pubkey,
+    /// The asset received from the first leg and paid into the second
+    pub received_mint: Pubkey,
+    /// The amount the first leg took - which can be less than offered, if
+    /// its venue only fills part
+    pub amount_in: u64,
+    /// The amount received from the first leg and paid into the second
+    pub amount_received: u64,
+    /// The amount received back from the second leg, before fees
+    pub amount_out: u64,
+    /// The program ID of the venue bought on
+    pub buy_venue: Pubkey,
+    /// The program ID of the venue sold on
+    pub sell_venue: Pubkey,
+    /// The realised profit - or loss, if negative - in that asset, after
+    /// fees
+    pub profit: i64,
+}
+
 /// Checks to see if there is an arbitrage opportunity between the two pools,
 /// and executes the trade if there is one
-pub fn try_arbitrage(args: TryArbitrageArgs<'_, '_>) -> ProgramResult {
+pub fn try_arbitrage(args: TryArbitrageArgs<'_, '_>) -> Result<TradeOutcome, ProgramError> {
     msg!("Swap #1 Pool: {}", args.swap_1_pool.key);
     msg!("Swap #2 Pool: {}", args.swap_2_pool.key);
+    // Skip any venue whose circuit breaker has been tripped, which leaves
+    // nothing to arbitrage against
+    for venue in [args.swap_1_venue, args.swap_2_venue] {
+        if venue.disabled {
+            msg!("Skipping disabled venue: {}", venue.program_id);
+            return Err(ArbitrageProgramError::NoArbitrage.into());
+        }
+    }
+    let venue_1 = venue::adapter(args.swap_1_venue, args.swap_1_layout)?;
+    let venue_2 = venue::adapter(args.swap_2_venue, args.swap_2_layout)?;
     let mints_len = args.mints.len();
     for i in 0..mints_len {
-        // Load each token account and the mint for the asset we want to drive arbitrage
-        // with
+        // Load the trader's token account and the mint for the asset we want to
+        // drive arbitrage with
         let user_i = args.token_accounts_user.get(i).ok_or_arb_err()?;
-        let swap_1_i = args.token_accounts_swap_1.get(i).ok_or_arb_err()?;
-        let swap_2_i = args.token_accounts_swap_2.get(i).ok_or_arb_err()?;
         let mint_i = args.mints.get(i).ok_or_arb_err()?;
+        // Size the trade within the asset's caps before quoting - or building -
+        // anything
+        let pay_amount = trade_size(
+            args.mint_caps.get(i).ok_or_arb_err()?,
+            args.strategy,
+            user_i.3,
+            allowance(&args, i)?,
+        );
+        if pay_amount == 0 {
+            continue;
+        }
         for j in (i + 1)..mints_len {
-            // Load each token account and the mint for the asset we are investigating
-            // arbitrage trading against
-            let user_j = args.token_accounts_user.get(j).ok_or_arb_err()?;
-            let swap_1_j = args.token_accounts_swap_1.get(j).ok_or_arb_err()?;
-            let swap_2_j = args.token_accounts_swap_2.get(j).ok_or_arb_err()?;
+            // Load the mint for the asset we are investigating arbitrage trading
+            // against
             let mint_j = args.mints.get(j).ok_or_arb_err()?;
+            // Skip any pair still cooling down from its last trade
+            if args.cooldown_slots > 0
+                && args.cooldowns.is_cooling_down(
+                    &pair_key(
+                        mint_i.0.key,
+                        mint_j.0.key,
+                        args.swap_1_pool.key,
+                        args.swap_2_pool.key,
+                    ),
+                    args.slot,
+                    args.cooldown_slots,
+                )
+            {
+                msg!(
+                    "Skipping pair on cooldown: {} / {}",
+                    mint_i.0.key,
+                    mint_j.0.key
+                );
+                continue;
+            }
             // Calculate how much of each asset we can expect to receive for our proposed
-            // asset we would pay
-            let r_swap_1 =
-                determine_swap_receive(swap_1_j.3, mint_j.1, swap_1_i.3, mint_i.1, user_i.3)?;
-            let r_swap_2 =
-                determine_swap_receive(swap_2_j.3, mint_j.1, swap_2_i.3, mint_i.1, user_i.3)?;
-            if r_swap_1 == 0 || r_swap_1 > swap_1_j.3 || r_swap_2 == 0 || r_swap_2 > swap_2_j.3 {
+            // asset we would pay, after each swap takes its fee
+            let buy_swap_1 = swap_leg(&args, Swap::Swap1, i, j)?;
+            let buy_swap_2 = swap_leg(&args, Swap::Swap2, i, j)?;
+            let r_swap_1 = venue_1.quote(&buy_swap_1, pay_amount)?;
+            let r_swap_2 = venue_2.quote(&buy_swap_2, pay_amount)?;
+            if r_swap_1 == 0
+                || r_swap_1 > buy_swap_1.pool_receive.3
+                || r_swap_2 == 0
+                || r_swap_2 > buy_swap_2.pool_receive.3
+            {
                 continue;
             }
+            // Skip the pair if either leg would sell back more than a delegate
+            // has been approved to spend
+            if let Some(allowance_j) = allowance(&args, j)? {
+                if r_swap_1.max(r_swap_2) > allowance_j {
+                    continue;
+                }
+            }
             // Evaluate the arbitrage check
             if let Some(trade) = check_for_arbitrage(r_swap_1, r_swap_2, args.temperature) {
                 // If we have a trade, place it
                 msg!("PLACING TRADE!");
-                return match trade {
+                let (amount_in, amount_received, buy_venue, sell_venue) = match trade {
                     // Buy on Swap #1 and sell on Swap #2
                     Buy::Swap1 => {
                         msg!("Buy on Swap #1 and sell on Swap #2");
+                        let amount_in = venue_1.amount_paid(&buy_swap_1, pay_amount)?;
                         invoke_arbitrage(
+                            (venue_1.as_ref(), &buy_swap_1, pay_amount),
                             (
-                                *args.swap_1_program.key,
-                                &[
-                                    args.swap_1_pool.to_owned(),
-                                    mint_j.0.to_owned(),
-                                    swap_1_j.0.to_owned(),
-                                    user_j.0.to_owned(),
-                                    mint_i.0.to_owned(),
-                                    swap_1_i.0.to_owned(),
-                                    user_i.0.to_owned(),
-                                    args.payer.to_owned(),
-                                    args.token_program.to_owned(),
-                                    args.system_program.to_owned(),
-                                    args.associated_token_program.to_owned(),
-                                ],
-                                user_i.3,
-                            ),
-                            (
-                                *args.swap_2_program.key,
-                                &[
-                                    args.swap_2_pool.to_owned(),
-                                    mint_i.0.to_owned(),
-                                    swap_2_i.0.to_owned(),
-                                    user_i.0.to_owned(),
-                                    mint_j.0.to_owned(),
-                                    swap_2_j.0.to_owned(),
-                                    user_j.0.to_owned(),
-                                    args.payer.to_owned(),
-                                    args.token_program.to_owned(),
-                                    args.system_program.to_owned(),
-                                    args.associated_token_program.to_owned(),
-                                ],
+                                venue_2.as_ref(),
+                                &swap_leg(&args, Swap::Swap2, j, i)?,
                                 r_swap_1,
                             ),
+                            args.authority.key,
+                            args.authority_seeds,
+                        )?;
+                        (
+                            amount_in,
+                            r_swap_1,
+                            args.swap_1_program.key,
+                            args.swap_2_program.key,
                         )
                     }
                     // Buy on Swap #2 and sell on Swap #1
                     Buy::Swap2 => {
                         msg!("Buy on Swap #2 and sell on Swap #1");
+                        let amount_in = venue_2.amount_paid(&buy_swap_2, pay_amount)?;
                         invoke_arbitrage(
+                            (venue_2.as_ref(), &buy_swap_2, pay_amount),
                             (
-                                *args.swap_2_program.key,
-                                &[
-                                    args.swap_2_pool.to_owned(),
-                                    mint_j.0.to_owned(),
-                                    swap_2_j.0.to_owned(),
-                                    user_j.0.to_owned(),
-                                    mint_i.0.to_owned(),
-                                    swap_2_i.0.to_owned(),
-                                    user_i.0.to_owned(),
-                                    args.payer.to_owned(),
-                                    args.token_program.to_owned(),
-                                    args.system_program.to_owned(),
-                                    args.associated_token_program.to_owned(),
-                                ],
+                                venue_1.as_ref(),
+                                &swap_leg(&args, Swap::Swap1, j, i)?,
                                 r_swap_2,
                             ),
-                            (
-                                *args.swap_1_program.key,
-                                &[
-                                    args.swap_1_pool.to_owned(),
-                                    mint_i.0.to_owned(),
-                                    swap_1_i.0.to_owned(),
-                                    user_i.0.to_owned(),
-                                    mint_j.0.to_owned(),
-                                    swap_1_j.0.to_owned(),
-                                    user_j.0.to_owned(),
-                                    args.payer.to_owned(),
-                                    args.token_program.to_owned(),
-                                    args.system_program.to_owned(),
-                                    args.associated_token_program.to_owned(),
-                                ],
-                                user_j.3,
-                            ),
+                            args.authority.key,
+                            args.authority_seeds,
+                        )?;
+                        (
+                            amount_in,
+                            r_swap_2,
+                            args.swap_2_program.key,
+                            args.swap_1_program.key,
                         )
                     }
                 };
+                // Measure the round trip's realised profit or loss by the change
+                // in our balance of the asset we paid
+                let balance_after =
+                    PartialTokenAccountState::try_deserialize(user_i.0, &user_i.2)?.3;
+                let profit = balance_after as i64 - user_i.3 as i64;
+                msg!("Realised profit: {}", profit);
+                // Sweep the fees out of any profit right after the sell leg
+                let swept = if profit > 0 {
+                    sweep_profit(&args, i, profit as u64)?
+                } else {
+                    0
+                };
+                return Ok(TradeOutcome {
+                    mint: *mint_i.0.key,
+                    received_mint: *mint_j.0.key,
+                    amount_in,
+                    amount_received,
+                    amount_out: (amount_in as i64 + profit) as u64,
+                    buy_venue: *buy_venue,
+                    sell_venue: *sell_venue,
+                    profit: profit - swept as i64,
+                });
             }
         }
     }
     Err(ArbitrageProgramError::NoArbitrage.into())
 }
 
+/// Transfers the config's fee split of a round trip's profit in asset `i`
+/// from the trader to the treasury and the keeper, returning the total swept
+fn sweep_profit(
+    args: &TryArbitrageArgs<'_, '_>,
+    i: usize,
+    profit: u64,
+) -> Result<u64, ProgramError> {
+    let treasury_fee = sweep_fee(
+        args,
+        i,
+        &args.token_accounts_treasury,
+        args.fee_split.treasury_fee(profit),
+    )?;
+    let keeper_fee = sweep_fee(
+        args,
+        i,
+        &args.token_accounts_keeper,
+        args.fee_split.keeper_fee(profit),
+    )?;
+    msg!(
+        "Profit split: {} to treasury, {} to keeper, {} retained",
+        treasury_fee,
+        keeper_fee,
+        profit - treasury_fee - keeper_fee
+    );
+    Ok(treasury_fee + keeper_fee)
+}
+
+/// Transfers a fee in asset `i` from the trader to its recipient's token
+/// account, if there's a fee to take and a recipient to take it
+fn sweep_fee<'a, 'b>(
+    args: &TryArbitrageArgs<'a, 'b>,
+    i: usize,
+    recipient_token_accounts: &Option<Vec<ArbitrageTokenAccountInfo<'a, 'b>>>,
+    fee: u64,
+) -> Result<u64, ProgramError> {
+    let recipient = match recipient_token_accounts {
+        Some(token_accounts) if fee > 0 => token_accounts.get(i).ok_or_arb_err()?,
+        _ => return Ok(0),
+    };
+    let user_i = args.token_accounts_user.get(i).ok_or_arb_err()?;
+    let mint_i = args.mints.get(i).ok_or_arb_err()?;
+    let signer_seeds: &[&[&[u8]]] = match args.authority_seeds {
+        Some(seeds) => &[seeds],
+        None => &[],
+    };
+    invoke_signed(
+        &spl_token_2022::instruction::transfer_checked(
+            args.token_program.key,
+            user_i.0.key,
+            mint_i.0.key,
+            recipient.0.key,
+            args.authority.key,
+            &[],
+            fee,
+            mint_i.1,
+        )?,
+        &[
+            user_i.0.to_owned(),
+            mint_i.0.to_owned(),
+            recipient.0.to_owned(),
+            args.authority.to_owned(),
+            args.token_program.to_owned(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(fee)
+}
+
+/// Sizes a trade of an asset from a balance - and, if trading as a delegate,
+/// the allowance on it
+///
+/// The asset's caps limit what the balance can pay and the allowance limits
+/// what the delegate can, while the strategy takes only its share of what's
+/// spendable - the trade is the smallest of the three
+fn trade_size(
+    caps: &AssetCaps,
+    strategy: &StrategyParams,
+    balance: u64,
+    allowance: Option<u64>,
+) -> u64 {
+    let spendable = allowance.map_or(balance, |a| a.min(balance));
+    caps.trade_size(balance)
+        .min(spendable)
+        .min(strategy.trade_size(spendable))
+}
+
+/// How much of user token account `i` may be spent, if trading as a delegate
+fn allowance(args: &TryArbitrageArgs<'_, '_>, i: usize) -> Result<Option<u64>, ProgramError> {
+    match &args.allowances {
+        Some(allowances) => Ok(Some(*allowances.get(i).ok_or_arb_err()?)),
+        None => Ok(None),
+    }
+}
+
 /// Enum used to tell the algorithm which swap pool is a "buy"
 enum Buy {
     /// Buy on Swap #1 and sell on Swap #2
@@ -157,6 +335,12 @@ enum Buy {
     Swap2,
 }
 
+/// One of the two swaps being arbitraged
+enum Swap {
+    Swap1,
+    Swap2,
+}
+
 /// Evaluates the percent difference in the calculated values for `r` and
 /// determines which pool to buy or sell, if any
 fn check_for_arbitrage(r_swap_1: u64, r_swap_2: u64, temperature: u8) -> Option<Buy> {
@@ -180,45 +364,132 @@ fn check_for_arbitrage(r_swap_1: u64, r_swap_2: u64, temperature: u8) -> Option<
     }
 }
 
+/// Lays out a leg of a round trip on one of the two swaps, paying asset `pay`
+/// in return for asset `receive`
+fn swap_leg<'a, 'b>(
+    args: &TryArbitrageArgs<'a, 'b>,
+    swap: Swap,
+    pay: usize,
+    receive: usize,
+) -> Result<SwapLeg<'a, 'b>, ProgramError> {
+    let (program, pool, token_accounts_swap, venue_accounts) = match swap {
+        Swap::Swap1 => (
+            args.swap_1_program,
+            args.swap_1_pool,
+            &args.token_accounts_swap_1,
+            args.swap_1_venue_accounts,
+        ),
+        Swap::Swap2 => (
+            args.swap_2_program,
+            args.swap_2_pool,
+            &args.token_accounts_swap_2,
+            args.swap_2_venue_accounts,
+        ),
+    };
+    Ok(SwapLeg {
+        program,
+        pool,
+        pay_mint: *args.mints.get(pay).ok_or_arb_err()?,
+        receive_mint: *args.mints.get(receive).ok_or_arb_err()?,
+        pool_pay: *token_accounts_swap.get(pay).ok_or_arb_err()?,
+        pool_receive: *token_accounts_swap.get(receive).ok_or_arb_err()?,
+        user_pay: *args.token_accounts_user.get(pay).ok_or_arb_err()?,
+        user_receive: *args.token_accounts_user.get(receive).ok_or_arb_err()?,
+        authority: args.authority,
+        token_program: args.token_program,
+        system_program: args.system_program,
+        associated_token_program: args.associated_token_program,
+        venue_accounts,
+    })
+}
+
 /// Invokes the arbitrage trade by sending a cross-program invocation (CPI)
 /// first to the swap program we intend to buy from (receive), and then
 /// immediately send another CPI to the swap program we intend to sell to
+///
+/// If the authority is one of this program's PDAs, both CPIs are signed with
+/// its seeds
 fn invoke_arbitrage(
-    buy: (Pubkey, &[AccountInfo], u64),
-    sell: (Pubkey, &[AccountInfo], u64),
+    buy: (&dyn SwapVenue, &SwapLeg, u64),
+    sell: (&dyn SwapVenue, &SwapLeg, u64),
+    authority: &Pubkey,
+    authority_seeds: Option<&[&[u8]]>,
 ) -> ProgramResult {
-    let (buy_swap_ix_data, sell_swap_ix_data) = build_ix_datas(buy.2, sell.2);
-    let ix_buy = Instruction::new_with_borsh(
-        buy.0,
-        &buy_swap_ix_data,
-        buy.1.iter().map(ToAccountMeta::to_account_meta).collect(),
-    );
-    let ix_sell = Instruction::new_with_borsh(
-        sell.0,
-        &sell_swap_ix_data,
-        sell.1.iter().map(ToAccountMeta::to_account_meta).collect(),
-    );
+    let signer_seeds: &[&[&[u8]]] = match authority_seeds {
+        Some(seeds) => &[seeds],
+        None => &[],
+    };
     msg!("Executing buy ...");
-    invoke(&ix_buy, buy.1)?;
+    invoke_swap(buy, authority, signer_seeds)?;
     msg!("Executing sell ...");
-    invoke(&ix_sell, sell.1)?;
-    Ok(())
+    invoke_swap(sell, authority, signer_seeds)
 }
 
-/// Used to build the instruction data for the `swap` instruction
-/// on each swap program
-fn build_ix_datas(buy_amount: u64, sell_amount: u64) -> ([u8; 16], [u8; 16]) {
-    // Initialize both datas
-    let mut buy_swap_ix_data = [0u8; 16];
-    let mut sell_swap_ix_data = [0u8; 16];
-    // Lay out the configs
-    let swap_ix_hash = solana_program::hash::hash(b"global:swap");
-    let buy_amount_as_bytes: [u8; 8] = buy_amount.to_le_bytes();
-    let sell_amount_as_bytes: [u8; 8] = sell_amount.to_le_bytes();
-    // Copy in the bytes
-    buy_swap_ix_data[..8].copy_from_slice(&swap_ix_hash.to_bytes()[..8]);
-    buy_swap_ix_data[8..].copy_from_slice(&buy_amount_as_bytes);
-    sell_swap_ix_data[..8].copy_from_slice(&swap_ix_hash.to_bytes()[..8]);
-    sell_swap_ix_data[8..].copy_from_slice(&sell_amount_as_bytes);
-    (buy_swap_ix_data, sell_swap_ix_data)
+/// Sends a single leg's `swap` instruction to its venue
+fn invoke_swap(
+    (venue, leg, amount): (&dyn SwapVenue, &SwapLeg, u64),
+    authority: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let accounts = venue.swap_accounts(leg);
+    // A PDA's `AccountInfo` isn't marked as a signer, so mark the authority's
+    // meta ourselves - `invoke_signed` supplies the signature
+    let account_metas = accounts
+        .iter()
+        .map(|a| {
+            let mut meta = a.to_account_meta();
+            meta.is_signer |= meta.pubkey.eq(authority);
+            meta
+        })
+        .collect();
+    let ix = Instruction::new_with_bytes(*leg.program.key, &venue.swap_data(amount), account_metas);
+    invoke_signed(&ix, &accounts, signer_seeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TradingAs;
+
+    fn strategy(trade_size_bps: u16) -> StrategyParams {
+        StrategyParams {
+            trade_size_bps,
+            ..StrategyParams::unrestricted(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                2,
+                90,
+                TradingAs::Payer,
+            )
+        }
+    }
+
+    #[test]
+    fn test_trade_size() {
+        let caps = AssetCaps {
+            reserve: 95_000,
+            ..AssetCaps::default()
+        };
+        // The reserve comes out of the balance, not the strategy's half of it
+        assert_eq!(trade_size(&caps, &strategy(5_000), 100_000, None), 5_000);
+        // The strategy's share is the limit when it's the smaller
+        assert_eq!(trade_size(&caps, &strategy(100), 100_000, None), 1_000);
+        let caps = AssetCaps {
+            max_trade_amount: Some(30_000),
+            max_trade_bps: Some(2_500),
+            ..AssetCaps::default()
+        };
+        assert_eq!(trade_size(&caps, &strategy(10_000), 100_000, None), 25_000);
+        // An allowance caps what's spendable, and the strategy's share is of
+        // the allowance - but the caps still see the whole balance
+        assert_eq!(
+            trade_size(&caps, &strategy(10_000), 100_000, Some(10_000)),
+            10_000
+        );
+        assert_eq!(
+            trade_size(&caps, &strategy(5_000), 100_000, Some(10_000)),
+            5_000
+        );
+        assert_eq!(trade_size(&caps, &strategy(10_000), 0, Some(10_000)), 0);
+    }
 }
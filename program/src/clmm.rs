@@ -0,0 +1,302 @@
+//! Concentrated-liquidity quoting, walking a pool's initialized ticks
+//!
+//! Prices are kept as square roots in Q64.64 fixed point, as concentrated
+//! liquidity pools do. Within a range between two initialized ticks the pool
+//! behaves like a constant-product pool with liquidity `L`:
+//!
+//! ```text
+//! Δx = L * (1 / sqrt(P') - 1 / sqrt(P))
+//! Δy = L * (sqrt(P') - sqrt(P))
+//! ```
+//!
+//! and crossing a tick adds (moving up) or removes (moving down) its net
+//! liquidity
+use crate::swap::apply_fee;
+
+/// `1.0` in Q64.64
+pub const Q64: u128 = 1 << 64;
+
+/// The parts of a concentrated-liquidity pool's state needed to quote it
+pub struct ClmmState {
+    /// The square root of the pool's price - token B per token A - in Q64.64
+    pub sqrt_price_x64: u128,
+    /// The liquidity active in the current range
+    pub liquidity: u128,
+    /// The index of the tick at or below the current price
+    pub tick_current: i32,
+    /// The pool's fee, in basis points of the amount paid
+    pub fee_bps: u16,
+}
+
+/// An initialized tick
+#[derive(Clone, Copy, Debug)]
+pub struct ClmmTick {
+    pub index: i32,
+    /// The square root of the tick's price, in Q64.64
+    pub sqrt_price_x64: u128,
+    /// The liquidity added when the price crosses the tick moving up - and
+    /// removed moving down
+    pub liquidity_net: i128,
+}
+
+/// Computes exactly how much a payment of `pay_amount` returns, walking the
+/// provided initialized ticks - which must be sorted by index - as the price
+/// moves
+///
+/// Returns `None` if the ticks provided run out before the payment is filled,
+/// since the pool would refuse the swap
+pub fn swap_receive(
+    state: &ClmmState,
+    ticks: &[ClmmTick],
+    a_to_b: bool,
+    pay_amount: u64,
+) -> Option<u64> {
+    let mut remaining = apply_fee(pay_amount, state.fee_bps) as u128;
+    let mut sqrt_price = state.sqrt_price_x64;
+    let mut liquidity = state.liquidity;
+    let mut tick_current = state.tick_current;
+    let mut received = 0u128;
+    while remaining > 0 {
+        // Selling token A moves the price down, selling token B moves it up
+        let next = if a_to_b {
+            ticks.iter().rev().find(|t| t.index <= tick_current)?
+        } else {
+            ticks.iter().find(|t| t.index > tick_current)?
+        };
+        let target = next.sqrt_price_x64;
+        let (max_in, max_out) = if a_to_b {
+            (
+                amount_a_delta(target, sqrt_price, liquidity, true)?,
+                amount_b_delta(target, sqrt_price, liquidity, false)?,
+            )
+        } else {
+            (
+                amount_b_delta(sqrt_price, target, liquidity, true)?,
+                amount_a_delta(sqrt_price, target, liquidity, false)?,
+            )
+        };
+        if remaining >= max_in {
+            // Fill the whole range and cross into the next one
+            remaining -= max_in;
+            received = received.checked_add(max_out)?;
+            sqrt_price = target;
+            if a_to_b {
+                liquidity =
+                    u128::try_from((liquidity as i128).checked_sub(next.liquidity_net)?).ok()?;
+                tick_current = next.index.checked_sub(1)?;
+            } else {
+                liquidity =
+                    u128::try_from((liquidity as i128).checked_add(next.liquidity_net)?).ok()?;
+                tick_current = next.index;
+            }
+        } else {
+            // Fill the rest within this range
+            let new_sqrt_price = if a_to_b {
+                // sqrt(P') = L * sqrt(P) / (L + Δx * sqrt(P)), rounded up
+                let numerator = liquidity.checked_mul(Q64)?;
+                div_ceil(numerator, (numerator / sqrt_price).checked_add(remaining)?)?
+            } else {
+                // sqrt(P') = sqrt(P) + Δy / L, rounded down
+                sqrt_price.checked_add(mul_div(remaining, Q64, liquidity)?)?
+            };
+            received = received.checked_add(if a_to_b {
+                amount_b_delta(new_sqrt_price, sqrt_price, liquidity, false)?
+            } else {
+                amount_a_delta(sqrt_price, new_sqrt_price, liquidity, false)?
+            })?;
+            remaining = 0;
+        }
+    }
+    u64::try_from(received).ok()
+}
+
+/// The amount of token A between two sqrt prices, `lower <= upper`:
+/// `L * (upper - lower) / (upper * lower)`
+pub fn amount_a_delta(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> Option<u128> {
+    if liquidity == 0 || lower == upper {
+        return Some(0);
+    }
+    let numerator = liquidity.checked_mul(Q64)?;
+    let delta = upper.checked_sub(lower)?;
+    if round_up {
+        div_ceil(mul_div_ceil(numerator, delta, upper)?, lower)
+    } else {
+        mul_div(numerator, delta, upper)?.checked_div(lower)
+    }
+}
+
+/// The amount of token B between two sqrt prices, `lower <= upper`:
+/// `L * (upper - lower)`
+pub fn amount_b_delta(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> Option<u128> {
+    let delta = upper.checked_sub(lower)?;
+    if round_up {
+        mul_div_ceil(liquidity, delta, Q64)
+    } else {
+        mul_div(liquidity, delta, Q64)
+    }
+}
+
+/// `ceil(a / b)`
+fn div_ceil(a: u128, b: u128) -> Option<u128> {
+    let quotient = a.checked_div(b)?;
+    Some(if quotient * b == a {
+        quotient
+    } else {
+        quotient + 1
+    })
+}
+
+/// `ceil(a * b / denominator)`, with a 256-bit intermediate product
+fn mul_div_ceil(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    let quotient = mul_div(a, b, denominator)?;
+    if widening_mul(quotient, denominator) == widening_mul(a, b) {
+        Some(quotient)
+    } else {
+        quotient.checked_add(1)
+    }
+}
+
+/// `floor(a * b / denominator)`, with a 256-bit intermediate product
+///
+/// Returns `None` if the result doesn't fit in 128 bits
+pub fn mul_div(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let (hi, lo) = widening_mul(a, b);
+    if hi == 0 {
+        return Some(lo / denominator);
+    }
+    if hi >= denominator {
+        return None;
+    }
+    // Long division, a bit at a time, keeping the remainder below the
+    // denominator
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+/// The full 256-bit product of two `u128`s, as `(high, low)` halves
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+    let mid = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (lo_lo & MASK) | (mid << 64);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `1.1` in Q64.64, as the mock's tick arrays would store it
+    const SQRT_1_1: u128 = Q64 + Q64 / 10;
+
+    fn state(liquidity: u128) -> ClmmState {
+        ClmmState {
+            sqrt_price_x64: Q64,
+            liquidity,
+            tick_current: 0,
+            fee_bps: 0,
+        }
+    }
+
+    fn tick(index: i32, sqrt_price_x64: u128, liquidity_net: i128) -> ClmmTick {
+        ClmmTick {
+            index,
+            sqrt_price_x64,
+            liquidity_net,
+        }
+    }
+
+    #[test]
+    fn test_swap_within_range() {
+        // 100,000 of B moves sqrt(P) from 1 to 1.1 at L = 1,000,000, which
+        // returns L * (1 / 1 - 1 / 1.1) = 90,909.09 of A
+        let ticks = [tick(100, SQRT_1_1, 1_000_000), tick(200, 2 * Q64, 0)];
+        assert_eq!(
+            swap_receive(&state(1_000_000), &ticks, false, 99_999),
+            Some(90_908)
+        );
+        // Exactly reaching the tick fills the range and crosses it
+        assert_eq!(
+            swap_receive(&state(1_000_000), &ticks, false, 100_000),
+            Some(90_909)
+        );
+    }
+
+    #[test]
+    fn test_swap_crossing_up() {
+        // The first 100,000 of B fills up to the tick at 1.1 for 90,909 of
+        // A, then crossing it doubles L, so the other 200,000 moves sqrt(P)
+        // on to 1.2 for 2,000,000 * (1 / 1.1 - 1 / 1.2) = 151,515.15 more
+        let ticks = [tick(100, SQRT_1_1, 1_000_000), tick(200, 2 * Q64, 0)];
+        assert_eq!(
+            swap_receive(&state(1_000_000), &ticks, false, 300_000),
+            Some(90_909 + 151_515)
+        );
+    }
+
+    #[test]
+    fn test_swap_crossing_down() {
+        // Selling A crosses the tick at the current price straight away,
+        // dropping L to 1,000,000. 250,000 of A then moves sqrt(P) to
+        // L / (L + 250,000) = 0.8, for L * (1 - 0.8) = 200,000 of B - less
+        // one, as the new price rounds up in the pool's favour
+        let ticks = [tick(-1_000, Q64 / 2, 1_000_000), tick(0, Q64, 1_000_000)];
+        assert_eq!(
+            swap_receive(&state(2_000_000), &ticks, true, 250_000),
+            Some(199_999)
+        );
+        // 1,000,000 of A takes it exactly to the tick at 0.5, for 500,000
+        assert_eq!(
+            swap_receive(&state(2_000_000), &ticks, true, 1_000_000),
+            Some(500_000)
+        );
+    }
+
+    #[test]
+    fn test_swap_runs_out_of_ticks() {
+        // Past the tick at 0.5 there's no liquidity left to fill the rest
+        let ticks = [tick(-1_000, Q64 / 2, 1_000_000), tick(0, Q64, 1_000_000)];
+        assert_eq!(
+            swap_receive(&state(2_000_000), &ticks, true, 2_000_000),
+            None
+        );
+        assert_eq!(swap_receive(&state(1_000_000), &[], false, 1), None);
+    }
+
+    #[test]
+    fn test_swap_takes_fee_first() {
+        // 30 bps off 100,300 leaves 99,999 to swap
+        let ticks = [tick(100, SQRT_1_1, 1_000_000), tick(200, 2 * Q64, 0)];
+        let mut state = state(1_000_000);
+        state.fee_bps = 30;
+        assert_eq!(swap_receive(&state, &ticks, false, 100_300), Some(90_908));
+    }
+
+    #[test]
+    fn test_mul_div_wide() {
+        // 2^127 * 6 / 12 overflows 128 bits part way, but not at the end
+        assert_eq!(mul_div(1 << 127, 6, 12), Some(1 << 126));
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div_ceil(7, 3, 2), Some(11));
+    }
+}
@@ -18,6 +18,41 @@ pub enum ArbitrageProgramError {
     /// error so that preflight fails
     #[error("No arbitrage opportunity detected")]
     NoArbitrage,
+    /// A swap program not on the config's allowlist was passed into the
+    /// program
+    #[error("Swap program is not on the allowlist of approved venues")]
+    VenueNotAllowed,
+    /// The account provided as the config's authority does not match, or did
+    /// not sign the transaction
+    #[error("Missing or invalid config authority signature")]
+    InvalidAuthority,
+    /// An account expected to be owned by the arbitrage program was not, or
+    /// does not hold the expected kind of data
+    #[error("Invalid arbitrage program account")]
+    InvalidProgramAccount,
+    /// The account does not have enough space left for the requested change
+    #[error("Account is full")]
+    AccountFull,
+    /// The proposed configuration is invalid
+    #[error("Invalid configuration")]
+    InvalidConfig,
+    /// The config's global pause switch is set
+    #[error("Arbitrage is paused")]
+    Paused,
+    /// A trader's losses within the current loss window have exceeded the
+    /// limit set for one of their assets
+    #[error("Loss limit exceeded")]
+    LossLimitExceeded,
+    /// A pooled vault deposit or withdrawal resolves to nothing, or can't be
+    /// priced
+    #[error("Invalid share amount")]
+    InvalidShareAmount,
+    /// A trade made on a permissionless vault's behalf lost it money
+    #[error("Trade was unprofitable")]
+    UnprofitableTrade,
+    /// A pool's mints have decimals too far apart to scale to a common unit
+    #[error("Decimals out of range")]
+    DecimalsOutOfRange,
 }
 
 impl From<ArbitrageProgramError> for solana_program::program_error::ProgramError {
@@ -53,6 +88,36 @@ impl solana_program::program_error::PrintProgramError for ArbitrageProgramError
             ArbitrageProgramError::NoArbitrage => {
                 solana_program::msg!("No arbitrage opportunity detected")
             }
+            ArbitrageProgramError::VenueNotAllowed => {
+                solana_program::msg!("Swap program is not on the allowlist of approved venues")
+            }
+            ArbitrageProgramError::InvalidAuthority => {
+                solana_program::msg!("Missing or invalid config authority signature")
+            }
+            ArbitrageProgramError::InvalidProgramAccount => {
+                solana_program::msg!("Invalid arbitrage program account")
+            }
+            ArbitrageProgramError::AccountFull => {
+                solana_program::msg!("Account is full")
+            }
+            ArbitrageProgramError::InvalidConfig => {
+                solana_program::msg!("Invalid configuration")
+            }
+            ArbitrageProgramError::Paused => {
+                solana_program::msg!("Arbitrage is paused")
+            }
+            ArbitrageProgramError::LossLimitExceeded => {
+                solana_program::msg!("Loss limit exceeded")
+            }
+            ArbitrageProgramError::InvalidShareAmount => {
+                solana_program::msg!("Invalid share amount")
+            }
+            ArbitrageProgramError::UnprofitableTrade => {
+                solana_program::msg!("Trade was unprofitable")
+            }
+            ArbitrageProgramError::DecimalsOutOfRange => {
+                solana_program::msg!("Decimals out of range")
+            }
         }
     }
 }
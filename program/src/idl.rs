@@ -0,0 +1,7 @@
+//! CPI builders for the Anchor programs we trade on, generated by `build.rs`
+//! from their IDLs in `app/src/idl`
+
+/// The Anchor swap program from the Solana Developers tutorial
+pub mod swap_program {
+    include!(concat!(env!("OUT_DIR"), "/swap_program.rs"));
+}
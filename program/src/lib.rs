@@ -1,10 +1,18 @@
 //! Arbitrage bot between two swap programs!
 mod arb;
+mod clmm;
 mod error;
+mod idl;
+mod order_book;
 mod partial_state;
 mod processor;
+mod stable_swap;
+mod state;
 mod swap;
+#[cfg(test)]
+mod test_utils;
 mod util;
+mod venue;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
@@ -12,8 +20,13 @@ use solana_program::{
     pubkey::Pubkey,
 };
 
+use crate::state::{
+    AccountSlot, ArgSlot, AssetCaps, Authority, ConfigChange, Curve, FeeModel, FeeSplit,
+    MintStatus, PoolSeed, RegisteredMint, StrategyParams, TemperatureBounds, TradingAs, Venue,
+    VenueAccountCheck, VenueKind, VenueLayout,
+};
+
 /// The program's instructions
-/// This program only takes one instruction
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub enum ArbitrageProgramInstruction {
     TryArbitrage {
@@ -31,27 +44,447 @@ pub enum ArbitrageProgramInstruction {
         /// trade
         temperature: u8,
     },
+    /// Creates the program's config, with the payer as its authority - only
+    /// the program's upgrade authority can do so
+    InitializeConfig,
+    /// Approves a swap program as a venue the bot may CPI into, once the
+    /// config's timelock has passed
+    AddVenue {
+        /// The program ID of the swap to approve
+        program_id: Pubkey,
+        /// The seeds the swap program derives its Liquidity Pools from
+        pool_seeds: Vec<PoolSeed>,
+        /// The swap's fee tier, in basis points of the amount paid
+        fee_bps: u16,
+        /// Which kind of swap program it is
+        kind: VenueKind,
+        /// The curve its pools price with
+        curve: Curve,
+    },
+    /// Removes a swap program from the approved venues, once the config's
+    /// timelock has passed
+    RemoveVenue {
+        /// The program ID of the swap to remove
+        program_id: Pubkey,
+    },
+    /// Sets or clears the global pause switch
+    SetPaused { paused: bool },
+    /// Trips or resets an approved venue's circuit breaker
+    SetVenueDisabled {
+        /// The program ID of the swap
+        program_id: Pubkey,
+        disabled: bool,
+    },
+    /// Creates the registry of tradable mints
+    InitializeMintRegistry,
+    /// Registers a mint as allowed or denied for trading - queued behind the
+    /// timelock if it loosens what can be traded
+    SetMint {
+        mint: Pubkey,
+        status: MintStatus,
+        /// Limits on how much of the asset can be traded
+        caps: AssetCaps,
+    },
+    /// Removes a mint from the registry, making it untradable
+    RemoveMint { mint: Pubkey },
+    /// Creates the payer's risk account, which tracks their realised profit
+    /// and loss
+    InitializeRiskAccount,
+    /// Sets how long each trader's loss window runs, once the config's
+    /// timelock has passed
+    SetLossWindow { loss_window_seconds: i64 },
+    /// Creates the shared account tracking each pair's last trade
+    InitializePairCooldowns,
+    /// Sets how many slots must pass before a traded pair can be traded
+    /// again, once the config's timelock has passed
+    SetPairCooldown { pair_cooldown_slots: u64 },
+    /// Creates the payer's stats account
+    InitializeStats {
+        /// Whether scans that find no arbitrage should be counted - which
+        /// means letting them succeed rather than fail preflight
+        count_scans: bool,
+    },
+    /// Emits a page of a trader's stats as logs and return data
+    EmitStats {
+        /// Which page to emit, from zero
+        page: u8,
+    },
+    /// Creates the payer's vault, which holds trading inventory the payer
+    /// owns but only the keeper needs to sign for trades from
+    InitializeVault {
+        /// The hot key allowed to trade the vault's inventory
+        keeper: Pubkey,
+    },
+    /// Replaces the hot key allowed to trade a vault's inventory
+    SetVaultKeeper { keeper: Pubkey },
+    /// Deposits tokens from the vault's owner into the vault
+    Deposit { amount: u64 },
+    /// Withdraws tokens from the vault to its owner
+    Withdraw { amount: u64 },
+    /// Same as `TryArbitrage`, but trades from a vault's inventory rather
+    /// than the payer's, signing for the swaps with the vault's PDA
+    TryVaultArbitrage {
+        swap_1_program_id: Pubkey,
+        swap_2_program_id: Pubkey,
+        concurrency: u8,
+        temperature: u8,
+    },
+    /// Creates the payer's pooled vault, which issues share tokens to anyone
+    /// depositing into it
+    InitializePooledVault {
+        /// The hot key allowed to trade the vault's inventory
+        keeper: Pubkey,
+        /// The venue whose pool prices the vault's assets in the quote asset
+        pricing_venue: Pubkey,
+    },
+    /// Adds an asset to a pooled vault's holdings
+    AddPooledAsset,
+    /// Deposits one of a pooled vault's assets in exchange for shares
+    DepositForShares { amount: u64 },
+    /// Burns shares of a pooled vault in exchange for a share of each of its
+    /// holdings
+    WithdrawShares { amount: u64 },
+    /// Sets how each round trip's profit is split off to the treasury and
+    /// the keeper, once the config's timelock has passed
+    SetFeeSplit { fee_split: FeeSplit },
+    /// Opens or closes a vault's inventory to be traded by anyone, in return
+    /// for the config's keeper fee
+    SetVaultPermissionless { permissionless: bool },
+    /// Same as `TryArbitrage`, but trades token accounts that have approved
+    /// the payer as their delegate, up to their delegated amounts
+    TryDelegatedArbitrage {
+        swap_1_program_id: Pubkey,
+        swap_2_program_id: Pubkey,
+        concurrency: u8,
+        temperature: u8,
+    },
+    /// Sets how many slots config changes must wait before they can be
+    /// applied, once the current timelock has passed
+    SetTimelock { timelock_slots: u64 },
+    /// Applies every queued config change whose timelock has passed
+    ApplyConfig,
+    /// Cancels a queued config change
+    CancelConfigChange {
+        /// The change's position in the queue
+        index: u8,
+    },
+    /// Offers to hand the config over to a new authority - a single key or an
+    /// M-of-N multisig
+    TransferAuthority { new_authority: Authority },
+    /// Accepts an offered authority transfer
+    AcceptAuthority,
+    /// Creates one of the payer's strategies
+    InitializeStrategy {
+        /// Distinguishes the payer's strategies from each other
+        id: u8,
+        params: StrategyParams,
+    },
+    /// Retunes a strategy
+    SetStrategy { params: StrategyParams },
+    /// Same as `TryArbitrage`, but with every parameter taken from the
+    /// payer's strategy
+    TryArbitrageWithStrategy,
+    /// Creates a trader's receipts account, for the payer or the payer's
+    /// vault
+    InitializeReceipts,
+    /// Closes a receipts account, reclaiming its rent
+    CloseReceipts,
+    /// Sets the limits within which adaptive temperatures move
+    SetTemperatureBounds {
+        temperature_bounds: TemperatureBounds,
+    },
+    /// Turns a trader's adaptive temperature on or off
+    SetAdaptiveTemperature {
+        /// The temperature to start adapting from - or `None` to go back to
+        /// the temperature each instruction provides
+        initial_temperature: Option<u8>,
+    },
+    /// Writes the layout a registered venue for a swap program is traded by,
+    /// which can't be traded until the timelock has passed
+    SetVenueLayout {
+        /// The program ID of the swap the layout describes
+        program_id: Pubkey,
+        /// The bytes its `swap` instruction data starts with
+        discriminator: Vec<u8>,
+        /// Its `swap` instruction's accounts, in order
+        accounts: Vec<AccountSlot>,
+        /// Its `swap` instruction's arguments, in order
+        args: Vec<ArgSlot>,
+        /// The further accounts its `swap` instruction needs, and what each
+        /// must be
+        venue_accounts: Vec<VenueAccountCheck>,
+        /// How it takes its fee
+        fee: FeeModel,
+        /// The curve its pools price with
+        curve: Curve,
+    },
+}
+
+impl ArbitrageProgramInstruction {
+    /// Whether this instruction attempts arbitrage, and is therefore subject
+    /// to the config's pause switch
+    fn is_trade(&self) -> bool {
+        matches!(
+            self,
+            Self::TryArbitrage { .. }
+                | Self::TryVaultArbitrage { .. }
+                | Self::TryDelegatedArbitrage { .. }
+                | Self::TryArbitrageWithStrategy
+        )
+    }
 }
 
 entrypoint!(process);
 
 /// Processor
-fn process(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    match ArbitrageProgramInstruction::try_from_slice(data) {
-        Ok(ix) => match ix {
-            ArbitrageProgramInstruction::TryArbitrage {
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let ix = match ArbitrageProgramInstruction::try_from_slice(data) {
+        Ok(ix) => ix,
+        Err(_) => return Err(ProgramError::InvalidInstructionData),
+    };
+    // Refuse to trade at all while the program is paused
+    if ix.is_trade() {
+        processor::assert_not_paused(program_id, accounts)?;
+    }
+    match ix {
+        ArbitrageProgramInstruction::TryArbitrage {
+            swap_1_program_id,
+            swap_2_program_id,
+            concurrency,
+            temperature,
+        } => processor::process_arbitrage(
+            program_id,
+            accounts,
+            Some(StrategyParams::unrestricted(
                 swap_1_program_id,
                 swap_2_program_id,
                 concurrency,
                 temperature,
-            } => processor::process_arbitrage(
+                TradingAs::Payer,
+            )),
+        ),
+        ArbitrageProgramInstruction::InitializeConfig => {
+            processor::config::process_initialize_config(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::AddVenue {
+            program_id: venue_program_id,
+            pool_seeds,
+            fee_bps,
+            kind,
+            curve,
+        } => processor::config::process_queue_config_change(
+            program_id,
+            accounts,
+            ConfigChange::AddVenue(Venue {
+                program_id: venue_program_id,
+                pool_seeds,
+                pool_bump: None,
+                fee_bps,
+                disabled: false,
+                kind,
+                curve,
+            }),
+        ),
+        ArbitrageProgramInstruction::RemoveVenue {
+            program_id: venue_program_id,
+        } => processor::config::process_queue_config_change(
+            program_id,
+            accounts,
+            ConfigChange::RemoveVenue(venue_program_id),
+        ),
+        ArbitrageProgramInstruction::SetPaused { paused } => {
+            processor::config::process_set_paused(program_id, accounts, paused)
+        }
+        ArbitrageProgramInstruction::SetVenueDisabled {
+            program_id: venue_program_id,
+            disabled,
+        } => processor::config::process_set_venue_disabled(
+            program_id,
+            accounts,
+            &venue_program_id,
+            disabled,
+        ),
+        ArbitrageProgramInstruction::InitializeMintRegistry => {
+            processor::mint_registry::process_initialize_mint_registry(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::SetMint { mint, status, caps } => {
+            processor::mint_registry::process_set_mint(
+                program_id,
                 accounts,
-                &swap_1_program_id,
-                &swap_2_program_id,
+                RegisteredMint { mint, status, caps },
+            )
+        }
+        ArbitrageProgramInstruction::RemoveMint { mint } => {
+            processor::mint_registry::process_remove_mint(program_id, accounts, &mint)
+        }
+        ArbitrageProgramInstruction::InitializeRiskAccount => {
+            processor::risk::process_initialize_risk_account(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::SetLossWindow {
+            loss_window_seconds,
+        } => processor::config::process_queue_config_change(
+            program_id,
+            accounts,
+            ConfigChange::SetLossWindow(loss_window_seconds),
+        ),
+        ArbitrageProgramInstruction::InitializePairCooldowns => {
+            processor::cooldown::process_initialize_pair_cooldowns(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::SetPairCooldown {
+            pair_cooldown_slots,
+        } => processor::config::process_queue_config_change(
+            program_id,
+            accounts,
+            ConfigChange::SetPairCooldown(pair_cooldown_slots),
+        ),
+        ArbitrageProgramInstruction::InitializeStats { count_scans } => {
+            processor::stats::process_initialize_stats(program_id, accounts, count_scans)
+        }
+        ArbitrageProgramInstruction::EmitStats { page } => {
+            processor::stats::process_emit_stats(program_id, accounts, page)
+        }
+        ArbitrageProgramInstruction::InitializeVault { keeper } => {
+            processor::vault::process_initialize_vault(program_id, accounts, keeper)
+        }
+        ArbitrageProgramInstruction::SetVaultKeeper { keeper } => {
+            processor::vault::process_set_vault_keeper(program_id, accounts, keeper)
+        }
+        ArbitrageProgramInstruction::Deposit { amount } => {
+            processor::vault::process_deposit(program_id, accounts, amount)
+        }
+        ArbitrageProgramInstruction::Withdraw { amount } => {
+            processor::vault::process_withdraw(program_id, accounts, amount)
+        }
+        ArbitrageProgramInstruction::TryVaultArbitrage {
+            swap_1_program_id,
+            swap_2_program_id,
+            concurrency,
+            temperature,
+        } => processor::process_arbitrage(
+            program_id,
+            accounts,
+            Some(StrategyParams::unrestricted(
+                swap_1_program_id,
+                swap_2_program_id,
                 concurrency,
                 temperature,
-            ),
-        },
-        Err(_) => Err(ProgramError::InvalidInstructionData),
+                TradingAs::Vault,
+            )),
+        ),
+        ArbitrageProgramInstruction::InitializePooledVault {
+            keeper,
+            pricing_venue,
+        } => processor::pooled_vault::process_initialize_pooled_vault(
+            program_id,
+            accounts,
+            keeper,
+            pricing_venue,
+        ),
+        ArbitrageProgramInstruction::AddPooledAsset => {
+            processor::pooled_vault::process_add_pooled_asset(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::DepositForShares { amount } => {
+            processor::pooled_vault::process_deposit_for_shares(program_id, accounts, amount)
+        }
+        ArbitrageProgramInstruction::WithdrawShares { amount } => {
+            processor::pooled_vault::process_withdraw_shares(program_id, accounts, amount)
+        }
+        ArbitrageProgramInstruction::SetFeeSplit { fee_split } => {
+            processor::config::process_queue_config_change(
+                program_id,
+                accounts,
+                ConfigChange::SetFeeSplit(fee_split),
+            )
+        }
+        ArbitrageProgramInstruction::SetVaultPermissionless { permissionless } => {
+            processor::vault::process_set_vault_permissionless(program_id, accounts, permissionless)
+        }
+        ArbitrageProgramInstruction::TryDelegatedArbitrage {
+            swap_1_program_id,
+            swap_2_program_id,
+            concurrency,
+            temperature,
+        } => processor::process_arbitrage(
+            program_id,
+            accounts,
+            Some(StrategyParams::unrestricted(
+                swap_1_program_id,
+                swap_2_program_id,
+                concurrency,
+                temperature,
+                TradingAs::Delegate,
+            )),
+        ),
+        ArbitrageProgramInstruction::SetTimelock { timelock_slots } => {
+            processor::config::process_queue_config_change(
+                program_id,
+                accounts,
+                ConfigChange::SetTimelock(timelock_slots),
+            )
+        }
+        ArbitrageProgramInstruction::ApplyConfig => {
+            processor::config::process_apply_config(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::CancelConfigChange { index } => {
+            processor::config::process_cancel_config_change(program_id, accounts, index)
+        }
+        ArbitrageProgramInstruction::TransferAuthority { new_authority } => {
+            processor::config::process_transfer_authority(program_id, accounts, new_authority)
+        }
+        ArbitrageProgramInstruction::AcceptAuthority => {
+            processor::config::process_accept_authority(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::InitializeStrategy { id, params } => {
+            processor::strategy::process_initialize_strategy(program_id, accounts, id, params)
+        }
+        ArbitrageProgramInstruction::SetStrategy { params } => {
+            processor::strategy::process_set_strategy(program_id, accounts, params)
+        }
+        ArbitrageProgramInstruction::TryArbitrageWithStrategy => {
+            processor::process_arbitrage(program_id, accounts, None)
+        }
+        ArbitrageProgramInstruction::InitializeReceipts => {
+            processor::receipts::process_initialize_receipts(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::CloseReceipts => {
+            processor::receipts::process_close_receipts(program_id, accounts)
+        }
+        ArbitrageProgramInstruction::SetTemperatureBounds { temperature_bounds } => {
+            processor::config::process_queue_config_change(
+                program_id,
+                accounts,
+                ConfigChange::SetTemperatureBounds(temperature_bounds),
+            )
+        }
+        ArbitrageProgramInstruction::SetAdaptiveTemperature {
+            initial_temperature,
+        } => processor::stats::process_set_adaptive_temperature(
+            program_id,
+            accounts,
+            initial_temperature,
+        ),
+        ArbitrageProgramInstruction::SetVenueLayout {
+            program_id: venue_program_id,
+            discriminator,
+            accounts: layout_accounts,
+            args,
+            venue_accounts,
+            fee,
+            curve,
+        } => processor::venue_layout::process_set_venue_layout(
+            program_id,
+            accounts,
+            VenueLayout {
+                program_id: venue_program_id,
+                bump: 0,
+                discriminator,
+                accounts: layout_accounts,
+                args,
+                venue_accounts,
+                fee,
+                curve,
+                effective_slot: 0,
+            },
+        ),
     }
 }
@@ -0,0 +1,135 @@
+//! Order-book quoting, walking the resting levels on one side of a book
+//!
+//! Orders are sized in whole lots of the base asset and priced in quote atoms
+//! per lot. A taker selling the base asset fills against the bids, highest
+//! first, and one buying it fills against the asks, lowest first - each up to
+//! the levels' quantities, with whatever can't fill a whole lot left unpaid
+
+/// A resting price level
+#[derive(Clone, Copy, Debug)]
+pub struct BookLevel {
+    /// Quote atoms per lot
+    pub price: u64,
+    /// Lots available at this price
+    pub quantity: u64,
+}
+
+/// The result of walking a book
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fill {
+    /// How much of the payment the book took
+    pub amount_paid: u64,
+    /// How much the payment returns, after the taker fee
+    pub amount_received: u64,
+}
+
+/// Fills a payment of `pay_amount` against the levels provided - which must be
+/// sorted best price first - taking the taker fee from what's received
+///
+/// Returns `None` if the math overflows
+pub fn fill(
+    levels: &[BookLevel],
+    base_lot_size: u64,
+    fee_bps: u16,
+    selling_base: bool,
+    pay_amount: u64,
+) -> Option<Fill> {
+    if base_lot_size == 0 {
+        return None;
+    }
+    let mut remaining = pay_amount;
+    let mut received = 0u64;
+    for level in levels {
+        // Whole lots only
+        let affordable = if selling_base {
+            remaining / base_lot_size
+        } else {
+            remaining / level.price.max(1)
+        };
+        let lots = affordable.min(level.quantity);
+        if lots == 0 {
+            break;
+        }
+        if selling_base {
+            remaining -= lots.checked_mul(base_lot_size)?;
+            received = received.checked_add(lots.checked_mul(level.price)?)?;
+        } else {
+            remaining -= lots.checked_mul(level.price)?;
+            received = received.checked_add(lots.checked_mul(base_lot_size)?)?;
+        }
+    }
+    let fee = received as u128 * fee_bps as u128 / 10_000;
+    Some(Fill {
+        amount_paid: pay_amount - remaining,
+        amount_received: received - fee as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(levels: &[(u64, u64)]) -> Vec<BookLevel> {
+        levels
+            .iter()
+            .map(|&(price, quantity)| BookLevel { price, quantity })
+            .collect()
+    }
+
+    #[test]
+    fn test_fill_buying_across_levels() {
+        // 2 lots at 100 then 3 at 110 spends all 530, for 5 lots of 10
+        assert_eq!(
+            fill(&levels(&[(100, 2), (110, 3), (120, 9)]), 10, 0, false, 530),
+            Some(Fill {
+                amount_paid: 530,
+                amount_received: 50,
+            })
+        );
+    }
+
+    #[test]
+    fn test_fill_selling_across_levels() {
+        // 1 lot into the 100 bid, then 3 into the 90 bid for 370, leaving 5
+        // that can't make a lot. The 30 bps fee on 370 floors to 1
+        assert_eq!(
+            fill(&levels(&[(100, 1), (90, 5)]), 10, 30, true, 45),
+            Some(Fill {
+                amount_paid: 40,
+                amount_received: 369,
+            })
+        );
+    }
+
+    #[test]
+    fn test_fill_partial() {
+        // The book runs out after 2 lots, so most of the payment stays unpaid
+        assert_eq!(
+            fill(&levels(&[(100, 2)]), 10, 0, false, 1_000),
+            Some(Fill {
+                amount_paid: 200,
+                amount_received: 20,
+            })
+        );
+        // Less than a lot's price fills nothing at all
+        assert_eq!(
+            fill(&levels(&[(100, 2)]), 10, 0, false, 99),
+            Some(Fill {
+                amount_paid: 0,
+                amount_received: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_fill_empty_slab() {
+        assert_eq!(
+            fill(&[], 10, 30, true, 1_000),
+            Some(Fill {
+                amount_paid: 0,
+                amount_received: 0,
+            })
+        );
+        assert_eq!(fill(&levels(&[(100, 2)]), 0, 0, true, 1_000), None);
+    }
+}
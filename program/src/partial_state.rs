@@ -1,17 +1,41 @@
 //! Bytemuck-powered zero-copy partial deserialization
 use bytemuck::{Pod, Zeroable};
 use solana_program::{account_info::AccountInfo, msg, program_error::ProgramError, pubkey::Pubkey};
-use spl_token_2022::pod::OptionalNonZeroPubkey;
+use spl_token_2022::pod::PodU64;
 
 use crate::error::ArbitrageProgramError;
 
-/// The first three fields of the `spl_token::state::Account`
+/// The fields of the `spl_token::state::Account` up to and including
+/// `delegated_amount`
+///
+/// Every integer is stored as a byte array - `PodU64` and the like - since
+/// Bytemuck won't allow the padding that mixing `u64`s with the single-byte
+/// `state` would need
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct PartialTokenAccountState {
     pub mint: Pubkey,
     pub owner: Pubkey,
-    pub amount: u64,
+    pub amount: PodU64,
+    pub delegate: PodCOptionPubkey,
+    pub state: u8,
+    pub is_native: [u8; 12],
+    pub delegated_amount: PodU64,
+}
+
+/// A `COption<Pubkey>` as laid out by the token program: a four-byte tag
+/// followed by the key
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct PodCOptionPubkey {
+    pub tag: [u8; 4],
+    pub key: Pubkey,
+}
+
+impl PodCOptionPubkey {
+    pub fn get(&self) -> Option<&Pubkey> {
+        (self.tag != [0; 4]).then_some(&self.key)
+    }
 }
 
 /// Custom return type for our arbitrage algorithm:
@@ -19,6 +43,8 @@ pub struct PartialTokenAccountState {
 pub type ArbitrageTokenAccountInfo<'a, 'b> = (&'a AccountInfo<'b>, Pubkey, Pubkey, u64);
 
 impl PartialTokenAccountState {
+    const LEN: usize = std::mem::size_of::<Self>();
+
     /// Attempts to use zero-copy deserialization via Bytemuck to determine if
     /// this account is in fact an associated token account
     ///
@@ -27,36 +53,58 @@ impl PartialTokenAccountState {
     /// for the rest of the arbitrage program
     pub fn try_deserialize<'a, 'b>(
         account_info: &'a AccountInfo<'b>,
-        owner: &'a Pubkey,
+        owner: &Pubkey,
     ) -> Result<ArbitrageTokenAccountInfo<'a, 'b>, ProgramError> {
+        let partial_token = Self::try_deserialize_state(account_info)?;
+        // Validate the owner
+        if !partial_token.owner.eq(owner) {
+            msg!("Owner mismatch");
+            msg!("Expected: {}", owner);
+            msg!("Got:      {}", partial_token.owner);
+            msg!("Token Account: {}", account_info.key);
+            return Err(ArbitrageProgramError::InvalidAccountsList.into());
+        }
+        // Return the vital information
+        Ok(partial_token.vital_info(account_info))
+    }
+
+    /// Same as `try_deserialize`, but validates the token account's approved
+    /// delegate - rather than its owner - against the provided address
+    ///
+    /// Also returns how much of the account the delegate may still spend
+    pub fn try_deserialize_delegated<'a, 'b>(
+        account_info: &'a AccountInfo<'b>,
+        delegate: &Pubkey,
+    ) -> Result<(ArbitrageTokenAccountInfo<'a, 'b>, u64), ProgramError> {
+        let partial_token = Self::try_deserialize_state(account_info)?;
+        // Validate the delegate
+        if !partial_token.delegate.get().eq(&Some(delegate)) {
+            msg!("Delegate mismatch");
+            msg!("Expected: {}", delegate);
+            msg!("Token Account: {}", account_info.key);
+            return Err(ArbitrageProgramError::InvalidAccountsList.into());
+        }
+        Ok((
+            partial_token.vital_info(account_info),
+            partial_token.delegated_amount.into(),
+        ))
+    }
+
+    /// Reads the partial state out of the account's data
+    fn try_deserialize_state(account_info: &AccountInfo) -> Result<Self, ProgramError> {
         // Check that the account has enough data to try to deserialize
-        if account_info.data_len() < 72 {
+        if account_info.data_len() < Self::LEN {
             msg!(
-                "Data too small. Should be 72. Found len: {}",
+                "Data too small. Should be {}. Found len: {}",
+                Self::LEN,
                 account_info.data_len()
             );
             msg!("Token Account: {}", account_info.key);
             return Err(ArbitrageProgramError::InvalidAccountsList.into());
         }
         // Try to partially deserialize the account data
-        match bytemuck::try_from_bytes::<Self>(&account_info.data.borrow()[..72]) {
-            // Validate the owner
-            Ok(partial_token) => {
-                if !partial_token.owner.eq(owner) {
-                    msg!("Owner mismatch");
-                    msg!("Expected: {}", owner);
-                    msg!("Got:      {}", partial_token.owner);
-                    msg!("Token Account: {}", account_info.key);
-                    return Err(ArbitrageProgramError::InvalidAccountsList.into());
-                }
-                // Return the vital information
-                Ok((
-                    account_info,
-                    partial_token.mint,
-                    partial_token.owner,
-                    partial_token.amount,
-                ))
-            }
+        match bytemuck::try_from_bytes::<Self>(&account_info.data.borrow()[..Self::LEN]) {
+            Ok(partial_token) => Ok(*partial_token),
             Err(_) => {
                 msg!("Failed to deserialize associated token account");
                 msg!("Token Account: {}", account_info.key);
@@ -64,6 +112,15 @@ impl PartialTokenAccountState {
             }
         }
     }
+
+    /// Returns only the vital information we need for the rest of the
+    /// arbitrage program
+    fn vital_info<'a, 'b>(
+        &self,
+        account_info: &'a AccountInfo<'b>,
+    ) -> ArbitrageTokenAccountInfo<'a, 'b> {
+        (account_info, self.mint, self.owner, self.amount.into())
+    }
 }
 
 /// The first two fields of the `spl_token::state::Mint`
@@ -77,8 +134,8 @@ impl PartialTokenAccountState {
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct PartialMintState {
-    pub mint_authority: OptionalNonZeroPubkey,
-    pub supply: u64,
+    pub mint_authority: PodCOptionPubkey,
+    pub supply: PodU64,
 }
 
 /// Custom return type for our arbitrage algorithm:
@@ -95,19 +152,19 @@ impl PartialMintState {
         account_info: &'a AccountInfo<'b>,
     ) -> Result<ArbitrageMintInfo<'a, 'b>, ProgramError> {
         // Check that the account has enough data to try to deserialize
-        if account_info.data_len() < 41 {
+        if account_info.data_len() < 45 {
             msg!(
-                "Data too small. Should be 41. Found len: {}",
+                "Data too small. Should be 45. Found len: {}",
                 account_info.data_len()
             );
             msg!("Mint: {}", account_info.key);
             return Err(ArbitrageProgramError::InvalidAccountsList.into());
         }
-        let data = &account_info.data.borrow()[..41];
+        let data = &account_info.data.borrow()[..45];
         // Try to partially deserialize the account data
-        match bytemuck::try_from_bytes::<Self>(&data[..40]) {
+        match bytemuck::try_from_bytes::<Self>(&data[..44]) {
             Ok(_) => {
-                let decimals = match data.get(40) {
+                let decimals = match data.get(44) {
                     Some(d) => d,
                     None => {
                         msg!("Could not get decimals");
@@ -125,4 +182,108 @@ impl PartialMintState {
             }
         }
     }
+
+    /// Reads a mint's current supply
+    pub fn try_deserialize_supply(account_info: &AccountInfo) -> Result<u64, ProgramError> {
+        let data = account_info.data.borrow();
+        match data
+            .get(..44)
+            .and_then(|data| bytemuck::try_from_bytes::<Self>(data).ok())
+        {
+            Some(partial_mint) => Ok(partial_mint.supply.into()),
+            None => {
+                msg!("Failed to deserialize mint account");
+                msg!("Mint: {}", account_info.key);
+                Err(ArbitrageProgramError::InvalidAccountsList.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{program_option::COption, program_pack::Pack};
+    use spl_token::state::{Account, AccountState, Mint};
+
+    use super::*;
+
+    #[test]
+    fn test_try_deserialize_delegated() {
+        let (key, mint, owner, delegate) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut data = vec![0; Account::LEN];
+        Account::pack(
+            Account {
+                mint,
+                owner,
+                amount: 1_000,
+                delegate: COption::Some(delegate),
+                state: AccountState::Initialized,
+                delegated_amount: 400,
+                ..Account::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        let mut lamports = 0;
+        let token_program = spl_token::id();
+        let info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &token_program,
+            false,
+            0,
+        );
+        let ((_, read_mint, read_owner, amount), allowance) =
+            PartialTokenAccountState::try_deserialize_delegated(&info, &delegate).unwrap();
+        assert_eq!(
+            (read_mint, read_owner, amount, allowance),
+            (mint, owner, 1_000, 400)
+        );
+        assert!(PartialTokenAccountState::try_deserialize_delegated(&info, &owner).is_err());
+        assert!(PartialTokenAccountState::try_deserialize(&info, &owner).is_ok());
+    }
+
+    #[test]
+    fn test_try_deserialize_mint() {
+        let key = Pubkey::new_unique();
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(
+            Mint {
+                mint_authority: COption::Some(Pubkey::new_unique()),
+                supply: 0x0102_0304_0506_0708,
+                decimals: 9,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            &mut data,
+        )
+        .unwrap();
+        let mut lamports = 0;
+        let token_program = spl_token::id();
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &token_program,
+            false,
+            0,
+        );
+        // The decimals follow the four-byte `COption` tag, the authority and
+        // the supply - none of whose bytes may be mistaken for them
+        assert_eq!(PartialMintState::try_deserialize(&info).unwrap().1, 9);
+        assert_eq!(
+            PartialMintState::try_deserialize_supply(&info),
+            Ok(0x0102_0304_0506_0708)
+        );
+    }
 }
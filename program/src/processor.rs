@@ -1,13 +1,50 @@
 //! Processes an attempt to arbitrage trade
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
+    account_info::{next_account_info, next_account_infos, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
     pubkey::Pubkey,
+    sysvar::Sysvar,
 };
 
 use crate::arb::{try_arbitrage, TryArbitrageArgs};
-use crate::partial_state::{PartialMintState, PartialTokenAccountState};
-use crate::util::check_pool_address;
+use crate::error::ArbitrageProgramError;
+use crate::partial_state::{ArbitrageTokenAccountInfo, PartialMintState, PartialTokenAccountState};
+use crate::state::{
+    pair_key, ArbitrageAccount, ArbitrageConfig, MintRegistry, PairCooldowns, RiskAccount,
+    Strategy, StrategyParams, TradeReceipts, TradeStats, TradingAs, Vault, Venue, VenueKind,
+    VenueLayout,
+};
+use crate::util::{assert_token_program, retain_by_mask, ArbitrageEvaluateOption};
+use crate::venue;
+
+pub mod config;
+pub mod cooldown;
+pub mod mint_registry;
+pub mod pooled_vault;
+pub mod receipts;
+pub mod risk;
+pub mod stats;
+pub mod strategy;
+pub mod vault;
+pub mod venue_layout;
+
+/// The position of the config in the accounts list of `process_arbitrage`
+pub const ARBITRAGE_CONFIG_ACCOUNT_INDEX: usize = 8;
+
+/// Asserts the config's global pause switch is off before any arbitrage is
+/// attempted
+///
+/// Reads the config from its fixed position in `process_arbitrage`'s
+/// accounts list
+pub fn assert_not_paused(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let config_info = accounts
+        .get(ARBITRAGE_CONFIG_ACCOUNT_INDEX)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    ArbitrageConfig::load(program_id, config_info)?.assert_not_paused()
+}
 
 /// Processes program inputs to search for an arbitrage opportunity between two
 /// swap programs
@@ -15,18 +52,31 @@ use crate::util::check_pool_address;
 /// Note: accounts must be provided in a very specific order:
 /// * Payer
 /// * Token Program
+/// * System Program
+/// * Associated Token Program
+/// * Swap #1 Program
+/// * Swap #2 Program
 /// * Swap #1 Liquidity Pool
 /// * Swap #2 Liquidity Pool
-/// * [Token Accounts for User]
+/// * Config
+/// * Mint Registry
+/// * Risk Account (Trader's)
+/// * Pair Cooldowns
+/// * Stats (Trader's)
+/// * Strategy (Only if no parameters are given - see [`strategy`])
+/// * Vault (Only if trading from a vault - see [`vault`])
+/// * Venue Layouts for Swaps #1 and #2 (If registered - see [`venue_layout`])
+/// * [Token Accounts for Trader]
 /// * [Token Accounts for Swap #1]
 /// * [Token Accounts for Swap #2]
 /// * [Mint Accounts]
+/// * [Venue Accounts for Each Swap] (Only if needed - see [`venue`])
+/// * [Token Accounts for Treasury, then Keeper] (See [`config`]'s fee split)
+/// * Receipts (Optional - see [`receipts`])
 pub fn process_arbitrage(
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
-    swap_1_program_id: &Pubkey,
-    swap_2_program_id: &Pubkey,
-    concurrency: u8,
-    temperature: u8,
+    params: Option<StrategyParams>,
 ) -> ProgramResult {
     // Load the first few "fixed" accounts provided
     let accounts_iter = &mut accounts.iter();
@@ -38,22 +88,139 @@ pub fn process_arbitrage(
     let swap_2_program = next_account_info(accounts_iter)?;
     let swap_1_pool = next_account_info(accounts_iter)?;
     let swap_2_pool = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let mint_registry_info = next_account_info(accounts_iter)?;
+    let risk_info = next_account_info(accounts_iter)?;
+    let cooldowns_info = next_account_info(accounts_iter)?;
+    let stats_info = next_account_info(accounts_iter)?;
+
+    // Trade as the payer's strategy says to, if no parameters were given
+    let params = match params {
+        Some(params) => params,
+        None => {
+            let strategy = Strategy::load(program_id, next_account_info(accounts_iter)?)?;
+            strategy.assert_owner(payer)?;
+            strategy.params
+        }
+    };
+    let swap_1_program_id = &params.swap_1_program_id;
+    let swap_2_program_id = &params.swap_2_program_id;
+    let concurrency = params.concurrency;
+
+    // Trade from the vault's inventory, if asked to, as long as the payer is
+    // allowed to trade it
+    let vault = if let TradingAs::Vault = params.trading_as {
+        let vault_info = next_account_info(accounts_iter)?;
+        let vault = Vault::load(program_id, vault_info)?;
+        vault.assert_can_trade(payer)?;
+        assert_token_program(token_program)?;
+        Some((vault_info, vault))
+    } else {
+        None
+    };
+    let (authority, trader) = match &vault {
+        Some((vault_info, _)) => (*vault_info, vault_info.key),
+        None => (payer, payer.key),
+    };
+    let vault_bump;
+    let vault_seeds;
+    let authority_seeds = match &vault {
+        Some((_, vault)) => {
+            vault_bump = [vault.bump];
+            vault_seeds = [Vault::SEED_PREFIX, vault.owner.as_ref(), &vault_bump];
+            Some(&vault_seeds[..])
+        }
+        None => None,
+    };
+
+    // Refuse to CPI into any swap program that isn't an approved venue
+    let config = ArbitrageConfig::load(program_id, config_info)?;
+    let swap_1_venue = config.venue(swap_1_program_id)?;
+    let swap_2_venue = config.venue(swap_2_program_id)?;
+    if !swap_1_program.key.eq(swap_1_program_id) || !swap_2_program.key.eq(swap_2_program_id) {
+        msg!("Swap program accounts do not match the provided program IDs");
+        return Err(ArbitrageProgramError::InvalidAccountsList.into());
+    }
 
-    // Ensure each pool address follows the correct derivation from its
-    // corresponding program ID
-    check_pool_address(swap_1_program_id, swap_1_pool.key)?;
-    check_pool_address(swap_2_program_id, swap_2_pool.key)?;
+    // Read the layouts of any registered venues
+    let swap_1_layout = next_venue_layout(program_id, swap_1_venue, accounts_iter)?;
+    let swap_2_layout = next_venue_layout(program_id, swap_2_venue, accounts_iter)?;
 
-    // Read the provided user's token accounts
-    let token_accounts_user = {
+    // Leave venues that charge more than the strategy will pay alone
+    for (venue, layout) in [
+        (swap_1_venue, &swap_1_layout),
+        (swap_2_venue, &swap_2_layout),
+    ] {
+        let fee_bps = layout.as_ref().map_or(venue.fee_bps, |l| l.fee.bps());
+        if fee_bps > params.max_venue_fee_bps {
+            msg!(
+                "Venue {} charges more than the strategy allows",
+                venue.program_id
+            );
+            return Err(ArbitrageProgramError::NoArbitrage.into());
+        }
+    }
+
+    // Ensure each pool is one its venue actually trades against - both legs
+    // can trade on the same swap program, but not in the same pool
+    if swap_1_pool.key.eq(swap_2_pool.key) {
+        msg!(
+            "Both swaps cannot trade in the same pool: {}",
+            swap_1_pool.key
+        );
+        return Err(ArbitrageProgramError::InvalidAccountsList.into());
+    }
+    let venue_1 = venue::adapter(swap_1_venue, swap_1_layout.as_ref())?;
+    let venue_2 = venue::adapter(swap_2_venue, swap_2_layout.as_ref())?;
+    venue_1.check_pool(swap_1_pool)?;
+    venue_2.check_pool(swap_2_pool)?;
+    let swap_1_pool_token_owner = venue_1.pool_token_owner(swap_1_pool)?;
+    let swap_2_pool_token_owner = venue_2.pool_token_owner(swap_2_pool)?;
+
+    // Refuse to trade once the trader's losses within the current loss window
+    // exceed the limit for any asset
+    let registry = MintRegistry::load(program_id, mint_registry_info)?;
+    let mut risk = RiskAccount::load(program_id, risk_info)?;
+    risk.assert_trader(trader)?;
+    let clock = Clock::get()?;
+    risk.roll_window(clock.unix_timestamp, config.loss_window_seconds);
+    risk.assert_within_loss_limits(&registry)?;
+    let mut cooldowns = PairCooldowns::load(program_id, cooldowns_info)?;
+    let mut stats = TradeStats::load(program_id, stats_info)?;
+    stats.assert_trader(trader)?;
+
+    // Read the provided trader's token accounts - or, if trading as a
+    // delegate, the token accounts the payer has been approved to spend from,
+    // along with how much it may spend
+    let (token_accounts_user, allowances) = if let TradingAs::Delegate = params.trading_as {
+        let mut accts: Vec<ArbitrageTokenAccountInfo> = vec![];
+        let mut allowances = vec![];
+        for _x in 0..concurrency {
+            let (acct, allowance) = PartialTokenAccountState::try_deserialize_delegated(
+                next_account_info(accounts_iter)?,
+                payer.key,
+            )?;
+            // Never move funds between different owners' accounts
+            if let Some(first) = accts.first() {
+                if !acct.2.eq(&first.2) {
+                    msg!("Delegated token accounts must share an owner");
+                    msg!("Token Account: {}", acct.0.key);
+                    return Err(ArbitrageProgramError::InvalidAccountsList.into());
+                }
+            }
+            accts.push(acct);
+            allowances.push(allowance);
+        }
+        (accts, Some(allowances))
+    } else {
         let mut accts = vec![];
         for _x in 0..concurrency {
             accts.push(PartialTokenAccountState::try_deserialize(
                 next_account_info(accounts_iter)?,
-                payer.key,
+                trader,
             )?);
         }
-        accts
+        (accts, None)
     };
 
     // Read the provided token accounts for Swap Program #1
@@ -62,7 +229,7 @@ pub fn process_arbitrage(
         for _x in 0..concurrency {
             accts.push(PartialTokenAccountState::try_deserialize(
                 next_account_info(accounts_iter)?,
-                swap_1_pool.key,
+                &swap_1_pool_token_owner,
             )?);
         }
         accts
@@ -74,7 +241,7 @@ pub fn process_arbitrage(
         for _x in 0..concurrency {
             accts.push(PartialTokenAccountState::try_deserialize(
                 next_account_info(accounts_iter)?,
-                swap_2_pool.key,
+                &swap_2_pool_token_owner,
             )?);
         }
         accts
@@ -91,14 +258,115 @@ pub fn process_arbitrage(
         accts
     };
 
+    // Read any further accounts each venue's swap needs
+    let swap_1_venue_accounts = next_account_infos(accounts_iter, venue_1.venue_accounts_len())?;
+    venue_1.check_venue_accounts(swap_1_pool, swap_1_venue_accounts)?;
+    let swap_2_venue_accounts = next_account_infos(accounts_iter, venue_2.venue_accounts_len())?;
+    venue_2.check_venue_accounts(swap_2_pool, swap_2_venue_accounts)?;
+
+    // Read the token accounts any profit is swept into, if the config takes
+    // a cut of it
+    let token_accounts_treasury = if config.fee_split.treasury_bps > 0 {
+        let mut accts = vec![];
+        for _x in 0..concurrency {
+            accts.push(PartialTokenAccountState::try_deserialize(
+                next_account_info(accounts_iter)?,
+                &config.fee_split.treasury,
+            )?);
+        }
+        Some(accts)
+    } else {
+        None
+    };
+    let token_accounts_keeper = if config.fee_split.keeper_bps > 0 && vault.is_some() {
+        let mut accts = vec![];
+        for _x in 0..concurrency {
+            accts.push(PartialTokenAccountState::try_deserialize(
+                next_account_info(accounts_iter)?,
+                payer.key,
+            )?);
+        }
+        Some(accts)
+    } else {
+        None
+    };
+    if token_accounts_treasury.is_some() || token_accounts_keeper.is_some() {
+        assert_token_program(token_program)?;
+    }
+
+    // Keep receipts of the trade, if the trader's receipts account was passed
+    let receipts = match next_account_info(accounts_iter) {
+        Ok(receipts_info) => {
+            let receipts = TradeReceipts::load(program_id, receipts_info)?;
+            receipts.assert_trader(trader)?;
+            Some((receipts_info, receipts))
+        }
+        Err(_) => None,
+    };
+
+    // Drop any asset the mint registry doesn't approve - along with its token
+    // accounts, so every list stays aligned - before evaluating any pairs
+    let mut tradable = vec![];
+    let mut mint_caps = vec![];
+    for (i, mint) in mints.iter().enumerate() {
+        for token_accounts in [
+            &token_accounts_user,
+            &token_accounts_swap_1,
+            &token_accounts_swap_2,
+        ]
+        .into_iter()
+        .chain(token_accounts_treasury.as_ref())
+        .chain(token_accounts_keeper.as_ref())
+        {
+            let token_account = token_accounts.get(i).ok_or_arb_err()?;
+            if !token_account.1.eq(mint.0.key) {
+                msg!("Mint mismatch");
+                msg!("Expected: {}", mint.0.key);
+                msg!("Got:      {}", token_account.1);
+                msg!("Token Account: {}", token_account.0.key);
+                return Err(ArbitrageProgramError::InvalidAccountsList.into());
+            }
+        }
+        // A pooled vault only trades the assets its shares are priced on
+        if let Some((_, vault)) = &vault {
+            if !vault.holds(mint.0.key) {
+                msg!("Skipping mint not held by the vault: {}", mint.0.key);
+                tradable.push(false);
+                continue;
+            }
+        }
+        match registry.tradable(mint.0.key) {
+            Some(caps) => {
+                tradable.push(true);
+                mint_caps.push(caps);
+            }
+            None => {
+                msg!("Skipping unapproved mint: {}", mint.0.key);
+                tradable.push(false);
+            }
+        }
+    }
+    let token_accounts_user = retain_by_mask(token_accounts_user, &tradable);
+    let allowances = allowances.map(|a| retain_by_mask(a, &tradable));
+    let token_accounts_swap_1 = retain_by_mask(token_accounts_swap_1, &tradable);
+    let token_accounts_swap_2 = retain_by_mask(token_accounts_swap_2, &tradable);
+    let token_accounts_treasury = token_accounts_treasury.map(|a| retain_by_mask(a, &tradable));
+    let token_accounts_keeper = token_accounts_keeper.map(|a| retain_by_mask(a, &tradable));
+    let mints = retain_by_mask(mints, &tradable);
+
     // Check if there is an arbitrage opportunity between the two pools, and
     // execute the trade if there is one
-    try_arbitrage(TryArbitrageArgs {
+    let outcome = match try_arbitrage(TryArbitrageArgs {
         token_accounts_user,
         token_accounts_swap_1,
         token_accounts_swap_2,
+        token_accounts_treasury,
+        token_accounts_keeper,
+        allowances,
         mints,
-        payer,
+        mint_caps,
+        authority,
+        authority_seeds,
         token_program,
         system_program,
         associated_token_program,
@@ -106,6 +374,390 @@ pub fn process_arbitrage(
         swap_2_program,
         swap_1_pool,
         swap_2_pool,
-        temperature,
-    })
+        swap_1_venue,
+        swap_2_venue,
+        swap_1_layout: swap_1_layout.as_ref(),
+        swap_2_layout: swap_2_layout.as_ref(),
+        swap_1_venue_accounts,
+        swap_2_venue_accounts,
+        fee_split: &config.fee_split,
+        cooldowns: &cooldowns,
+        cooldown_slots: config.pair_cooldown_slots,
+        slot: clock.slot,
+        strategy: &params,
+        temperature: stats.temperature(params.temperature, &config.temperature_bounds),
+    }) {
+        Ok(outcome) => outcome,
+        // Count the empty scan if we've been asked to - which means letting
+        // the instruction succeed so the count isn't rolled back
+        Err(e) if stats.count_scans && e == ArbitrageProgramError::NoArbitrage.into() => {
+            stats.no_arbitrage_scans = stats.no_arbitrage_scans.saturating_add(1);
+            return stats.save(stats_info);
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Don't keep trades that made less than the strategy is after
+    if !params.is_profitable_enough(outcome.amount_in, outcome.profit) {
+        msg!(
+            "Profit of {} on {} is below the strategy's minimum",
+            outcome.profit,
+            outcome.amount_in
+        );
+        return Err(ArbitrageProgramError::UnprofitableTrade.into());
+    }
+
+    // Never let a keeper trading a permissionless vault lose it money
+    if let Some((_, vault)) = &vault {
+        if vault.permissionless && outcome.profit < 0 {
+            msg!("Permissionless trade lost {}", -outcome.profit);
+            return Err(ArbitrageProgramError::UnprofitableTrade.into());
+        }
+    }
+
+    // Record the round trip's realised profit or loss, start the pair's
+    // cooldown and update the trader's stats
+    let pair = pair_key(
+        &outcome.mint,
+        &outcome.received_mint,
+        swap_1_pool.key,
+        swap_2_pool.key,
+    );
+    risk.record(&outcome.mint, outcome.profit)?;
+    risk.save(risk_info)?;
+    cooldowns.record(pair, clock.slot);
+    cooldowns.save(cooldowns_info)?;
+    stats.record_trade(&outcome, pair, clock.slot)?;
+    if let Some(adaptive) = stats.adaptive_temperature.as_mut() {
+        adaptive.record(outcome.profit, &config.temperature_bounds);
+    }
+    stats.save(stats_info)?;
+    if let Some((receipts_info, mut receipts)) = receipts {
+        receipts.record(&outcome, clock.slot, clock.unix_timestamp);
+        receipts.save(receipts_info)?;
+    }
+    Ok(())
+}
+
+/// Reads the next account as a venue's layout, if the venue is registered,
+/// refusing a layout still waiting out the timelock
+fn next_venue_layout<'a, 'b: 'a>(
+    program_id: &Pubkey,
+    venue: &Venue,
+    accounts_iter: &mut impl Iterator<Item = &'a AccountInfo<'b>>,
+) -> Result<Option<VenueLayout>, ProgramError> {
+    if venue.kind != VenueKind::Registered {
+        return Ok(None);
+    }
+    let layout_info = next_account_info(accounts_iter)?;
+    let (layout_address, _) = VenueLayout::address(program_id, &venue.program_id);
+    if !layout_info.key.eq(&layout_address) {
+        msg!("Expected venue layout address: {}", layout_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    let layout = VenueLayout::load(program_id, layout_info)?;
+    if layout.effective_slot > Clock::get()?.slot {
+        msg!(
+            "Venue layout for {} can't be traded until slot {}",
+            venue.program_id,
+            layout.effective_slot
+        );
+        return Err(ArbitrageProgramError::VenueNotAllowed.into());
+    }
+    Ok(Some(layout))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{program_option::COption, pubkey::Pubkey, system_program};
+    use spl_associated_token_account::get_associated_token_address;
+
+    use crate::error::ArbitrageProgramError;
+    use crate::state::{
+        pair_key, AssetCaps, FeeSplit, PairCooldowns, RiskAccount, Strategy, StrategyParams,
+        TradeStats, TradingAs, DEFAULT_LOSS_WINDOW_SECONDS,
+    };
+    use crate::test_utils::{cpis, readonly, set_clock, signer, writable, Market};
+    use crate::ArbitrageProgramInstruction;
+
+    /// The amounts paid into each swap, in the order the swaps were made
+    fn swap_amounts(market: &Market) -> Vec<u64> {
+        cpis()
+            .iter()
+            .filter(|ix| market.swaps.contains(&ix.program_id))
+            .map(|ix| u64::from_le_bytes(ix.data[8..16].try_into().unwrap()))
+            .collect()
+    }
+
+    /// Creates a payer's strategy with the parameters given, returning its
+    /// address
+    fn initialize_strategy(market: &mut Market, params: StrategyParams) -> Pubkey {
+        let (strategy, _) = Strategy::address(&market.program_id, &market.payer, 0);
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &[
+                    signer(market.payer),
+                    writable(strategy),
+                    readonly(system_program::id()),
+                ],
+                &ArbitrageProgramInstruction::InitializeStrategy { id: 0, params },
+            )
+            .unwrap();
+        strategy
+    }
+
+    #[test]
+    fn test_strategy_trade_size_within_caps() {
+        let mut market = Market::new();
+        market.set_mint(
+            market.mints[0],
+            AssetCaps {
+                reserve: 95_000_000,
+                ..AssetCaps::default()
+            },
+        );
+        let params = StrategyParams {
+            trade_size_bps: 5_000,
+            ..market.params(TradingAs::Payer)
+        };
+        let strategy = initialize_strategy(&mut market, params);
+        let accounts = market.trade_accounts_for(&market.payer, &[readonly(strategy)]);
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &accounts,
+                &ArbitrageProgramInstruction::TryArbitrageWithStrategy,
+            )
+            .unwrap();
+        // The reserve leaves 5,000,000 of the balance to trade, which is less
+        // than the strategy's half of it
+        assert_eq!(swap_amounts(&market)[0], 5_000_000);
+        assert!(market.balance(&market.payer, 0) > Market::BALANCE);
+    }
+
+    #[test]
+    fn test_delegated_trade_within_allowance() {
+        let mut market = Market::new();
+        let owner = market.bank.add_wallet();
+        let mints = market.mints;
+        let token_accounts = mints.map(|mint| market.bank.add_ata(&owner, &mint, Market::BALANCE));
+        // Only part of each balance is approved
+        market
+            .bank
+            .approve(&token_accounts[0], &market.payer, 3_000_000);
+        market
+            .bank
+            .approve(&token_accounts[1], &market.payer, 5_000_000);
+        let mut accounts = market.trade_accounts();
+        accounts[13] = writable(token_accounts[0]);
+        accounts[14] = writable(token_accounts[1]);
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &accounts,
+                &ArbitrageProgramInstruction::TryDelegatedArbitrage {
+                    swap_1_program_id: market.swaps[0],
+                    swap_2_program_id: market.swaps[1],
+                    concurrency: 2,
+                    temperature: 90,
+                },
+            )
+            .unwrap();
+        // The whole allowance - and no more of the balance - is paid in, and
+        // what the buy leg returns is sold back out of the second allowance
+        let amounts = swap_amounts(&market);
+        assert_eq!(amounts[0], 3_000_000);
+        let first = market.bank.token_account(&token_accounts[0]);
+        let second = market.bank.token_account(&token_accounts[1]);
+        assert_eq!(first.delegated_amount, 0);
+        assert_eq!(second.delegated_amount, 5_000_000 - amounts[1]);
+        assert_eq!(second.delegate, COption::Some(market.payer));
+        assert!(first.amount > Market::BALANCE);
+        assert_eq!(second.amount, Market::BALANCE);
+        // The payer's own balances are untouched
+        for mint in mints {
+            assert_eq!(
+                market
+                    .bank
+                    .balance(&get_associated_token_address(&market.payer, &mint)),
+                Market::BALANCE
+            );
+        }
+    }
+
+    #[test]
+    fn test_cooldown_keyed_by_pool() {
+        let mut market = Market::on_one_program();
+        assert_eq!(market.swaps[0], market.swaps[1]);
+        market
+            .config(ArbitrageProgramInstruction::SetPairCooldown {
+                pair_cooldown_slots: 10,
+            })
+            .unwrap();
+        let accounts = market.trade_accounts();
+        let ix = market.try_arbitrage(90);
+        market
+            .bank
+            .process(&market.program_id, &accounts, &ix)
+            .unwrap();
+        let pair = pair_key(
+            &market.mints[0],
+            &market.mints[1],
+            &market.pools[0],
+            &market.pools[1],
+        );
+        let cooldowns: PairCooldowns = market.bank.load(
+            &market.program_id,
+            &PairCooldowns::address(&market.program_id).0,
+        );
+        assert_eq!(cooldowns.pairs.len(), 1);
+        assert_eq!(cooldowns.pairs[0].key, pair);
+        let stats: TradeStats = market.bank.load(
+            &market.program_id,
+            &TradeStats::address(&market.program_id, &market.payer).0,
+        );
+        assert_eq!(stats.pairs[0].key, pair);
+        // The same two pools are cooling down - and the spread's still there
+        assert_eq!(
+            market.bank.process(&market.program_id, &accounts, &ix),
+            Err(ArbitrageProgramError::NoArbitrage.into())
+        );
+    }
+
+    #[test]
+    fn test_paused_blocks_arbitrage() {
+        let mut market = Market::new();
+        market
+            .config(ArbitrageProgramInstruction::SetPaused { paused: true })
+            .unwrap();
+        let accounts = market.trade_accounts();
+        let ix = market.try_arbitrage(90);
+        assert_eq!(
+            market.bank.process(&market.program_id, &accounts, &ix),
+            Err(ArbitrageProgramError::Paused.into())
+        );
+        assert!(swap_amounts(&market).is_empty());
+        assert_eq!(market.balance(&market.payer, 0), Market::BALANCE);
+
+        // Lifting the pause lets the same spread be traded
+        market
+            .config(ArbitrageProgramInstruction::SetPaused { paused: false })
+            .unwrap();
+        market
+            .bank
+            .process(&market.program_id, &accounts, &ix)
+            .unwrap();
+        assert_eq!(swap_amounts(&market).len(), 2);
+    }
+
+    #[test]
+    fn test_treasury_fee_swept_through_token_program() {
+        let mut market = Market::new();
+        let treasury = market.bank.add_wallet();
+        market
+            .config(ArbitrageProgramInstruction::SetFeeSplit {
+                fee_split: FeeSplit {
+                    treasury,
+                    treasury_bps: 2_000,
+                    keeper_bps: 1_000,
+                },
+            })
+            .unwrap();
+        for mint in market.mints {
+            market.bank.add_ata(&treasury, &mint, 0);
+        }
+        let mut accounts = market.trade_accounts();
+        accounts.extend(
+            market
+                .mints
+                .iter()
+                .map(|mint| writable(get_associated_token_address(&treasury, mint))),
+        );
+        let ix = market.try_arbitrage(90);
+
+        // A trader's own trade never sweeps a fee through any other program
+        let mut spoofed = accounts.clone();
+        spoofed[1] = readonly(Pubkey::new_unique());
+        assert_eq!(
+            market.bank.process(&market.program_id, &spoofed, &ix),
+            Err(ArbitrageProgramError::InvalidProgramAccount.into())
+        );
+
+        // With no vault there's no keeper to pay, so the trader keeps all but
+        // the treasury's fifth
+        market
+            .bank
+            .process(&market.program_id, &accounts, &ix)
+            .unwrap();
+        let treasury_fee = market.balance(&treasury, 0);
+        let retained = market.balance(&market.payer, 0) - Market::BALANCE;
+        assert!(treasury_fee > 0);
+        assert_eq!(treasury_fee, (treasury_fee + retained) * 2_000 / 10_000);
+        assert_eq!(market.balance(&treasury, 1), 0);
+    }
+
+    #[test]
+    fn test_loss_limit_blocks_arbitrage() {
+        let mut market = Market::new();
+        market.set_mint(
+            market.mints[0],
+            AssetCaps {
+                max_window_loss: Some(1_000),
+                ..AssetCaps::default()
+            },
+        );
+        let program_id = market.program_id;
+        let (risk_key, _) = RiskAccount::address(&program_id, &market.payer);
+        let mut risk: RiskAccount = market.bank.load(&program_id, &risk_key);
+        risk.record(&market.mints[0], -1_001).unwrap();
+        market.bank.store(&program_id, risk_key, &risk);
+        let accounts = market.trade_accounts();
+        let ix = market.try_arbitrage(90);
+        assert_eq!(
+            market.bank.process(&program_id, &accounts, &ix),
+            Err(ArbitrageProgramError::LossLimitExceeded.into())
+        );
+        assert!(swap_amounts(&market).is_empty());
+
+        // Once the loss window rolls over the trader can trade again
+        set_clock(2, risk.window_start + DEFAULT_LOSS_WINDOW_SECONDS);
+        market.bank.process(&program_id, &accounts, &ix).unwrap();
+        assert_eq!(swap_amounts(&market).len(), 2);
+    }
+
+    #[test]
+    fn test_disabled_venue_refused() {
+        let mut market = Market::new();
+        market
+            .config(ArbitrageProgramInstruction::SetVenueDisabled {
+                program_id: market.swaps[1],
+                disabled: true,
+            })
+            .unwrap();
+        let accounts = market.trade_accounts();
+        let ix = market.try_arbitrage(90);
+        assert_eq!(
+            market.bank.process(&market.program_id, &accounts, &ix),
+            Err(ArbitrageProgramError::NoArbitrage.into())
+        );
+        assert!(swap_amounts(&market).is_empty());
+        assert_eq!(market.balance(&market.payer, 0), Market::BALANCE);
+
+        // Resetting the circuit breaker lets the venue be traded again
+        market
+            .config(ArbitrageProgramInstruction::SetVenueDisabled {
+                program_id: market.swaps[1],
+                disabled: false,
+            })
+            .unwrap();
+        market
+            .bank
+            .process(&market.program_id, &accounts, &ix)
+            .unwrap();
+        assert_eq!(swap_amounts(&market).len(), 2);
+    }
 }
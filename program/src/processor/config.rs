@@ -0,0 +1,438 @@
+//! Processes changes to the arbitrage program's global config
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::state::{
+    ArbitrageAccount, ArbitrageConfig, Authority, ConfigChange, FeeSplit, MintRegistry,
+    TemperatureBounds, DEFAULT_LOSS_WINDOW_SECONDS,
+};
+use crate::util::create_pda_account;
+
+/// Creates the config PDA, setting the payer as its authority
+///
+/// Only the program's upgrade authority can create the config, so nobody can
+/// take over a freshly deployed program by initializing it first
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority (Payer)
+/// * Config
+/// * Program Data (The arbitrage program's, from the upgradeable loader)
+/// * System Program
+pub fn process_initialize_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let program_data_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !authority.is_signer
+        || upgrade_authority(program_id, program_data_info)? != Some(*authority.key)
+    {
+        msg!("Only the program's upgrade authority can initialize the config");
+        return Err(ArbitrageProgramError::InvalidAuthority.into());
+    }
+
+    let (config_address, bump) = ArbitrageConfig::address(program_id);
+    if !config_info.key.eq(&config_address) {
+        msg!("Expected config address: {}", config_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    create_pda_account(
+        authority,
+        config_info,
+        system_program,
+        program_id,
+        ArbitrageConfig::LEN,
+        &[ArbitrageConfig::SEED_PREFIX, &[bump]],
+    )?;
+    ArbitrageConfig {
+        authority: Authority::Single(*authority.key),
+        bump,
+        paused: false,
+        loss_window_seconds: DEFAULT_LOSS_WINDOW_SECONDS,
+        pair_cooldown_slots: 0,
+        fee_split: FeeSplit::default(),
+        temperature_bounds: TemperatureBounds::default(),
+        timelock_slots: 0,
+        pending_changes: vec![],
+        pending_authority: None,
+        venues: vec![],
+    }
+    .save(config_info)?;
+    msg!("Config initialized with authority: {}", authority.key);
+    Ok(())
+}
+
+/// Reads the program's upgrade authority from its Program Data account -
+/// `None` if the program has been made immutable
+fn upgrade_authority(
+    program_id: &Pubkey,
+    program_data_info: &AccountInfo,
+) -> Result<Option<Pubkey>, ProgramError> {
+    let (program_data_address, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if !program_data_info.key.eq(&program_data_address)
+        || !program_data_info.owner.eq(&bpf_loader_upgradeable::id())
+    {
+        msg!("Expected program data address: {}", program_data_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    // `UpgradeableLoaderState::ProgramData` is a four-byte variant tag of 3,
+    // the slot it was last deployed at, and then an optional authority
+    let data = program_data_info.data.borrow();
+    match (data.get(..4), data.get(12), data.get(13..45)) {
+        (Some([3, 0, 0, 0]), Some(0), _) => Ok(None),
+        (Some([3, 0, 0, 0]), Some(1), Some(key)) => {
+            Ok(Some(Pubkey::new_from_array(key.try_into().unwrap())))
+        }
+        _ => {
+            msg!("Failed to deserialize program data account");
+            Err(ArbitrageProgramError::InvalidProgramAccount.into())
+        }
+    }
+}
+
+/// Queues a change to the config, which can be applied once the config's
+/// timelock has passed - or makes it straight away, if there's no timelock
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority
+/// * Config
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_queue_config_change(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    change: ConfigChange,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let _authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    let mut config = ArbitrageConfig::load(program_id, config_info)?;
+    let signers = config.assert_authority(accounts)?;
+    msg!("Config change requested by {:?}", signers);
+    config.queue_change(change, Clock::get()?.slot, None)?;
+    config.save(config_info)
+}
+
+/// Applies every queued config change whose timelock has passed
+///
+/// Anyone can apply them, since the changes themselves were authorised when
+/// they were queued
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Config
+/// * [Mint Registry] (To apply queued changes to the mint registry)
+pub fn process_apply_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_info = next_account_info(accounts_iter)?;
+    let registry_info = accounts_iter.next();
+
+    let mut config = ArbitrageConfig::load(program_id, config_info)?;
+    let mut registry = registry_info
+        .map(|info| MintRegistry::load(program_id, info))
+        .transpose()?;
+    config.apply_pending_changes(Clock::get()?.slot, registry.as_mut());
+    if let (Some(registry), Some(registry_info)) = (registry, registry_info) {
+        registry.save(registry_info)?;
+    }
+    config.save(config_info)
+}
+
+/// Cancels a queued config change before it's applied
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority
+/// * Config
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_cancel_config_change(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u8,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let _authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    let mut config = ArbitrageConfig::load(program_id, config_info)?;
+    let signers = config.assert_authority(accounts)?;
+    if index as usize >= config.pending_changes.len() {
+        msg!("No queued change at index {}", index);
+        return Err(ArbitrageProgramError::InvalidConfig.into());
+    }
+    let cancelled = config.pending_changes.remove(index as usize);
+    msg!("Cancelled {:?} by {:?}", cancelled.change, signers);
+    config.save(config_info)
+}
+
+/// Offers to hand the config over to a new authority - a single key or a
+/// multisig - who must accept it
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority
+/// * Config
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_transfer_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_authority: Authority,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let _authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    let mut config = ArbitrageConfig::load(program_id, config_info)?;
+    let signers = config.assert_authority(accounts)?;
+    new_authority.validate()?;
+    msg!(
+        "Authority transfer to {:?} offered by {:?}",
+        new_authority,
+        signers
+    );
+    config.pending_authority = Some(new_authority);
+    config.save(config_info)
+}
+
+/// Accepts an offered authority transfer, taking over the config
+///
+/// For a multisig, enough of its signers must sign to meet its threshold
+///
+/// Note: accounts must be provided in a very specific order:
+/// * New Authority (or one of its signers)
+/// * Config
+/// * [Additional Multisig Signers] (If the new authority is a multisig)
+pub fn process_accept_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let _new_authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    let mut config = ArbitrageConfig::load(program_id, config_info)?;
+    let new_authority = match config.pending_authority.take() {
+        Some(new_authority) => new_authority,
+        None => {
+            msg!("No authority transfer has been offered");
+            return Err(ArbitrageProgramError::InvalidAuthority.into());
+        }
+    };
+    new_authority.assert_signed(accounts)?;
+    msg!("Authority transferred to {:?}", new_authority);
+    config.authority = new_authority;
+    config.save(config_info)
+}
+
+/// Flips the config's global pause switch
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority
+/// * Config
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_set_paused(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    paused: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let _authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    let mut config = ArbitrageConfig::load(program_id, config_info)?;
+    let signers = config.assert_authority(accounts)?;
+    config.paused = paused;
+    msg!("Paused set to {} by {:?}", paused, signers);
+    config.save(config_info)
+}
+
+/// Trips or resets a venue's circuit breaker
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority
+/// * Config
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_set_venue_disabled(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    venue_program_id: &Pubkey,
+    disabled: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let _authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    let mut config = ArbitrageConfig::load(program_id, config_info)?;
+    let signers = config.assert_authority(accounts)?;
+    config.set_venue_disabled(venue_program_id, disabled)?;
+    msg!(
+        "Venue {} disabled set to {} by {:?}",
+        venue_program_id,
+        disabled,
+        signers
+    );
+    config.save(config_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{
+        account_info::AccountInfo, entrypoint::ProgramResult, instruction::AccountMeta,
+        pubkey::Pubkey, system_program,
+    };
+
+    use crate::error::ArbitrageProgramError;
+    use crate::state::{ArbitrageConfig, Authority};
+    use crate::test_utils::{readonly, signer, writable, Bank, Market};
+    use crate::ArbitrageProgramInstruction;
+
+    fn initialize_config(
+        bank: &mut Bank,
+        program_id: &Pubkey,
+        authority: Pubkey,
+        program_data: Pubkey,
+    ) -> ProgramResult {
+        bank.process(
+            program_id,
+            &[
+                signer(authority),
+                writable(ArbitrageConfig::address(program_id).0),
+                readonly(program_data),
+                readonly(system_program::id()),
+            ],
+            &ArbitrageProgramInstruction::InitializeConfig,
+        )
+    }
+
+    #[test]
+    fn test_only_upgrade_authority_initializes_config() {
+        let mut bank = Bank::new();
+        let program_id = Pubkey::new_unique();
+        let deployer = bank.add_wallet();
+        let attacker = bank.add_wallet();
+
+        // Somebody else's program's data doesn't count
+        let other_program_data = bank.add_program_data(&Pubkey::new_unique(), Some(attacker));
+        assert_eq!(
+            initialize_config(&mut bank, &program_id, attacker, other_program_data),
+            Err(ArbitrageProgramError::InvalidProgramAccount.into())
+        );
+
+        // Nor does an immutable program have anyone who can initialize it
+        let program_data = bank.add_program_data(&program_id, None);
+        assert_eq!(
+            initialize_config(&mut bank, &program_id, deployer, program_data),
+            Err(ArbitrageProgramError::InvalidAuthority.into())
+        );
+
+        bank.add_program_data(&program_id, Some(deployer));
+        assert_eq!(
+            initialize_config(&mut bank, &program_id, attacker, program_data),
+            Err(ArbitrageProgramError::InvalidAuthority.into())
+        );
+        initialize_config(&mut bank, &program_id, deployer, program_data).unwrap();
+        let config: ArbitrageConfig =
+            bank.load(&program_id, &ArbitrageConfig::address(&program_id).0);
+        assert!(matches!(config.authority, Authority::Single(key) if key == deployer));
+    }
+
+    #[test]
+    fn test_multisig_threshold() {
+        let mut market = Market::new();
+        let config = ArbitrageConfig::address(&market.program_id).0;
+        let [a, b, c] = [(); 3].map(|_| market.bank.add_wallet());
+        let mut send = |metas: &[AccountMeta], instruction| {
+            market.bank.process(&market.program_id, metas, &instruction)
+        };
+
+        // A threshold no set of signers could meet is refused outright
+        assert_eq!(
+            send(
+                &[signer(market.payer), writable(config)],
+                ArbitrageProgramInstruction::TransferAuthority {
+                    new_authority: Authority::Multisig {
+                        threshold: 3,
+                        signers: vec![a, b],
+                    },
+                },
+            ),
+            Err(ArbitrageProgramError::InvalidConfig.into())
+        );
+        send(
+            &[signer(market.payer), writable(config)],
+            ArbitrageProgramInstruction::TransferAuthority {
+                new_authority: Authority::Multisig {
+                    threshold: 2,
+                    signers: vec![a, b, c],
+                },
+            },
+        )
+        .unwrap();
+
+        // Taking over needs two of the three, just as using it does
+        assert_eq!(
+            send(
+                &[signer(a), writable(config), readonly(b)],
+                ArbitrageProgramInstruction::AcceptAuthority,
+            ),
+            Err(ArbitrageProgramError::InvalidAuthority.into())
+        );
+        send(
+            &[signer(a), writable(config), signer(b)],
+            ArbitrageProgramInstruction::AcceptAuthority,
+        )
+        .unwrap();
+
+        // Neither the old authority, one signer, nor one signer passed twice
+        // can act alone
+        for metas in [
+            vec![signer(market.payer), writable(config)],
+            vec![signer(c), writable(config)],
+            vec![signer(c), writable(config), signer(c)],
+        ] {
+            assert_eq!(
+                send(
+                    &metas,
+                    ArbitrageProgramInstruction::SetPaused { paused: true }
+                ),
+                Err(ArbitrageProgramError::InvalidAuthority.into())
+            );
+        }
+        send(
+            &[signer(c), writable(config), signer(a)],
+            ArbitrageProgramInstruction::SetPaused { paused: true },
+        )
+        .unwrap();
+        let config: ArbitrageConfig = market.bank.load(&market.program_id, &config);
+        assert!(config.paused);
+    }
+
+    #[test]
+    fn test_authority_reports_counted_signers() {
+        let [spoof, a, b, c] = [(); 4].map(|_| Pubkey::new_unique());
+        let authority = Authority::Multisig {
+            threshold: 2,
+            signers: vec![a, b, c],
+        };
+        let mut lamports = [0; 4];
+        let mut data = [(); 4].map(|_| vec![]);
+        let owner = system_program::id();
+        let keys = [(spoof, true), (a, false), (b, true), (c, true)];
+        let infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut().zip(data.iter_mut()))
+            .map(|((key, is_signer), (lamports, data))| {
+                AccountInfo::new(key, *is_signer, false, lamports, data, &owner, false, 0)
+            })
+            .collect();
+
+        // A signer outside the multisig in the first slot isn't who acted -
+        // only the signers that met the threshold are
+        assert_eq!(authority.assert_signed(&infos), Ok(vec![b, c]));
+    }
+}
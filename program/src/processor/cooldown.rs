@@ -0,0 +1,50 @@
+//! Processes the creation of the shared per-pair cooldown account
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::state::{ArbitrageAccount, ArbitrageConfig, PairCooldowns};
+use crate::util::create_pda_account;
+
+/// Creates the cooldown account PDA
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority (Payer)
+/// * Config
+/// * Pair Cooldowns
+/// * System Program
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_initialize_pair_cooldowns(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let cooldowns_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    ArbitrageConfig::load(program_id, config_info)?.assert_authority(accounts)?;
+    let (cooldowns_address, bump) = PairCooldowns::address(program_id);
+    if !cooldowns_info.key.eq(&cooldowns_address) {
+        msg!("Expected pair cooldowns address: {}", cooldowns_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    create_pda_account(
+        authority,
+        cooldowns_info,
+        system_program,
+        program_id,
+        PairCooldowns::LEN,
+        &[PairCooldowns::SEED_PREFIX, &[bump]],
+    )?;
+    PairCooldowns {
+        bump,
+        pairs: vec![],
+    }
+    .save(cooldowns_info)
+}
@@ -0,0 +1,250 @@
+//! Processes changes to the registry of tradable mints
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::state::{ArbitrageAccount, ArbitrageConfig, ConfigChange, MintRegistry, RegisteredMint};
+use crate::util::create_pda_account;
+
+/// Creates the mint registry PDA
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority (Payer)
+/// * Config
+/// * Mint Registry
+/// * System Program
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_initialize_mint_registry(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let registry_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    ArbitrageConfig::load(program_id, config_info)?.assert_authority(accounts)?;
+    let (registry_address, bump) = MintRegistry::address(program_id);
+    if !registry_info.key.eq(&registry_address) {
+        msg!("Expected mint registry address: {}", registry_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    create_pda_account(
+        authority,
+        registry_info,
+        system_program,
+        program_id,
+        MintRegistry::LEN,
+        &[MintRegistry::SEED_PREFIX, &[bump]],
+    )?;
+    MintRegistry {
+        bump,
+        mints: vec![],
+    }
+    .save(registry_info)
+}
+
+/// Registers a mint as allowed or denied, along with its caps
+///
+/// Denying a mint or tightening its caps is done straight away, but anything
+/// that loosens what can be traded is queued behind the config's timelock
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority
+/// * Config
+/// * Mint Registry
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_set_mint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    registered: RegisteredMint,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let _authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let registry_info = next_account_info(accounts_iter)?;
+
+    let mut config = ArbitrageConfig::load(program_id, config_info)?;
+    let signers = config.assert_authority(accounts)?;
+    let mut registry = MintRegistry::load(program_id, registry_info)?;
+    msg!(
+        "Registering mint {} as {:?} by {:?}",
+        registered.mint,
+        registered.status,
+        signers
+    );
+    if registry.is_tightening(&registered) {
+        registry.upsert(registered)?;
+    } else {
+        config.queue_change(
+            ConfigChange::SetMint(registered),
+            Clock::get()?.slot,
+            Some(&mut registry),
+        )?;
+        config.save(config_info)?;
+    }
+    registry.save(registry_info)
+}
+
+/// Removes a mint from the registry
+///
+/// This only ever narrows what can be traded, so it's never timelocked
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority
+/// * Config
+/// * Mint Registry
+/// * [Additional Multisig Signers] (If the authority is a multisig)
+pub fn process_remove_mint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint: &Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let _authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let registry_info = next_account_info(accounts_iter)?;
+
+    let signers = ArbitrageConfig::load(program_id, config_info)?.assert_authority(accounts)?;
+    let mut registry = MintRegistry::load(program_id, registry_info)?;
+    msg!("Removing mint {} by {:?}", mint, signers);
+    registry.remove(mint)?;
+    registry.save(registry_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{entrypoint::ProgramResult, pubkey::Pubkey};
+
+    use crate::state::{ArbitrageConfig, AssetCaps, MintRegistry, MintStatus};
+    use crate::test_utils::{set_clock, signer, writable, Market};
+    use crate::ArbitrageProgramInstruction;
+
+    fn set_mint(market: &mut Market, mint: Pubkey, status: MintStatus, caps: AssetCaps) {
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &[
+                    signer(market.payer),
+                    writable(ArbitrageConfig::address(&market.program_id).0),
+                    writable(MintRegistry::address(&market.program_id).0),
+                ],
+                &ArbitrageProgramInstruction::SetMint { mint, status, caps },
+            )
+            .unwrap();
+    }
+
+    fn apply_config(market: &mut Market, with_registry: bool) -> ProgramResult {
+        let mut accounts = vec![writable(ArbitrageConfig::address(&market.program_id).0)];
+        if with_registry {
+            accounts.push(writable(MintRegistry::address(&market.program_id).0));
+        }
+        market.bank.process(
+            &market.program_id,
+            &accounts,
+            &ArbitrageProgramInstruction::ApplyConfig,
+        )
+    }
+
+    fn registry(market: &Market) -> MintRegistry {
+        market.bank.load(
+            &market.program_id,
+            &MintRegistry::address(&market.program_id).0,
+        )
+    }
+
+    fn pending(market: &Market) -> usize {
+        let config: ArbitrageConfig = market.bank.load(
+            &market.program_id,
+            &ArbitrageConfig::address(&market.program_id).0,
+        );
+        config.pending_changes.len()
+    }
+
+    #[test]
+    fn test_loosening_mint_changes_wait_out_timelock() {
+        let mut market = Market::new();
+        market
+            .config(ArbitrageProgramInstruction::SetTimelock { timelock_slots: 10 })
+            .unwrap();
+        let capped = AssetCaps {
+            max_trade_amount: Some(1_000),
+            ..AssetCaps::default()
+        };
+
+        // Lowering a cap is made straight away
+        let mint = market.mints[0];
+        set_mint(&mut market, mint, MintStatus::Allowed, capped);
+        assert_eq!(
+            registry(&market).caps(&mint).unwrap().max_trade_amount,
+            Some(1_000)
+        );
+        assert_eq!(pending(&market), 0);
+
+        // Approving a new mint and lifting the cap both wait
+        let new_mint = market.bank.add_mint(6);
+        set_mint(
+            &mut market,
+            new_mint,
+            MintStatus::Allowed,
+            AssetCaps::default(),
+        );
+        set_mint(&mut market, mint, MintStatus::Allowed, AssetCaps::default());
+        assert_eq!(pending(&market), 2);
+        assert!(registry(&market).tradable(&new_mint).is_none());
+
+        // Nothing applies a slot early
+        set_clock(10, 1_700_000_010);
+        apply_config(&mut market, true).unwrap();
+        assert_eq!(pending(&market), 2);
+        assert!(registry(&market).tradable(&new_mint).is_none());
+        assert_eq!(
+            registry(&market).caps(&mint).unwrap().max_trade_amount,
+            Some(1_000)
+        );
+
+        // Once the timelock has passed, mint changes still wait for the
+        // registry
+        set_clock(11, 1_700_000_011);
+        apply_config(&mut market, false).unwrap();
+        assert_eq!(pending(&market), 2);
+        apply_config(&mut market, true).unwrap();
+        assert_eq!(pending(&market), 0);
+        assert!(registry(&market).tradable(&new_mint).is_some());
+        assert_eq!(
+            registry(&market).caps(&mint).unwrap().max_trade_amount,
+            None
+        );
+
+        // Denying and removing mints are made straight away
+        set_mint(
+            &mut market,
+            new_mint,
+            MintStatus::Denied,
+            AssetCaps::default(),
+        );
+        assert!(registry(&market).tradable(&new_mint).is_none());
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &[
+                    signer(market.payer),
+                    writable(ArbitrageConfig::address(&market.program_id).0),
+                    writable(MintRegistry::address(&market.program_id).0),
+                ],
+                &ArbitrageProgramInstruction::RemoveMint { mint: new_mint },
+            )
+            .unwrap();
+        assert!(registry(&market).caps(&new_mint).is_none());
+        assert_eq!(pending(&market), 0);
+    }
+}
@@ -0,0 +1,949 @@
+//! Processes the creation of pooled vaults and deposits into and withdrawals
+//! from them in exchange for shares
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token_2022::state::Mint;
+
+use crate::error::ArbitrageProgramError;
+use crate::partial_state::{ArbitrageTokenAccountInfo, PartialMintState, PartialTokenAccountState};
+use crate::state::{
+    spot_price, ArbitrageAccount, ArbitrageConfig, PooledAsset, Vault, VaultShares, VIRTUAL_SHARES,
+};
+use crate::util::{assert_token_program, create_pda_account, ArbitrageEvaluateOption};
+use crate::venue::{self, SwapVenue};
+
+use super::vault::create_vault_token_account;
+
+/// Creates the payer's pooled vault and its share mint
+///
+/// The share mint takes the quote asset's decimals, so the first deposit
+/// mints one share per unit of quote value
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner (Payer)
+/// * Vault
+/// * Share Mint
+/// * Quote Mint
+/// * Vault's Quote Token Account
+/// * Token Program
+/// * System Program
+/// * Associated Token Program
+/// * Config
+pub fn process_initialize_pooled_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    keeper: Pubkey,
+    pricing_venue: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+    let share_mint = next_account_info(accounts_iter)?;
+    let quote_mint = next_account_info(accounts_iter)?;
+    let vault_quote_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let associated_token_program = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    // Shares can only be priced against an approved venue
+    ArbitrageConfig::load(program_id, config_info)?.venue(&pricing_venue)?;
+
+    let (vault_address, bump) = Vault::address(program_id, owner.key);
+    if !vault_info.key.eq(&vault_address) {
+        msg!("Expected vault address: {}", vault_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    let (share_mint_address, mint_bump) = VaultShares::mint_address(program_id, vault_info.key);
+    if !share_mint.key.eq(&share_mint_address) {
+        msg!("Expected share mint address: {}", share_mint_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    let (_, decimals) = PartialMintState::try_deserialize(quote_mint)?;
+
+    create_pda_account(
+        owner,
+        vault_info,
+        system_program,
+        program_id,
+        Vault::LEN,
+        &[Vault::SEED_PREFIX, owner.key.as_ref(), &[bump]],
+    )?;
+    create_pda_account(
+        owner,
+        share_mint,
+        system_program,
+        token_program.key,
+        Mint::LEN,
+        &[
+            VaultShares::SEED_PREFIX,
+            vault_info.key.as_ref(),
+            &[mint_bump],
+        ],
+    )?;
+    invoke(
+        &spl_token_2022::instruction::initialize_mint2(
+            token_program.key,
+            share_mint.key,
+            vault_info.key,
+            None,
+            decimals,
+        )?,
+        &[share_mint.clone(), token_program.clone()],
+    )?;
+    create_vault_token_account(
+        owner,
+        vault_info,
+        vault_quote_token_account,
+        quote_mint,
+        token_program,
+        system_program,
+        associated_token_program,
+    )?;
+    Vault {
+        owner: *owner.key,
+        keeper,
+        permissionless: false,
+        bump,
+        shares: Some(VaultShares {
+            mint: *share_mint.key,
+            mint_bump,
+            pricing_venue,
+            assets: vec![PooledAsset {
+                mint: *quote_mint.key,
+                reference_price: 1 << 64,
+                reference_slot: 0,
+            }],
+        }),
+    }
+    .save(vault_info)?;
+    msg!(
+        "Pooled vault initialized with share mint: {}",
+        share_mint.key
+    );
+    Ok(())
+}
+
+/// Adds an asset to a pooled vault's holdings, creating the vault's
+/// associated token account for it
+///
+/// The asset's reference price starts at the pricing pool's spot price, as
+/// vouched for by the owner
+///
+/// Assets can't be removed again, since any balance left in them would no
+/// longer be valued or withdrawable
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner
+/// * Vault
+/// * Mint
+/// * Vault's Token Account
+/// * Token Program
+/// * System Program
+/// * Associated Token Program
+/// * Config
+/// * Pricing Venue's Liquidity Pool
+/// * Liquidity Pool's Quote Token Account
+/// * Liquidity Pool's Token Account
+pub fn process_add_pooled_asset(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+    let mint = next_account_info(accounts_iter)?;
+    let vault_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let associated_token_program = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let pricing_pool = next_account_info(accounts_iter)?;
+    let pool_quote_token_account = next_account_info(accounts_iter)?;
+    let pool_token_account = next_account_info(accounts_iter)?;
+
+    let mut vault = Vault::load(program_id, vault_info)?;
+    vault.assert_owner(owner)?;
+    assert_token_program(token_program)?;
+    let shares = vault.shares_mut()?;
+    let config = ArbitrageConfig::load(program_id, config_info)?;
+    let pricing_venue = venue::adapter(config.venue(&shares.pricing_venue)?, None)?;
+    pricing_venue.check_pool(pricing_pool)?;
+    let pool_quote = read_pool_token_account(
+        pricing_venue.as_ref(),
+        pricing_pool,
+        pool_quote_token_account,
+        &shares.assets[0].mint,
+    )?;
+    let pool_balance = read_pool_token_account(
+        pricing_venue.as_ref(),
+        pricing_pool,
+        pool_token_account,
+        mint.key,
+    )?;
+    let price = match spot_price(pool_quote, pool_balance) {
+        Some(price) => price,
+        None => {
+            msg!("Pricing pool holds none of mint: {}", mint.key);
+            return Err(ArbitrageProgramError::InvalidShareAmount.into());
+        }
+    };
+    shares.add_asset(*mint.key, price, Clock::get()?.slot)?;
+    create_vault_token_account(
+        owner,
+        vault_info,
+        vault_token_account,
+        mint,
+        token_program,
+        system_program,
+        associated_token_program,
+    )?;
+    msg!("Pooled vault now holds mint: {}", mint.key);
+    vault.save(vault_info)
+}
+
+/// Deposits one of a pooled vault's assets in exchange for newly minted
+/// shares, priced by the value of the vault's holdings before the deposit
+///
+/// The deposit is valued at the lower of the asset's spot and reference
+/// prices, and the vault's holdings at the higher of theirs, so moving the
+/// pricing pool can only short the depositor. Each reference price then
+/// follows the spot price as far as it's allowed to
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Depositor
+/// * Vault
+/// * Share Mint
+/// * Depositor's Share Token Account
+/// * Depositor's Token Account
+/// * Mint
+/// * Token Program
+/// * Config
+/// * Pricing Venue's Liquidity Pool
+/// * [Vault's Token Accounts] (One per vault asset, in order)
+/// * [Liquidity Pool's Token Accounts] (One per vault asset, in order)
+pub fn process_deposit_for_shares(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let depositor = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+    let share_mint = next_account_info(accounts_iter)?;
+    let depositor_share_token_account = next_account_info(accounts_iter)?;
+    let depositor_token_account = next_account_info(accounts_iter)?;
+    let mint = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let pricing_pool = next_account_info(accounts_iter)?;
+
+    let mut vault = Vault::load(program_id, vault_info)?;
+    assert_token_program(token_program)?;
+    let shares = vault.shares()?;
+    assert_share_mint(shares, share_mint)?;
+    let config = ArbitrageConfig::load(program_id, config_info)?;
+    let pricing_venue = venue::adapter(config.venue(&shares.pricing_venue)?, None)?;
+    pricing_venue.check_pool(pricing_pool)?;
+
+    let index = match shares.assets.iter().position(|a| a.mint.eq(mint.key)) {
+        Some(index) => index,
+        None => {
+            msg!("Pooled vault does not hold mint: {}", mint.key);
+            return Err(ArbitrageProgramError::InvalidAccountsList.into());
+        }
+    };
+    let holdings = read_holdings(
+        shares,
+        vault_info,
+        pricing_venue.as_ref(),
+        pricing_pool,
+        accounts_iter,
+    )?;
+    let mut total_value = 0u128;
+    for (holding, asset) in holdings.iter().zip(&shares.assets) {
+        let price = holding.spot_price.max(asset.reference_price);
+        total_value = total_value.saturating_add(value(holding.balance, price)?);
+    }
+    let asset = &shares.assets[index];
+    let deposit_value = value(
+        amount,
+        holdings[index].spot_price.min(asset.reference_price),
+    )?;
+
+    // The virtual shares start the share price at one share per unit of
+    // value, and keep a donation from moving it enough to matter
+    let supply = PartialMintState::try_deserialize_supply(share_mint)?;
+    let minted = deposit_value.saturating_mul(supply as u128 + VIRTUAL_SHARES)
+        / total_value.saturating_add(VIRTUAL_SHARES);
+    let minted = match u64::try_from(minted) {
+        Ok(minted) if minted > 0 => minted,
+        _ => {
+            msg!("Deposit resolves to {} shares", minted);
+            return Err(ArbitrageProgramError::InvalidShareAmount.into());
+        }
+    };
+
+    let (_, decimals) = PartialMintState::try_deserialize(mint)?;
+    let vault_token_account = holdings[index].token_account;
+    invoke(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            depositor_token_account.key,
+            mint.key,
+            vault_token_account.key,
+            depositor.key,
+            &[],
+            amount,
+            decimals,
+        )?,
+        &[
+            depositor_token_account.clone(),
+            mint.clone(),
+            vault_token_account.clone(),
+            depositor.clone(),
+            token_program.clone(),
+        ],
+    )?;
+    invoke_signed(
+        &spl_token_2022::instruction::mint_to(
+            token_program.key,
+            share_mint.key,
+            depositor_share_token_account.key,
+            vault_info.key,
+            &[],
+            minted,
+        )?,
+        &[
+            share_mint.clone(),
+            depositor_share_token_account.clone(),
+            vault_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[Vault::SEED_PREFIX, vault.owner.as_ref(), &[vault.bump]]],
+    )?;
+    msg!(
+        "Deposited {} of mint {} for {} shares",
+        amount,
+        mint.key,
+        minted
+    );
+
+    let slot = Clock::get()?.slot;
+    let spot_prices: Vec<u128> = holdings.iter().map(|h| h.spot_price).collect();
+    for (asset, spot_price) in vault
+        .shares_mut()?
+        .assets
+        .iter_mut()
+        .zip(spot_prices)
+        .skip(1)
+    {
+        asset.follow(spot_price, slot);
+    }
+    vault.save(vault_info)
+}
+
+/// Burns shares of a pooled vault in exchange for the same fraction of each
+/// of the vault's holdings
+///
+/// Withdrawing in kind means withdrawals don't depend on any price, so they
+/// can't be front-run by moving the pricing venue's pool
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Withdrawer
+/// * Vault
+/// * Share Mint
+/// * Withdrawer's Share Token Account
+/// * Token Program
+/// * [Vault's Token Accounts] (One per vault asset, in order)
+/// * [Destination Token Accounts] (One per vault asset, in order)
+/// * [Mint Accounts] (One per vault asset, in order)
+pub fn process_withdraw_shares(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let withdrawer = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+    let share_mint = next_account_info(accounts_iter)?;
+    let withdrawer_share_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    let vault = Vault::load(program_id, vault_info)?;
+    assert_token_program(token_program)?;
+    let shares = vault.shares()?;
+    assert_share_mint(shares, share_mint)?;
+    let supply = PartialMintState::try_deserialize_supply(share_mint)?;
+    if amount == 0 || amount > supply {
+        msg!("Cannot withdraw {} of {} shares", amount, supply);
+        return Err(ArbitrageProgramError::InvalidShareAmount.into());
+    }
+
+    let vault_token_accounts = shares
+        .assets
+        .iter()
+        .map(|asset| {
+            read_vault_token_account(next_account_info(accounts_iter)?, vault_info, &asset.mint)
+        })
+        .collect::<Result<Vec<_>, ProgramError>>()?;
+    let destination_token_accounts = shares
+        .assets
+        .iter()
+        .map(|_| next_account_info(accounts_iter))
+        .collect::<Result<Vec<_>, ProgramError>>()?;
+    let mints = shares
+        .assets
+        .iter()
+        .map(|asset| {
+            let mint = next_account_info(accounts_iter)?;
+            if !mint.key.eq(&asset.mint) {
+                msg!("Expected mint: {}", asset.mint);
+                return Err(ArbitrageProgramError::InvalidAccountsList.into());
+            }
+            PartialMintState::try_deserialize(mint)
+        })
+        .collect::<Result<Vec<_>, ProgramError>>()?;
+
+    invoke(
+        &spl_token_2022::instruction::burn(
+            token_program.key,
+            withdrawer_share_token_account.key,
+            share_mint.key,
+            withdrawer.key,
+            &[],
+            amount,
+        )?,
+        &[
+            withdrawer_share_token_account.clone(),
+            share_mint.clone(),
+            withdrawer.clone(),
+            token_program.clone(),
+        ],
+    )?;
+    for ((vault_token_account, destination), mint) in vault_token_accounts
+        .iter()
+        .zip(destination_token_accounts)
+        .zip(mints)
+    {
+        // Round down, so the remaining holders are never diluted - the
+        // virtual shares are owed their cut too
+        let withdrawn = (vault_token_account.3 as u128 * amount as u128
+            / (supply as u128 + VIRTUAL_SHARES)) as u64;
+        if withdrawn == 0 {
+            continue;
+        }
+        invoke_signed(
+            &spl_token_2022::instruction::transfer_checked(
+                token_program.key,
+                vault_token_account.0.key,
+                mint.0.key,
+                destination.key,
+                vault_info.key,
+                &[],
+                withdrawn,
+                mint.1,
+            )?,
+            &[
+                vault_token_account.0.clone(),
+                mint.0.clone(),
+                destination.clone(),
+                vault_info.clone(),
+                token_program.clone(),
+            ],
+            &[&[Vault::SEED_PREFIX, vault.owner.as_ref(), &[vault.bump]]],
+        )?;
+        msg!("Withdrew {} of mint {}", withdrawn, mint.0.key);
+    }
+    Ok(())
+}
+
+/// One of a pooled vault's assets, along with its spot price in the pricing
+/// pool
+struct Holding<'a, 'b> {
+    token_account: &'a AccountInfo<'b>,
+    balance: u64,
+    spot_price: u128,
+}
+
+/// Values an amount of one of a pooled vault's assets in the quote asset, at
+/// a 64.64 fixed-point price
+fn value(amount: u64, price: u128) -> Result<u128, ProgramError> {
+    match (amount as u128).checked_mul(price) {
+        Some(value) => Ok(value >> 64),
+        None => {
+            msg!("Cannot value {} at price {}", amount, price);
+            Err(ArbitrageProgramError::InvalidShareAmount.into())
+        }
+    }
+}
+
+/// Reads the vault's and the pricing pool's token accounts for each of the
+/// vault's assets, pricing each in the quote asset - the first of them
+fn read_holdings<'a, 'b>(
+    shares: &VaultShares,
+    vault_info: &'a AccountInfo<'b>,
+    pricing_venue: &dyn SwapVenue,
+    pricing_pool: &AccountInfo,
+    accounts_iter: &mut impl Iterator<Item = &'a AccountInfo<'b>>,
+) -> Result<Vec<Holding<'a, 'b>>, ProgramError> {
+    let vault_token_accounts = shares
+        .assets
+        .iter()
+        .map(|asset| {
+            read_vault_token_account(
+                accounts_iter.next().ok_or_arb_err()?,
+                vault_info,
+                &asset.mint,
+            )
+        })
+        .collect::<Result<Vec<_>, ProgramError>>()?;
+    let pool_balances = shares
+        .assets
+        .iter()
+        .map(|asset| {
+            read_pool_token_account(
+                pricing_venue,
+                pricing_pool,
+                accounts_iter.next().ok_or_arb_err()?,
+                &asset.mint,
+            )
+        })
+        .collect::<Result<Vec<_>, ProgramError>>()?;
+    vault_token_accounts
+        .into_iter()
+        .zip(&pool_balances)
+        .enumerate()
+        .map(|(i, (vault_token_account, pool_balance))| {
+            let spot_price = if i == 0 {
+                1 << 64
+            } else {
+                match spot_price(pool_balances[0], *pool_balance) {
+                    Some(price) => price,
+                    None => {
+                        msg!("Pricing pool holds none of mint: {}", vault_token_account.1);
+                        return Err(ArbitrageProgramError::InvalidShareAmount.into());
+                    }
+                }
+            };
+            Ok(Holding {
+                token_account: vault_token_account.0,
+                balance: vault_token_account.3,
+                spot_price,
+            })
+        })
+        .collect()
+}
+
+/// Reads the vault's token account for a mint, which must be the vault's
+/// associated token account
+fn read_vault_token_account<'a, 'b>(
+    account_info: &'a AccountInfo<'b>,
+    vault_info: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<ArbitrageTokenAccountInfo<'a, 'b>, ProgramError> {
+    let expected = get_associated_token_address(vault_info.key, mint);
+    if !account_info.key.eq(&expected) {
+        msg!("Expected vault token account: {}", expected);
+        return Err(ArbitrageProgramError::InvalidAccountsList.into());
+    }
+    read_token_account(account_info, vault_info.key, mint)
+}
+
+/// Reads the pricing pool's balance of a mint, from the pool's own token
+/// account for it
+fn read_pool_token_account(
+    pricing_venue: &dyn SwapVenue,
+    pricing_pool: &AccountInfo,
+    account_info: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<u64, ProgramError> {
+    pricing_venue.check_pool_token_account(pricing_pool, account_info, mint)?;
+    let owner = pricing_venue.pool_token_owner(pricing_pool)?;
+    Ok(read_token_account(account_info, &owner, mint)?.3)
+}
+
+/// Reads a token account, asserting it's held by the token program along
+/// with its owner and mint
+fn read_token_account<'a, 'b>(
+    account_info: &'a AccountInfo<'b>,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Result<ArbitrageTokenAccountInfo<'a, 'b>, ProgramError> {
+    if !account_info.owner.eq(&spl_token::id()) {
+        msg!("Not a token account: {}", account_info.key);
+        return Err(ArbitrageProgramError::InvalidAccountsList.into());
+    }
+    let token_account = PartialTokenAccountState::try_deserialize(account_info, owner)?;
+    if !token_account.1.eq(mint) {
+        msg!("Mint mismatch");
+        msg!("Expected: {}", mint);
+        msg!("Got:      {}", token_account.1);
+        msg!("Token Account: {}", account_info.key);
+        return Err(ArbitrageProgramError::InvalidAccountsList.into());
+    }
+    Ok(token_account)
+}
+
+/// Asserts the provided account is the pooled vault's share mint
+fn assert_share_mint(shares: &VaultShares, share_mint: &AccountInfo) -> ProgramResult {
+    if !share_mint.key.eq(&shares.mint) {
+        msg!("Expected share mint: {}", shares.mint);
+        return Err(ArbitrageProgramError::InvalidAccountsList.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{entrypoint::ProgramResult, instruction::AccountMeta, pubkey::Pubkey};
+    use spl_associated_token_account::get_associated_token_address;
+
+    use crate::error::ArbitrageProgramError;
+    use crate::state::{ArbitrageConfig, Vault, VaultShares};
+    use crate::test_utils::{readonly, signer, writable, Market};
+    use crate::ArbitrageProgramInstruction;
+
+    /// A pooled vault quoted in the first mint, also holding the second and
+    /// priced on Swap #1 - whose pool prices them one for one
+    struct Pooled {
+        market: Market,
+        vault: Pubkey,
+        share_mint: Pubkey,
+    }
+
+    impl Pooled {
+        fn new() -> Self {
+            let mut market = Market::new();
+            let program_id = market.program_id;
+            let payer = market.payer;
+            let (vault, _) = Vault::address(&program_id, &payer);
+            let (share_mint, _) = VaultShares::mint_address(&program_id, &vault);
+            let [quote_mint, mint] = market.mints;
+            let config = ArbitrageConfig::address(&program_id).0;
+            market
+                .bank
+                .process(
+                    &program_id,
+                    &[
+                        signer(payer),
+                        writable(vault),
+                        writable(share_mint),
+                        readonly(quote_mint),
+                        writable(get_associated_token_address(&vault, &quote_mint)),
+                        readonly(spl_token::id()),
+                        readonly(solana_program::system_program::id()),
+                        readonly(spl_associated_token_account::id()),
+                        readonly(config),
+                    ],
+                    &ArbitrageProgramInstruction::InitializePooledVault {
+                        keeper: payer,
+                        pricing_venue: market.swaps[0],
+                    },
+                )
+                .unwrap();
+            let pool = market.pools[0];
+            market
+                .bank
+                .process(
+                    &program_id,
+                    &[
+                        signer(payer),
+                        writable(vault),
+                        readonly(mint),
+                        writable(get_associated_token_address(&vault, &mint)),
+                        readonly(spl_token::id()),
+                        readonly(solana_program::system_program::id()),
+                        readonly(spl_associated_token_account::id()),
+                        readonly(config),
+                        readonly(pool),
+                        readonly(get_associated_token_address(&pool, &quote_mint)),
+                        readonly(get_associated_token_address(&pool, &mint)),
+                    ],
+                    &ArbitrageProgramInstruction::AddPooledAsset,
+                )
+                .unwrap();
+            market.bank.add_ata(&payer, &share_mint, 0);
+            Self {
+                market,
+                vault,
+                share_mint,
+            }
+        }
+
+        fn vault_token_accounts(&self) -> Vec<AccountMeta> {
+            self.market
+                .mints
+                .iter()
+                .map(|mint| writable(get_associated_token_address(&self.vault, mint)))
+                .collect()
+        }
+
+        fn pool_token_accounts(&self) -> Vec<AccountMeta> {
+            self.market
+                .mints
+                .iter()
+                .map(|mint| readonly(get_associated_token_address(&self.market.pools[0], mint)))
+                .collect()
+        }
+
+        fn deposit_accounts(&self, depositor: Pubkey, mint: usize) -> Vec<AccountMeta> {
+            let mint = self.market.mints[mint];
+            let mut accounts = vec![
+                signer(depositor),
+                writable(self.vault),
+                writable(self.share_mint),
+                writable(get_associated_token_address(&depositor, &self.share_mint)),
+                writable(get_associated_token_address(&depositor, &mint)),
+                readonly(mint),
+                readonly(spl_token::id()),
+                readonly(ArbitrageConfig::address(&self.market.program_id).0),
+                readonly(self.market.pools[0]),
+            ];
+            accounts.extend(self.vault_token_accounts());
+            accounts.extend(self.pool_token_accounts());
+            accounts
+        }
+
+        fn withdraw_accounts(&self, withdrawer: Pubkey) -> Vec<AccountMeta> {
+            let mut accounts = vec![
+                signer(withdrawer),
+                readonly(self.vault),
+                writable(self.share_mint),
+                writable(get_associated_token_address(&withdrawer, &self.share_mint)),
+                readonly(spl_token::id()),
+            ];
+            accounts.extend(self.vault_token_accounts());
+            accounts.extend(
+                self.market
+                    .mints
+                    .iter()
+                    .map(|mint| writable(get_associated_token_address(&withdrawer, mint))),
+            );
+            accounts.extend(self.market.mints.iter().copied().map(readonly));
+            accounts
+        }
+
+        fn send(
+            &mut self,
+            accounts: &[AccountMeta],
+            instruction: ArbitrageProgramInstruction,
+        ) -> ProgramResult {
+            self.market
+                .bank
+                .process(&self.market.program_id, accounts, &instruction)
+        }
+
+        fn deposit(&mut self, mint: usize, amount: u64) -> ProgramResult {
+            let accounts = self.deposit_accounts(self.market.payer, mint);
+            self.send(
+                &accounts,
+                ArbitrageProgramInstruction::DepositForShares { amount },
+            )
+        }
+
+        fn shares(&self, owner: &Pubkey) -> u64 {
+            self.market
+                .bank
+                .balance(&get_associated_token_address(owner, &self.share_mint))
+        }
+    }
+
+    #[test]
+    fn test_deposit_and_withdraw() {
+        let mut pooled = Pooled::new();
+        let payer = pooled.market.payer;
+        // The first deposit mints a share per unit of quote value, and the
+        // second asset is worth the same at the pool's price
+        pooled.deposit(0, 1_000_000).unwrap();
+        assert_eq!(pooled.shares(&payer), 1_000_000);
+        pooled.deposit(1, 1_000_000).unwrap();
+        assert_eq!(pooled.shares(&payer), 2_000_000);
+
+        // Half the shares withdraw just under half of each holding, leaving
+        // the virtual shares their cut
+        let accounts = pooled.withdraw_accounts(payer);
+        pooled
+            .send(
+                &accounts,
+                ArbitrageProgramInstruction::WithdrawShares { amount: 1_000_000 },
+            )
+            .unwrap();
+        assert_eq!(pooled.shares(&payer), 1_000_000);
+        for mint in 0..2 {
+            assert_eq!(
+                pooled.market.balance(&payer, mint),
+                Market::BALANCE - 1_000_000 + 499_750
+            );
+        }
+    }
+
+    #[test]
+    fn test_deposit_with_substituted_accounts() {
+        let mut pooled = Pooled::new();
+        let payer = pooled.market.payer;
+        pooled.deposit(0, 1_000_000).unwrap();
+        let [quote_mint, mint] = pooled.market.mints;
+        let accounts = pooled.deposit_accounts(payer, 0);
+
+        // A token account the vault owns, but not its associated one
+        let fake = Pubkey::new_unique();
+        let vault = pooled.vault;
+        pooled
+            .market
+            .bank
+            .add_token_account_at(fake, &quote_mint, &vault, 0);
+        let mut substituted = accounts.clone();
+        substituted[9] = writable(fake);
+        assert_eq!(
+            pooled.send(
+                &substituted,
+                ArbitrageProgramInstruction::DepositForShares { amount: 1_000 },
+            ),
+            Err(ArbitrageProgramError::InvalidAccountsList.into())
+        );
+
+        // A token account the pool owns, holding a lopsided balance to make
+        // the second asset look cheap
+        let pool = pooled.market.pools[0];
+        pooled
+            .market
+            .bank
+            .add_token_account_at(fake, &mint, &pool, u64::MAX / 2);
+        let mut substituted = accounts.clone();
+        substituted[12] = readonly(fake);
+        assert_eq!(
+            pooled.send(
+                &substituted,
+                ArbitrageProgramInstruction::DepositForShares { amount: 1_000 },
+            ),
+            Err(ArbitrageProgramError::InvalidAccountsList.into())
+        );
+
+        // The pool's own token account, but held by another program
+        let pool_token_account = get_associated_token_address(&pool, &mint);
+        pooled
+            .market
+            .bank
+            .accounts
+            .get_mut(&pool_token_account)
+            .unwrap()
+            .owner = Pubkey::new_unique();
+        assert_eq!(
+            pooled.deposit(0, 1_000),
+            Err(ArbitrageProgramError::InvalidAccountsList.into())
+        );
+        pooled
+            .market
+            .bank
+            .accounts
+            .get_mut(&pool_token_account)
+            .unwrap()
+            .owner = spl_token::id();
+
+        // A token program that isn't one
+        let mut substituted = accounts;
+        substituted[6] = readonly(Pubkey::new_unique());
+        assert_eq!(
+            pooled.send(
+                &substituted,
+                ArbitrageProgramInstruction::DepositForShares { amount: 1_000 },
+            ),
+            Err(ArbitrageProgramError::InvalidProgramAccount.into())
+        );
+        pooled.deposit(0, 1_000).unwrap();
+    }
+
+    #[test]
+    fn test_withdraw_with_substituted_accounts() {
+        let mut pooled = Pooled::new();
+        let payer = pooled.market.payer;
+        pooled.deposit(0, 1_000_000).unwrap();
+        let fake = Pubkey::new_unique();
+        let vault = pooled.vault;
+        let quote_mint = pooled.market.mints[0];
+        pooled
+            .market
+            .bank
+            .add_token_account_at(fake, &quote_mint, &vault, 0);
+        let mut accounts = pooled.withdraw_accounts(payer);
+        accounts[5] = writable(fake);
+        assert_eq!(
+            pooled.send(
+                &accounts,
+                ArbitrageProgramInstruction::WithdrawShares { amount: 1_000 },
+            ),
+            Err(ArbitrageProgramError::InvalidAccountsList.into())
+        );
+    }
+
+    #[test]
+    fn test_moving_the_pricing_pool_does_not_pay() {
+        let mut pooled = Pooled::new();
+        let payer = pooled.market.payer;
+        pooled.deposit(0, 1_000_000).unwrap();
+        // Halving the pool's balance of the second asset doubles its spot
+        // price - but a deposit of it is still valued at the reference price
+        let pool_token_account =
+            get_associated_token_address(&pooled.market.pools[0], &pooled.market.mints[1]);
+        pooled
+            .market
+            .bank
+            .set_balance(&pool_token_account, 500_000_000);
+        pooled.deposit(1, 1_000_000).unwrap();
+        assert_eq!(pooled.shares(&payer), 2_000_000);
+    }
+
+    #[test]
+    fn test_donation_does_not_inflate_shares() {
+        let mut pooled = Pooled::new();
+        // The first depositor takes a single share, then donates to the vault
+        // to make each share worth a fortune
+        pooled.deposit(0, 1).unwrap();
+        let vault_token_account =
+            get_associated_token_address(&pooled.vault, &pooled.market.mints[0]);
+        pooled
+            .market
+            .bank
+            .set_balance(&vault_token_account, 10_000_001);
+
+        // A later deposit still mints shares, and redeems for nearly what it paid
+        let depositor = pooled.market.bank.add_wallet();
+        let quote_mint = pooled.market.mints[0];
+        pooled
+            .market
+            .bank
+            .add_ata(&depositor, &quote_mint, 1_000_000);
+        let share_mint = pooled.share_mint;
+        pooled.market.bank.add_ata(&depositor, &share_mint, 0);
+        for mint in pooled.market.mints {
+            if mint != quote_mint {
+                pooled.market.bank.add_ata(&depositor, &mint, 0);
+            }
+        }
+        let accounts = pooled.deposit_accounts(depositor, 0);
+        pooled
+            .send(
+                &accounts,
+                ArbitrageProgramInstruction::DepositForShares { amount: 1_000_000 },
+            )
+            .unwrap();
+        let minted = pooled.shares(&depositor);
+        assert!(minted > 0);
+        let accounts = pooled.withdraw_accounts(depositor);
+        pooled
+            .send(
+                &accounts,
+                ArbitrageProgramInstruction::WithdrawShares { amount: minted },
+            )
+            .unwrap();
+        assert!(pooled.market.balance(&depositor, 0) >= 999_000);
+    }
+}
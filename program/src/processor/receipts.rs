@@ -0,0 +1,191 @@
+//! Processes the creation and closing of per-trader receipts accounts
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::state::{ArbitrageAccount, TradeReceipts};
+use crate::util::create_pda_account;
+
+use super::vault::resolve_trader;
+
+/// Creates a trader's receipts account PDA, for either the payer or the
+/// payer's vault, with the payer as the authority who may close it
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Payer
+/// * Receipts
+/// * System Program
+/// * Vault (Optional - if the account records the payer's vault)
+pub fn process_initialize_receipts(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let receipts_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let trader = resolve_trader(program_id, payer, accounts_iter)?;
+
+    let (receipts_address, bump) = TradeReceipts::address(program_id, &trader);
+    if !receipts_info.key.eq(&receipts_address) {
+        msg!("Expected receipts address: {}", receipts_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    create_pda_account(
+        payer,
+        receipts_info,
+        system_program,
+        program_id,
+        TradeReceipts::LEN,
+        &[TradeReceipts::SEED_PREFIX, trader.as_ref(), &[bump]],
+    )?;
+    TradeReceipts {
+        trader,
+        authority: *payer.key,
+        bump,
+        total_recorded: 0,
+        head: 0,
+        receipts: vec![],
+    }
+    .save(receipts_info)
+}
+
+/// Closes a receipts account once its receipts have been exported, returning
+/// its rent to the authority
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority
+/// * Receipts
+pub fn process_close_receipts(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let receipts_info = next_account_info(accounts_iter)?;
+
+    let receipts = TradeReceipts::load(program_id, receipts_info)?;
+    receipts.assert_authority(authority)?;
+    msg!(
+        "Closing receipts for {} after {} trades",
+        receipts.trader,
+        receipts.total_recorded
+    );
+    let lamports = receipts_info.lamports();
+    **receipts_info.try_borrow_mut_lamports()? = 0;
+    **authority.try_borrow_mut_lamports()? = authority
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ArbitrageProgramError::InvalidProgramAccount)?;
+    receipts_info.data.borrow_mut().fill(0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{pubkey::Pubkey, rent::Rent, system_program};
+
+    use crate::error::ArbitrageProgramError;
+    use crate::state::{TradeReceipts, TradeStats};
+    use crate::test_utils::{readonly, signer, writable, Market};
+    use crate::ArbitrageProgramInstruction;
+
+    fn initialize_receipts(market: &mut Market) -> Pubkey {
+        let (receipts, _) = TradeReceipts::address(&market.program_id, &market.payer);
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &[
+                    signer(market.payer),
+                    writable(receipts),
+                    readonly(system_program::id()),
+                ],
+                &ArbitrageProgramInstruction::InitializeReceipts,
+            )
+            .unwrap();
+        receipts
+    }
+
+    #[test]
+    fn test_initialize_receipts() {
+        let mut market = Market::new();
+        let receipts = initialize_receipts(&mut market);
+        let state: TradeReceipts = market.bank.load(&market.program_id, &receipts);
+        assert_eq!(state.trader, market.payer);
+        assert_eq!(state.authority, market.payer);
+        assert_eq!(state.head, 0);
+        assert!(state.receipts.is_empty());
+        // Only the trader's own receipts address will do
+        let (stats, _) = TradeStats::address(&market.program_id, &market.payer);
+        assert_eq!(
+            market.bank.process(
+                &market.program_id,
+                &[
+                    signer(market.payer),
+                    writable(stats),
+                    readonly(system_program::id()),
+                ],
+                &ArbitrageProgramInstruction::InitializeReceipts,
+            ),
+            Err(ArbitrageProgramError::InvalidProgramAccount.into())
+        );
+    }
+
+    #[test]
+    fn test_trade_writes_receipt() {
+        let mut market = Market::new();
+        let receipts = initialize_receipts(&mut market);
+        let mut accounts = market.trade_accounts();
+        accounts.push(writable(receipts));
+        let ix = market.try_arbitrage(90);
+        market
+            .bank
+            .process(&market.program_id, &accounts, &ix)
+            .unwrap();
+        let state: TradeReceipts = market.bank.load(&market.program_id, &receipts);
+        assert_eq!(state.total_recorded, 1);
+        let receipt = &state.receipts[0];
+        assert_eq!(receipt.sequence, 0);
+        // Bought on Swap #2, where the second mint is cheaper
+        assert_eq!(receipt.buy.venue, market.swaps[1]);
+        assert_eq!(receipt.buy.pay_mint, market.mints[0]);
+        assert_eq!(receipt.sell.venue, market.swaps[0]);
+        assert_eq!(receipt.sell.amount_in, receipt.buy.amount_out);
+        assert_eq!(
+            receipt.profit,
+            market.balance(&market.payer, 0) as i64 - Market::BALANCE as i64
+        );
+    }
+
+    #[test]
+    fn test_close_receipts() {
+        let mut market = Market::new();
+        let receipts = initialize_receipts(&mut market);
+        let rent = market.bank.accounts[&receipts].lamports;
+        assert_eq!(
+            rent,
+            Rent::default().minimum_balance(market.bank.accounts[&receipts].data.len())
+        );
+        // Only the authority can close it
+        let stranger = market.bank.add_wallet();
+        assert_eq!(
+            market.bank.process(
+                &market.program_id,
+                &[signer(stranger), writable(receipts)],
+                &ArbitrageProgramInstruction::CloseReceipts,
+            ),
+            Err(ArbitrageProgramError::InvalidAuthority.into())
+        );
+        let before = market.bank.accounts[&market.payer].lamports;
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &[signer(market.payer), writable(receipts)],
+                &ArbitrageProgramInstruction::CloseReceipts,
+            )
+            .unwrap();
+        assert_eq!(market.bank.accounts[&market.payer].lamports, before + rent);
+        assert_eq!(market.bank.accounts[&receipts].lamports, 0);
+        assert!(market.bank.accounts[&receipts].data.iter().all(|b| *b == 0));
+    }
+}
@@ -0,0 +1,55 @@
+//! Processes the creation of per-trader risk accounts
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::state::{ArbitrageAccount, RiskAccount};
+use crate::util::create_pda_account;
+
+use super::vault::resolve_trader;
+
+/// Creates a trader's risk account PDA, for either the payer or the payer's
+/// vault
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Payer
+/// * Risk Account
+/// * System Program
+/// * Vault (Optional - if the account tracks the payer's vault)
+pub fn process_initialize_risk_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let risk_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let trader = resolve_trader(program_id, payer, accounts_iter)?;
+
+    let (risk_address, bump) = RiskAccount::address(program_id, &trader);
+    if !risk_info.key.eq(&risk_address) {
+        msg!("Expected risk account address: {}", risk_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    create_pda_account(
+        payer,
+        risk_info,
+        system_program,
+        program_id,
+        RiskAccount::LEN,
+        &[RiskAccount::SEED_PREFIX, trader.as_ref(), &[bump]],
+    )?;
+    RiskAccount {
+        trader,
+        bump,
+        window_start: Clock::get()?.unix_timestamp,
+        assets: vec![],
+    }
+    .save(risk_info)
+}
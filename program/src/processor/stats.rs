@@ -0,0 +1,259 @@
+//! Processes the creation and reporting of per-trader stats accounts
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::set_return_data,
+    pubkey::Pubkey,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::state::{AdaptiveTemperature, ArbitrageAccount, TradeStats};
+use crate::util::create_pda_account;
+
+use super::vault::resolve_trader;
+
+/// Creates a trader's stats account PDA, for either the payer or the payer's
+/// vault
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Payer
+/// * Stats
+/// * System Program
+/// * Vault (Optional - if the account tracks the payer's vault)
+pub fn process_initialize_stats(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    count_scans: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let stats_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let trader = resolve_trader(program_id, payer, accounts_iter)?;
+
+    let (stats_address, bump) = TradeStats::address(program_id, &trader);
+    if !stats_info.key.eq(&stats_address) {
+        msg!("Expected stats address: {}", stats_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    create_pda_account(
+        payer,
+        stats_info,
+        system_program,
+        program_id,
+        TradeStats::LEN,
+        &[TradeStats::SEED_PREFIX, trader.as_ref(), &[bump]],
+    )?;
+    TradeStats {
+        trader,
+        bump,
+        count_scans,
+        adaptive_temperature: None,
+        trade_count: 0,
+        no_arbitrage_scans: 0,
+        assets: vec![],
+        pairs: vec![],
+    }
+    .save(stats_info)
+}
+
+/// Turns a trader's adaptive temperature on - starting from the provided
+/// temperature - or off
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Payer
+/// * Stats
+/// * Vault (Optional - if the account tracks the payer's vault)
+pub fn process_set_adaptive_temperature(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    initial_temperature: Option<u8>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let stats_info = next_account_info(accounts_iter)?;
+    let trader = resolve_trader(program_id, payer, accounts_iter)?;
+
+    if !payer.is_signer {
+        msg!("Payer must sign to change the adaptive temperature");
+        return Err(ArbitrageProgramError::InvalidAuthority.into());
+    }
+    let mut stats = TradeStats::load(program_id, stats_info)?;
+    stats.assert_trader(&trader)?;
+    stats.adaptive_temperature = match initial_temperature {
+        Some(temperature) if temperature > 100 => {
+            msg!("Temperature can be at most 100");
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        Some(temperature) => Some(AdaptiveTemperature {
+            temperature,
+            win_streak: 0,
+            loss_streak: 0,
+        }),
+        None => None,
+    };
+    msg!(
+        "Adaptive temperature for {} set to {:?}",
+        trader,
+        initial_temperature
+    );
+    stats.save(stats_info)
+}
+
+/// Emits a page of a trader's stats, both as logs and as the instruction's
+/// return data (Borsh-serialized `StatsPage`)
+///
+/// The whole account is too big for return data, so it's emitted a page at a
+/// time, each page saying how many there are
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Stats
+pub fn process_emit_stats(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    page: u8,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let stats_info = next_account_info(accounts_iter)?;
+
+    let stats = TradeStats::load(program_id, stats_info)?;
+    let page = match stats.page(page) {
+        Some(page) => page,
+        None => {
+            msg!("No page {} of stats", page);
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+    };
+    page.log();
+    set_return_data(
+        &page
+            .try_to_vec()
+            .map_err(|_| ArbitrageProgramError::InvalidProgramAccount)?,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use solana_program::{program::MAX_RETURN_DATA, pubkey::Pubkey};
+
+    use crate::error::ArbitrageProgramError;
+    use crate::state::{
+        AssetStats, PairStats, StatsPage, TemperatureBounds, TradeStats, MAX_STATS_ASSETS,
+        MAX_STATS_PAIRS,
+    };
+    use crate::test_utils::{readonly, return_data, signer, writable, Market};
+    use crate::ArbitrageProgramInstruction;
+
+    #[test]
+    fn test_emit_full_stats() {
+        let mut market = Market::new();
+        let (stats, _) = TradeStats::address(&market.program_id, &market.payer);
+        let mut state: TradeStats = market.bank.load(&market.program_id, &stats);
+        state.trade_count = 100;
+        state.assets = (0..MAX_STATS_ASSETS)
+            .map(|i| AssetStats {
+                mint: Pubkey::new_unique(),
+                volume: i as u64,
+                realised_profit: -(i as i64),
+            })
+            .collect();
+        state.pairs = (0..MAX_STATS_PAIRS)
+            .map(|i| PairStats {
+                key: (i as u64).to_le_bytes(),
+                trade_count: 1,
+                last_slot: i as u64,
+            })
+            .collect();
+        // Far too big to return whole
+        assert!(state.try_to_vec().unwrap().len() > MAX_RETURN_DATA);
+        market.bank.store(&market.program_id, stats, &state);
+
+        let mut assets = vec![];
+        let mut pairs = vec![];
+        for page in 0..4 {
+            market
+                .bank
+                .process(
+                    &market.program_id,
+                    &[readonly(stats)],
+                    &ArbitrageProgramInstruction::EmitStats { page },
+                )
+                .unwrap();
+            let data = return_data().unwrap();
+            assert!(data.len() <= StatsPage::MAX_LEN);
+            let emitted = StatsPage::try_from_slice(&data).unwrap();
+            assert_eq!(emitted.trader, market.payer);
+            assert_eq!(emitted.trade_count, 100);
+            assert_eq!((emitted.page, emitted.page_count), (page, 4));
+            assets.extend(emitted.assets.into_iter().map(|a| a.mint));
+            pairs.extend(emitted.pairs.into_iter().map(|p| p.key));
+        }
+        // Every asset and pair is emitted once, in order
+        assert_eq!(
+            assets,
+            state.assets.iter().map(|a| a.mint).collect::<Vec<_>>()
+        );
+        assert_eq!(pairs, state.pairs.iter().map(|p| p.key).collect::<Vec<_>>());
+        assert_eq!(
+            market.bank.process(
+                &market.program_id,
+                &[readonly(stats)],
+                &ArbitrageProgramInstruction::EmitStats { page: 4 },
+            ),
+            Err(ArbitrageProgramError::InvalidConfig.into())
+        );
+    }
+
+    #[test]
+    fn test_adaptive_temperature_follows_trades() {
+        let mut market = Market::new();
+        market
+            .config(ArbitrageProgramInstruction::SetTemperatureBounds {
+                temperature_bounds: TemperatureBounds {
+                    min: 10,
+                    max: 92,
+                    step: 5,
+                    streak: 1,
+                },
+            })
+            .unwrap();
+        let (stats, _) = TradeStats::address(&market.program_id, &market.payer);
+        let set_adaptive = |market: &mut Market, initial_temperature| {
+            market
+                .bank
+                .process(
+                    &market.program_id,
+                    &[signer(market.payer), writable(stats)],
+                    &ArbitrageProgramInstruction::SetAdaptiveTemperature {
+                        initial_temperature: Some(initial_temperature),
+                    },
+                )
+                .unwrap();
+        };
+        let accounts = market.trade_accounts();
+        let ix = market.try_arbitrage(90);
+
+        // A cold trader passes on the spread, whatever the instruction asks
+        set_adaptive(&mut market, 50);
+        assert_eq!(
+            market.bank.process(&market.program_id, &accounts, &ix),
+            Err(ArbitrageProgramError::NoArbitrage.into())
+        );
+
+        // A profitable round trip warms the trader up, but no further than
+        // the config allows
+        set_adaptive(&mut market, 90);
+        market
+            .bank
+            .process(&market.program_id, &accounts, &ix)
+            .unwrap();
+        let state: TradeStats = market.bank.load(&market.program_id, &stats);
+        let adaptive = state.adaptive_temperature.unwrap();
+        assert_eq!(adaptive.temperature, 92);
+        assert_eq!((adaptive.win_streak, adaptive.loss_streak), (0, 0));
+    }
+}
@@ -0,0 +1,74 @@
+//! Processes the creation and retuning of strategies
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::state::{ArbitrageAccount, Strategy, StrategyParams};
+use crate::util::create_pda_account;
+
+/// Creates one of the payer's strategy PDAs
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner (Payer)
+/// * Strategy
+/// * System Program
+pub fn process_initialize_strategy(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    id: u8,
+    params: StrategyParams,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let strategy_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    params.validate()?;
+    let (strategy_address, bump) = Strategy::address(program_id, owner.key, id);
+    if !strategy_info.key.eq(&strategy_address) {
+        msg!("Expected strategy address: {}", strategy_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    create_pda_account(
+        owner,
+        strategy_info,
+        system_program,
+        program_id,
+        Strategy::LEN,
+        &[Strategy::SEED_PREFIX, owner.key.as_ref(), &[id], &[bump]],
+    )?;
+    msg!("Strategy {} initialized: {:?}", id, params);
+    Strategy {
+        owner: *owner.key,
+        id,
+        bump,
+        params,
+    }
+    .save(strategy_info)
+}
+
+/// Retunes a strategy, taking effect from the next trade that uses it
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner
+/// * Strategy
+pub fn process_set_strategy(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    params: StrategyParams,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let strategy_info = next_account_info(accounts_iter)?;
+
+    let mut strategy = Strategy::load(program_id, strategy_info)?;
+    strategy.assert_owner(owner)?;
+    params.validate()?;
+    msg!("Strategy {} set to: {:?}", strategy.id, params);
+    strategy.params = params;
+    strategy.save(strategy_info)
+}
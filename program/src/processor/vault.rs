@@ -0,0 +1,525 @@
+//! Processes the creation of vaults and deposits into and withdrawals from
+//! them
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::partial_state::PartialMintState;
+use crate::state::{ArbitrageAccount, Vault};
+use crate::util::{assert_token_program, create_pda_account};
+
+/// Creates the payer's vault PDA
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner (Payer)
+/// * Vault
+/// * System Program
+pub fn process_initialize_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    keeper: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    let (vault_address, bump) = Vault::address(program_id, owner.key);
+    if !vault_info.key.eq(&vault_address) {
+        msg!("Expected vault address: {}", vault_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    create_pda_account(
+        owner,
+        vault_info,
+        system_program,
+        program_id,
+        Vault::LEN,
+        &[Vault::SEED_PREFIX, owner.key.as_ref(), &[bump]],
+    )?;
+    Vault {
+        owner: *owner.key,
+        keeper,
+        permissionless: false,
+        bump,
+        shares: None,
+    }
+    .save(vault_info)?;
+    msg!("Vault initialized with keeper: {}", keeper);
+    Ok(())
+}
+
+/// Replaces the hot key allowed to trade a vault's inventory
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner
+/// * Vault
+pub fn process_set_vault_keeper(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    keeper: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+
+    let mut vault = Vault::load(program_id, vault_info)?;
+    vault.assert_owner(owner)?;
+    vault.keeper = keeper;
+    msg!("Vault keeper set to {} by {}", keeper, owner.key);
+    vault.save(vault_info)
+}
+
+/// Opens or closes a vault's inventory to be traded by anyone
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner
+/// * Vault
+pub fn process_set_vault_permissionless(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    permissionless: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+
+    let mut vault = Vault::load(program_id, vault_info)?;
+    vault.assert_owner(owner)?;
+    vault.permissionless = permissionless;
+    msg!(
+        "Vault permissionless trading set to {} by {}",
+        permissionless,
+        owner.key
+    );
+    vault.save(vault_info)
+}
+
+/// Deposits tokens from the owner into the vault, creating the vault's
+/// associated token account for the mint if it doesn't exist yet
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner
+/// * Vault
+/// * Owner's Token Account
+/// * Vault's Token Account
+/// * Mint
+/// * Token Program
+/// * System Program
+/// * Associated Token Program
+pub fn process_deposit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+    let owner_token_account = next_account_info(accounts_iter)?;
+    let vault_token_account = next_account_info(accounts_iter)?;
+    let mint = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let associated_token_program = next_account_info(accounts_iter)?;
+
+    let vault = Vault::load(program_id, vault_info)?;
+    vault.assert_owner(owner)?;
+    vault.assert_not_pooled()?;
+    let (_, decimals) = PartialMintState::try_deserialize(mint)?;
+
+    create_vault_token_account(
+        owner,
+        vault_info,
+        vault_token_account,
+        mint,
+        token_program,
+        system_program,
+        associated_token_program,
+    )?;
+    invoke(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            owner_token_account.key,
+            mint.key,
+            vault_token_account.key,
+            owner.key,
+            &[],
+            amount,
+            decimals,
+        )?,
+        &[
+            owner_token_account.clone(),
+            mint.clone(),
+            vault_token_account.clone(),
+            owner.clone(),
+            token_program.clone(),
+        ],
+    )?;
+    msg!("Deposited {} of mint {}", amount, mint.key);
+    Ok(())
+}
+
+/// Withdraws tokens from the vault to a token account of the owner's
+/// choosing, signed for by the vault's PDA
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Owner
+/// * Vault
+/// * Vault's Token Account
+/// * Destination Token Account
+/// * Mint
+/// * Token Program
+pub fn process_withdraw(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let vault_info = next_account_info(accounts_iter)?;
+    let vault_token_account = next_account_info(accounts_iter)?;
+    let destination_token_account = next_account_info(accounts_iter)?;
+    let mint = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    let vault = Vault::load(program_id, vault_info)?;
+    vault.assert_owner(owner)?;
+    vault.assert_not_pooled()?;
+    assert_token_program(token_program)?;
+    let (_, decimals) = PartialMintState::try_deserialize(mint)?;
+
+    invoke_signed(
+        &spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            vault_token_account.key,
+            mint.key,
+            destination_token_account.key,
+            vault_info.key,
+            &[],
+            amount,
+            decimals,
+        )?,
+        &[
+            vault_token_account.clone(),
+            mint.clone(),
+            destination_token_account.clone(),
+            vault_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[Vault::SEED_PREFIX, vault.owner.as_ref(), &[vault.bump]]],
+    )?;
+    msg!("Withdrew {} of mint {}", amount, mint.key);
+    Ok(())
+}
+
+/// Creates a vault's associated token account for a mint if it doesn't
+/// exist yet, funded by the payer
+pub fn create_vault_token_account<'a>(
+    payer: &AccountInfo<'a>,
+    vault_info: &AccountInfo<'a>,
+    vault_token_account: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    associated_token_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    invoke(
+        &spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            payer.key,
+            vault_info.key,
+            mint.key,
+            token_program.key,
+        ),
+        &[
+            payer.clone(),
+            vault_token_account.clone(),
+            vault_info.clone(),
+            mint.clone(),
+            system_program.clone(),
+            token_program.clone(),
+            associated_token_program.clone(),
+        ],
+    )
+}
+
+/// Resolves whose inventory a per-trader account (risk, stats, ...) is being
+/// created for: the payer's own, or - if a vault is passed as the next
+/// account - the payer's vault's
+pub fn resolve_trader<'a, 'b>(
+    program_id: &Pubkey,
+    payer: &'a AccountInfo<'b>,
+    accounts_iter: &mut impl Iterator<Item = &'a AccountInfo<'b>>,
+) -> Result<Pubkey, ProgramError> {
+    match accounts_iter.next() {
+        Some(vault_info) => {
+            Vault::load(program_id, vault_info)?.assert_owner(payer)?;
+            Ok(*vault_info.key)
+        }
+        None => Ok(*payer.key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{entrypoint::ProgramResult, pubkey::Pubkey, system_program};
+    use spl_associated_token_account::get_associated_token_address;
+
+    use crate::error::ArbitrageProgramError;
+    use crate::state::{FeeSplit, Vault};
+    use crate::test_utils::{readonly, signer, writable, Market};
+    use crate::ArbitrageProgramInstruction;
+
+    /// Creates the payer's vault, with the keeper given
+    fn initialize_vault(market: &mut Market, keeper: Pubkey) -> Pubkey {
+        let (vault, _) = Vault::address(&market.program_id, &market.payer);
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &[
+                    signer(market.payer),
+                    writable(vault),
+                    readonly(system_program::id()),
+                ],
+                &ArbitrageProgramInstruction::InitializeVault { keeper },
+            )
+            .unwrap();
+        vault
+    }
+
+    fn deposit(market: &mut Market, vault: Pubkey, mint: usize, amount: u64) -> ProgramResult {
+        let mint = market.mints[mint];
+        market.bank.process(
+            &market.program_id,
+            &[
+                signer(market.payer),
+                readonly(vault),
+                writable(get_associated_token_address(&market.payer, &mint)),
+                writable(get_associated_token_address(&vault, &mint)),
+                readonly(mint),
+                readonly(spl_token::id()),
+                readonly(system_program::id()),
+                readonly(spl_associated_token_account::id()),
+            ],
+            &ArbitrageProgramInstruction::Deposit { amount },
+        )
+    }
+
+    fn withdraw(
+        market: &mut Market,
+        owner: Pubkey,
+        vault: Pubkey,
+        token_program: Pubkey,
+        amount: u64,
+    ) -> ProgramResult {
+        let mint = market.mints[0];
+        market.bank.process(
+            &market.program_id,
+            &[
+                signer(owner),
+                readonly(vault),
+                writable(get_associated_token_address(&vault, &mint)),
+                writable(get_associated_token_address(&owner, &mint)),
+                readonly(mint),
+                readonly(token_program),
+            ],
+            &ArbitrageProgramInstruction::Withdraw { amount },
+        )
+    }
+
+    #[test]
+    fn test_deposit_and_withdraw() {
+        let mut market = Market::new();
+        let payer = market.payer;
+        let vault = initialize_vault(&mut market, payer);
+        deposit(&mut market, vault, 0, 1_000).unwrap();
+        assert_eq!(market.balance(&vault, 0), 1_000);
+        assert_eq!(market.balance(&payer, 0), Market::BALANCE - 1_000);
+
+        // Only the owner can take funds out, and only through the token
+        // program
+        let stranger = market.bank.add_wallet();
+        let mint = market.mints[0];
+        market.bank.add_ata(&stranger, &mint, 0);
+        assert_eq!(
+            withdraw(&mut market, stranger, vault, spl_token::id(), 400),
+            Err(ArbitrageProgramError::InvalidAuthority.into())
+        );
+        let fake_token_program = Pubkey::new_unique();
+        market.bank.add_program(fake_token_program);
+        assert_eq!(
+            withdraw(&mut market, payer, vault, fake_token_program, 400),
+            Err(ArbitrageProgramError::InvalidProgramAccount.into())
+        );
+
+        withdraw(&mut market, payer, vault, spl_token::id(), 400).unwrap();
+        assert_eq!(market.balance(&vault, 0), 600);
+        assert_eq!(market.balance(&payer, 0), Market::BALANCE - 600);
+    }
+
+    #[test]
+    fn test_vault_arbitrage() {
+        let mut market = Market::new();
+        let keeper = market.bank.add_wallet();
+        let vault = initialize_vault(&mut market, keeper);
+        for mint in 0..2 {
+            deposit(&mut market, vault, mint, Market::BALANCE / 2).unwrap();
+        }
+        market.initialize_trader(Some(vault));
+        let ix = ArbitrageProgramInstruction::TryVaultArbitrage {
+            swap_1_program_id: market.swaps[0],
+            swap_2_program_id: market.swaps[1],
+            concurrency: 2,
+            temperature: 90,
+        };
+
+        // Anyone but the keeper or owner is refused
+        let stranger = market.bank.add_wallet();
+        let mut accounts = market.trade_accounts_for(&vault, &[readonly(vault)]);
+        accounts[0] = signer(stranger);
+        assert_eq!(
+            market.bank.process(&market.program_id, &accounts, &ix),
+            Err(ArbitrageProgramError::InvalidAuthority.into())
+        );
+
+        // The keeper trades the vault's inventory, not its own
+        accounts[0] = signer(keeper);
+        market
+            .bank
+            .process(&market.program_id, &accounts, &ix)
+            .unwrap();
+        assert!(market.balance(&vault, 0) > Market::BALANCE / 2);
+        assert_eq!(market.balance(&market.payer, 0), Market::BALANCE / 2);
+        assert_eq!(market.balance(&market.payer, 1), Market::BALANCE / 2);
+    }
+
+    #[test]
+    fn test_permissionless_keeper_reward() {
+        let mut market = Market::new();
+        let payer = market.payer;
+        let vault = initialize_vault(&mut market, payer);
+        for mint in 0..2 {
+            deposit(&mut market, vault, mint, Market::BALANCE / 2).unwrap();
+        }
+        market.initialize_trader(Some(vault));
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &[signer(payer), writable(vault)],
+                &ArbitrageProgramInstruction::SetVaultPermissionless {
+                    permissionless: true,
+                },
+            )
+            .unwrap();
+        market
+            .config(ArbitrageProgramInstruction::SetFeeSplit {
+                fee_split: FeeSplit {
+                    treasury: Pubkey::new_unique(),
+                    treasury_bps: 0,
+                    keeper_bps: 1_000,
+                },
+            })
+            .unwrap();
+
+        // Anyone can crank the vault, and is paid a tenth of its profit
+        let keeper = market.bank.add_wallet();
+        for mint in market.mints {
+            market.bank.add_ata(&keeper, &mint, 0);
+        }
+        let mut accounts = market.trade_accounts_for(&vault, &[readonly(vault)]);
+        accounts[0] = signer(keeper);
+        accounts.extend(
+            market
+                .mints
+                .iter()
+                .map(|mint| writable(get_associated_token_address(&keeper, mint))),
+        );
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &accounts,
+                &ArbitrageProgramInstruction::TryVaultArbitrage {
+                    swap_1_program_id: market.swaps[0],
+                    swap_2_program_id: market.swaps[1],
+                    concurrency: 2,
+                    temperature: 90,
+                },
+            )
+            .unwrap();
+        let reward = market.balance(&keeper, 0);
+        let retained = market.balance(&vault, 0) - Market::BALANCE / 2;
+        assert!(reward > 0);
+        assert_eq!(reward, (reward + retained) / 10);
+        assert_eq!(market.balance(&keeper, 1), 0);
+    }
+
+    #[test]
+    fn test_profit_split_between_treasury_and_keeper() {
+        let mut market = Market::new();
+        let keeper = market.bank.add_wallet();
+        let vault = initialize_vault(&mut market, keeper);
+        for mint in 0..2 {
+            deposit(&mut market, vault, mint, Market::BALANCE / 2).unwrap();
+        }
+        market.initialize_trader(Some(vault));
+        let treasury = market.bank.add_wallet();
+        market
+            .config(ArbitrageProgramInstruction::SetFeeSplit {
+                fee_split: FeeSplit {
+                    treasury,
+                    treasury_bps: 2_000,
+                    keeper_bps: 1_000,
+                },
+            })
+            .unwrap();
+        for owner in [treasury, keeper] {
+            for mint in market.mints {
+                market.bank.add_ata(&owner, &mint, 0);
+            }
+        }
+        let mut accounts = market.trade_accounts_for(&vault, &[readonly(vault)]);
+        accounts[0] = signer(keeper);
+        for owner in [treasury, keeper] {
+            accounts.extend(
+                market
+                    .mints
+                    .iter()
+                    .map(|mint| writable(get_associated_token_address(&owner, mint))),
+            );
+        }
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &accounts,
+                &ArbitrageProgramInstruction::TryVaultArbitrage {
+                    swap_1_program_id: market.swaps[0],
+                    swap_2_program_id: market.swaps[1],
+                    concurrency: 2,
+                    temperature: 90,
+                },
+            )
+            .unwrap();
+
+        // The treasury takes a fifth of the profit and the keeper a tenth,
+        // leaving the rest with the vault
+        let treasury_fee = market.balance(&treasury, 0);
+        let keeper_fee = market.balance(&keeper, 0);
+        let retained = market.balance(&vault, 0) - Market::BALANCE / 2;
+        let profit = treasury_fee + keeper_fee + retained;
+        assert!(keeper_fee > 0);
+        assert_eq!(treasury_fee, profit * 2_000 / 10_000);
+        assert_eq!(keeper_fee, profit * 1_000 / 10_000);
+        assert_eq!(retained, profit - treasury_fee - keeper_fee);
+        assert_eq!(market.balance(&treasury, 1), 0);
+        assert_eq!(market.balance(&keeper, 1), 0);
+    }
+}
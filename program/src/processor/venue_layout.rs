@@ -0,0 +1,269 @@
+//! Processes writing the on-chain layouts of registered venues
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::error::ArbitrageProgramError;
+use crate::state::{ArbitrageAccount, ArbitrageConfig, VenueLayout};
+use crate::util::create_pda_account;
+
+/// Creates - or overwrites - a swap program's layout PDA, describing how a
+/// registered venue for it is traded
+///
+/// The layout only takes effect for a venue of the `Registered` kind on the
+/// config's allowlist, so a swap program still has to be approved before
+/// any signer privileges are forwarded to it. Nor can it be traded by until
+/// the config's timelock has passed, so a venue can't be pointed somewhere
+/// new without notice - in the meantime, the venue doesn't trade at all
+///
+/// Note: accounts must be provided in a very specific order:
+/// * Authority (Payer)
+/// * Config
+/// * Venue Layout
+/// * System Program
+pub fn process_set_venue_layout(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    layout: VenueLayout,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let layout_info = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    let config = ArbitrageConfig::load(program_id, config_info)?;
+    let signers = config.assert_authority(accounts)?;
+    layout.validate()?;
+    let effective_slot = Clock::get()?.slot.saturating_add(config.timelock_slots);
+
+    let (layout_address, bump) = VenueLayout::address(program_id, &layout.program_id);
+    if !layout_info.key.eq(&layout_address) {
+        msg!("Expected venue layout address: {}", layout_address);
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+    }
+    if layout_info.data_is_empty() {
+        create_pda_account(
+            authority,
+            layout_info,
+            system_program,
+            program_id,
+            VenueLayout::LEN,
+            &[
+                VenueLayout::SEED_PREFIX,
+                layout.program_id.as_ref(),
+                &[bump],
+            ],
+        )?;
+    } else {
+        // Make sure we're overwriting a layout, not some other account
+        VenueLayout::load(program_id, layout_info)?;
+    }
+    msg!(
+        "Venue layout set for {} by {:?}, tradable from slot {}",
+        layout.program_id,
+        signers,
+        effective_slot
+    );
+    VenueLayout {
+        bump,
+        effective_slot,
+        ..layout
+    }
+    .save(layout_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{
+        entrypoint::ProgramResult, hash::hash, instruction::Instruction, pubkey::Pubkey,
+        system_program,
+    };
+
+    use crate::error::ArbitrageProgramError;
+    use crate::state::{
+        AccountSlot, ArbitrageConfig, ArgSlot, Curve, FeeModel, PoolSeed, VenueAccountCheck,
+        VenueKind, VenueLayout,
+    };
+    use crate::test_utils::{
+        cpis, readonly, set_clock, signer, writable, Market, TestAccount, LIQUIDITY_POOL_SEED,
+    };
+    use crate::ArbitrageProgramInstruction;
+
+    /// The Anchor swap's own layout, as a registered venue would describe it
+    fn set_anchor_layout(
+        market: &mut Market,
+        accounts: Vec<AccountSlot>,
+        venue_accounts: Vec<VenueAccountCheck>,
+    ) -> ProgramResult {
+        let (layout, _) = VenueLayout::address(&market.program_id, &market.swaps[0]);
+        market.bank.process(
+            &market.program_id,
+            &[
+                signer(market.payer),
+                readonly(ArbitrageConfig::address(&market.program_id).0),
+                writable(layout),
+                readonly(system_program::id()),
+            ],
+            &ArbitrageProgramInstruction::SetVenueLayout {
+                program_id: market.swaps[0],
+                discriminator: hash(b"global:swap").to_bytes()[..8].to_vec(),
+                accounts,
+                args: vec![ArgSlot::Amount],
+                venue_accounts,
+                fee: FeeModel::OnInput { bps: 0 },
+                curve: Curve::ConstantProduct,
+            },
+        )
+    }
+
+    fn anchor_accounts() -> Vec<AccountSlot> {
+        vec![
+            AccountSlot::Pool,
+            AccountSlot::ReceiveMint,
+            AccountSlot::PoolReceive,
+            AccountSlot::PayerReceive,
+            AccountSlot::PayMint,
+            AccountSlot::PoolPay,
+            AccountSlot::PayerPay,
+            AccountSlot::Authority,
+            AccountSlot::TokenProgram,
+            AccountSlot::SystemProgram,
+            AccountSlot::AssociatedTokenProgram,
+        ]
+    }
+
+    /// Approves Swap #1 as a registered venue, traded by its layout
+    fn register_swap_1(market: &mut Market) {
+        market
+            .config(ArbitrageProgramInstruction::AddVenue {
+                program_id: market.swaps[0],
+                pool_seeds: vec![PoolSeed::Literal(LIQUIDITY_POOL_SEED.to_vec())],
+                fee_bps: 0,
+                kind: VenueKind::Registered,
+                curve: Curve::ConstantProduct,
+            })
+            .unwrap();
+    }
+
+    /// The CPIs into Swap #1 made since the `from`th
+    fn swap_1_cpis(market: &Market, from: usize) -> Vec<Instruction> {
+        cpis()
+            .into_iter()
+            .skip(from)
+            .filter(|ix| ix.program_id == market.swaps[0])
+            .collect()
+    }
+
+    #[test]
+    fn test_registered_venue_builds_anchor_cpi() {
+        let mut market = Market::new();
+        let ix = market.try_arbitrage(90);
+
+        // What the Anchor swap's own adapter sends
+        let accounts = market.bank.accounts.clone();
+        market
+            .bank
+            .process(&market.program_id, &market.trade_accounts(), &ix)
+            .unwrap();
+        let expected = swap_1_cpis(&market, 0);
+        assert!(!expected.is_empty());
+        market.bank.accounts = accounts;
+
+        // Registering Swap #1 behind the timelock
+        register_swap_1(&mut market);
+        market
+            .config(ArbitrageProgramInstruction::SetTimelock { timelock_slots: 10 })
+            .unwrap();
+        set_anchor_layout(&mut market, anchor_accounts(), vec![]).unwrap();
+        let (layout, _) = VenueLayout::address(&market.program_id, &market.swaps[0]);
+        let state: VenueLayout = market.bank.load(&market.program_id, &layout);
+        assert_eq!(state.effective_slot, 11);
+
+        // The layout can't be traded by until the timelock has passed
+        let accounts = market.trade_accounts_for(&market.payer, &[readonly(layout)]);
+        assert_eq!(
+            market.bank.process(&market.program_id, &accounts, &ix),
+            Err(ArbitrageProgramError::VenueNotAllowed.into())
+        );
+        set_clock(11, 1_700_000_011);
+        let from = cpis().len();
+        market
+            .bank
+            .process(&market.program_id, &accounts, &ix)
+            .unwrap();
+        assert_eq!(swap_1_cpis(&market, from), expected);
+    }
+
+    #[test]
+    fn test_venue_account_out_of_range() {
+        let mut market = Market::new();
+        let mut accounts = anchor_accounts();
+        accounts.push(AccountSlot::VenueAccount(1));
+        assert_eq!(
+            set_anchor_layout(&mut market, accounts.clone(), vec![VenueAccountCheck::Any]),
+            Err(ArbitrageProgramError::InvalidConfig.into())
+        );
+        set_anchor_layout(&mut market, accounts, vec![VenueAccountCheck::Any; 2]).unwrap();
+    }
+
+    #[test]
+    fn test_venue_accounts_checked_against_layout() {
+        let mut market = Market::new();
+        register_swap_1(&mut market);
+        let mut accounts = anchor_accounts();
+        accounts.extend([AccountSlot::VenueAccount(0), AccountSlot::VenueAccount(1)]);
+        let pinned = market.bank.add_wallet();
+        let swap_1 = market.swaps[0];
+        set_anchor_layout(
+            &mut market,
+            accounts,
+            vec![
+                VenueAccountCheck::Key(pinned),
+                VenueAccountCheck::Owner(swap_1),
+            ],
+        )
+        .unwrap();
+        let owned = Pubkey::new_unique();
+        market.bank.accounts.insert(
+            owned,
+            TestAccount {
+                owner: swap_1,
+                ..TestAccount::default()
+            },
+        );
+        let stranger = market.bank.add_wallet();
+        let (layout, _) = VenueLayout::address(&market.program_id, &swap_1);
+        let metas = market.trade_accounts_for(&market.payer, &[readonly(layout)]);
+        let trade_accounts = |venue_accounts: [Pubkey; 2]| {
+            let mut metas = metas.clone();
+            metas.extend(venue_accounts.map(readonly));
+            metas
+        };
+        let ix = market.try_arbitrage(90);
+
+        // Neither another account in place of the pinned one, nor one owned
+        // by some other program, is forwarded to the venue
+        for venue_accounts in [[stranger, owned], [pinned, stranger]] {
+            assert_eq!(
+                market
+                    .bank
+                    .process(&market.program_id, &trade_accounts(venue_accounts), &ix),
+                Err(ArbitrageProgramError::InvalidAccountsList.into())
+            );
+        }
+        market
+            .bank
+            .process(&market.program_id, &trade_accounts([pinned, owned]), &ix)
+            .unwrap();
+        let swaps = swap_1_cpis(&market, 0);
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].accounts[11].pubkey, pinned);
+        assert_eq!(swaps[0].accounts[12].pubkey, owned);
+    }
+}
@@ -0,0 +1,593 @@
+//! The arbitrage program's global config
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::{AccountKind, ArbitrageAccount, MintRegistry, RegisteredMint};
+use crate::error::ArbitrageProgramError;
+use crate::stable_swap::MAX_AMPLIFICATION;
+
+/// The most swap programs the config can allowlist at once
+pub const MAX_VENUES: usize = 16;
+
+/// The longest literal seed a venue's pool seed template can hold
+pub const MAX_POOL_SEED_LEN: usize = 32;
+
+/// The most seeds a venue's pool seed template can hold
+pub const MAX_POOL_SEEDS: usize = 4;
+
+/// The loss window a new config starts with: one day
+pub const DEFAULT_LOSS_WINDOW_SECONDS: i64 = 86_400;
+
+/// The most signers a multisig authority can have
+pub const MAX_MULTISIG_SIGNERS: usize = 11;
+
+/// The most config changes that can be queued at once
+pub const MAX_PENDING_CHANGES: usize = 8;
+
+/// The global config for the arbitrage program, holding the authority that
+/// manages it and the allowlist of swap programs we're willing to forward our
+/// signer privileges to
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct ArbitrageConfig {
+    /// The only key - or set of keys - allowed to modify this config
+    pub authority: Authority,
+    /// Bump seed of the config's PDA
+    pub bump: u8,
+    /// Global pause switch: while set, no arbitrage will be attempted
+    pub paused: bool,
+    /// How long - in seconds - each trader's loss window runs before their
+    /// window losses are reset
+    pub loss_window_seconds: i64,
+    /// How many slots must pass after a pair is traded before it can be
+    /// traded again
+    pub pair_cooldown_slots: u64,
+    /// How the profit of each round trip is split off from the trader
+    pub fee_split: FeeSplit,
+    /// The limits within which adaptive temperatures move
+    pub temperature_bounds: TemperatureBounds,
+    /// How many slots a queued config change must wait before it can be
+    /// applied - with no delay, changes apply as soon as they're made
+    pub timelock_slots: u64,
+    /// Config changes waiting out the timelock
+    pub pending_changes: Vec<PendingChange>,
+    /// The authority the current one has offered to hand the config over
+    /// to, which must accept before it takes over
+    pub pending_authority: Option<Authority>,
+    /// The approved swap programs
+    pub venues: Vec<Venue>,
+}
+
+/// Who may modify the config
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Authority {
+    /// A single key, which must sign
+    Single(Pubkey),
+    /// A set of keys, at least `threshold` of which must sign the same
+    /// instruction
+    Multisig { threshold: u8, signers: Vec<Pubkey> },
+}
+
+impl Authority {
+    const LEN: usize = 1 + 1 + (4 + MAX_MULTISIG_SIGNERS * 32);
+
+    /// Asserts a multisig's threshold can be met, and that it doesn't list
+    /// any signer twice
+    pub fn validate(&self) -> ProgramResult {
+        if let Authority::Multisig { threshold, signers } = self {
+            if signers.len() > MAX_MULTISIG_SIGNERS {
+                msg!(
+                    "A multisig can have at most {} signers",
+                    MAX_MULTISIG_SIGNERS
+                );
+                return Err(ArbitrageProgramError::InvalidConfig.into());
+            }
+            if *threshold == 0 || *threshold as usize > signers.len() {
+                msg!("Multisig threshold must be between 1 and {}", signers.len());
+                return Err(ArbitrageProgramError::InvalidConfig.into());
+            }
+            if signers
+                .iter()
+                .enumerate()
+                .any(|(i, signer)| signers[..i].contains(signer))
+            {
+                msg!("Multisig signers must be unique");
+                return Err(ArbitrageProgramError::InvalidConfig.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Asserts enough of the authority's keys have signed the transaction,
+    /// each appearing somewhere in the instruction's accounts
+    ///
+    /// Returns the keys that were counted, which is who to attribute the
+    /// instruction to - the first account alone proves nothing under a
+    /// multisig
+    pub fn assert_signed(&self, accounts: &[AccountInfo]) -> Result<Vec<Pubkey>, ProgramError> {
+        let (threshold, signers) = match self {
+            Authority::Single(key) => (1, std::slice::from_ref(key)),
+            Authority::Multisig { threshold, signers } => (*threshold as usize, &signers[..]),
+        };
+        let signed: Vec<Pubkey> = signers
+            .iter()
+            .filter(|signer| accounts.iter().any(|a| a.is_signer && a.key.eq(signer)))
+            .copied()
+            .collect();
+        if signed.len() < threshold {
+            msg!("Expected config authority: {:?}", self);
+            msg!("Got {} of {} required signatures", signed.len(), threshold);
+            return Err(ArbitrageProgramError::InvalidAuthority.into());
+        }
+        Ok(signed)
+    }
+}
+
+/// A change to the config that's subject to the timelock
+///
+/// The pause switch and venue circuit breakers aren't, so they can always be
+/// thrown the moment something looks wrong
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub enum ConfigChange {
+    /// Approves a venue, or updates its pool seed and fee tier
+    AddVenue(Venue),
+    /// Removes a venue from the allowlist
+    RemoveVenue(Pubkey),
+    /// Sets how long each trader's loss window runs, in seconds
+    SetLossWindow(i64),
+    /// Sets how many slots must pass before a traded pair can be traded again
+    SetPairCooldown(u64),
+    /// Sets how each round trip's profit is split off from the trader
+    SetFeeSplit(FeeSplit),
+    /// Sets how many slots queued changes must wait
+    SetTimelock(u64),
+    /// Sets the limits within which adaptive temperatures move
+    SetTemperatureBounds(TemperatureBounds),
+    /// Registers a mint in the mint registry, when doing so loosens what can
+    /// be traded
+    SetMint(RegisteredMint),
+}
+
+impl ConfigChange {
+    /// Whether the change is made to the mint registry rather than the config
+    fn is_mint_change(&self) -> bool {
+        matches!(self, ConfigChange::SetMint(_))
+    }
+}
+
+/// A queued config change, and the slot from which it can be applied
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct PendingChange {
+    pub change: ConfigChange,
+    pub effective_slot: u64,
+}
+
+impl PendingChange {
+    // The largest change is a venue
+    const LEN: usize = 1 + Venue::LEN + 8;
+}
+
+/// The shares of each round trip's profit swept out of the trading account
+/// right after the sell leg
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default)]
+pub struct FeeSplit {
+    /// The owner of the treasury's token accounts
+    pub treasury: Pubkey,
+    /// The share of profit sent to the treasury, in basis points
+    pub treasury_bps: u16,
+    /// The share of profit sent to the keeper who submitted the trade, in
+    /// basis points
+    ///
+    /// Only taken when trading from a vault, since otherwise the keeper is
+    /// already the trader
+    pub keeper_bps: u16,
+}
+
+impl FeeSplit {
+    const LEN: usize = 32 + 2 + 2;
+
+    /// The treasury's share of a profit
+    pub fn treasury_fee(&self, profit: u64) -> u64 {
+        (profit as u128 * self.treasury_bps as u128 / 10_000) as u64
+    }
+
+    /// The keeper's share of a profit
+    pub fn keeper_fee(&self, profit: u64) -> u64 {
+        (profit as u128 * self.keeper_bps as u128 / 10_000) as u64
+    }
+}
+
+/// The limits within which a stats account's adaptive temperature moves, and
+/// how quickly it moves within them
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct TemperatureBounds {
+    /// The lowest - most cautious - temperature an adaptive trader can fall to
+    pub min: u8,
+    /// The highest - most aggressive - temperature an adaptive trader can
+    /// rise to
+    pub max: u8,
+    /// How far the temperature moves after each run of outcomes
+    pub step: u8,
+    /// How many outcomes in a row - profitable or not - it takes to move the
+    /// temperature
+    pub streak: u8,
+}
+
+impl TemperatureBounds {
+    const LEN: usize = 1 + 1 + 1 + 1;
+
+    /// Asserts the bounds are ordered, at most 100, and actually move
+    pub fn validate(&self) -> ProgramResult {
+        if self.min > self.max || self.max > 100 {
+            msg!("Temperature bounds must satisfy min <= max <= 100");
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        if self.step == 0 || self.streak == 0 {
+            msg!("Temperature step and streak must be positive");
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        Ok(())
+    }
+
+    /// Keeps a temperature within the bounds
+    pub fn clamp(&self, temperature: u8) -> u8 {
+        temperature.clamp(self.min, self.max)
+    }
+}
+
+impl Default for TemperatureBounds {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: 100,
+            step: 1,
+            streak: 3,
+        }
+    }
+}
+
+/// An approved swap program (venue) the arbitrage program may CPI into
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct Venue {
+    /// The swap program's ID
+    pub program_id: Pubkey,
+    /// The seeds the swap program derives its Liquidity Pools from, in order
+    pub pool_seeds: Vec<PoolSeed>,
+    /// Bump seed of the venue's Liquidity Pool, cached when the venue is
+    /// approved - if its pool seeds name a single pool rather than one per
+    /// pair of mints
+    pub pool_bump: Option<u8>,
+    /// The swap's fee tier, in basis points of the amount paid
+    pub fee_bps: u16,
+    /// Circuit breaker: while set, the venue stays on the allowlist but the
+    /// bot won't trade against it
+    pub disabled: bool,
+    /// Which kind of swap program the venue is, and so how to trade on it
+    pub kind: VenueKind,
+    /// The curve the venue's pools price with
+    ///
+    /// SPL Token Swap, CLMM and order-book pools price with their own curves,
+    /// and registered venues with their layout's, so this is only used for
+    /// the Anchor swap
+    pub curve: Curve,
+}
+
+impl Venue {
+    const LEN: usize = 32 + (4 + MAX_POOL_SEEDS * PoolSeed::LEN) + (1 + 1) + 2 + 1 + 1 + Curve::LEN;
+
+    /// Fills in the venue's pool seed template, with the mints of the pool
+    /// if it has a pool for each pair
+    ///
+    /// Returns `None` if the template needs mints but none were provided
+    pub fn resolve_pool_seeds(&self, mints: Option<(&Pubkey, &Pubkey)>) -> Option<Vec<Vec<u8>>> {
+        self.pool_seeds
+            .iter()
+            .map(|seed| match seed {
+                PoolSeed::Literal(bytes) => Some(bytes.clone()),
+                PoolSeed::MintA => mints.map(|(a, _)| a.to_bytes().to_vec()),
+                PoolSeed::MintB => mints.map(|(_, b)| b.to_bytes().to_vec()),
+                PoolSeed::FeeTier => Some(self.fee_bps.to_le_bytes().to_vec()),
+            })
+            .collect()
+    }
+}
+
+/// One seed of a venue's pool seed template
+///
+/// The Anchor swap from the tutorial derives its one pool from
+/// `[Literal("liquidity_pool")]`, while a swap with a pool per pair and fee
+/// tier might use `[Literal("pool"), MintA, MintB, FeeTier]`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum PoolSeed {
+    /// Fixed bytes
+    Literal(Vec<u8>),
+    /// The first of the pool's two mints, in the order the pool records them
+    MintA,
+    /// The second of the pool's two mints
+    MintB,
+    /// The venue's fee tier, as little-endian `u16` basis points
+    FeeTier,
+}
+
+impl PoolSeed {
+    const LEN: usize = 1 + (4 + MAX_POOL_SEED_LEN);
+}
+
+/// The pricing curve of a venue's pools
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    /// `x * y = k`
+    ConstantProduct,
+    /// The StableSwap invariant, for pools of pegged assets
+    StableSwap {
+        /// How tightly the pool holds its assets to one-for-one
+        amplification: u64,
+    },
+}
+
+impl Curve {
+    const LEN: usize = 1 + 8;
+}
+
+/// The kinds of swap program the arbitrage program knows how to trade on
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VenueKind {
+    /// The Anchor swap program from the Solana Developers tutorial, whose
+    /// single Liquidity Pool is derived from the venue's pool seed
+    AnchorSwap,
+    /// The canonical SPL Token Swap program, whose pools are each a swap
+    /// account holding two assets
+    SplTokenSwap,
+    /// A concentrated-liquidity pool program, laid out like the mock in
+    /// `mocks/clmm-pool`
+    Clmm,
+    /// A central limit order book, traded on with immediate-or-cancel orders
+    /// and laid out like the mock in `mocks/order-book`
+    OrderBook,
+    /// An Anchor-style swap fork whose `swap` instruction and pricing are
+    /// described by its on-chain `VenueLayout`, rather than by code
+    Registered,
+}
+
+impl ArbitrageAccount for ArbitrageConfig {
+    const KIND: AccountKind = AccountKind::Config;
+    const LEN: usize = 1
+        + Authority::LEN
+        + 1
+        + 1
+        + 8
+        + 8
+        + FeeSplit::LEN
+        + TemperatureBounds::LEN
+        + 8
+        + (4 + MAX_PENDING_CHANGES * PendingChange::LEN)
+        + (1 + Authority::LEN)
+        + (4 + MAX_VENUES * Venue::LEN);
+}
+
+impl ArbitrageConfig {
+    pub const SEED_PREFIX: &'static [u8] = b"config";
+
+    /// Derives the address of the config PDA
+    pub fn address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX], program_id)
+    }
+
+    /// Asserts the config's authority has signed the transaction - or, for a
+    /// multisig, enough of its signers - among the instruction's accounts,
+    /// returning the signers that were counted
+    pub fn assert_authority(&self, accounts: &[AccountInfo]) -> Result<Vec<Pubkey>, ProgramError> {
+        self.authority.assert_signed(accounts)
+    }
+
+    /// Asserts the global pause switch is off
+    pub fn assert_not_paused(&self) -> ProgramResult {
+        if self.paused {
+            msg!("Arbitrage is paused");
+            return Err(ArbitrageProgramError::Paused.into());
+        }
+        Ok(())
+    }
+
+    /// Looks up an approved venue by its program ID
+    pub fn venue(&self, program_id: &Pubkey) -> Result<&Venue, ProgramError> {
+        self.venues
+            .iter()
+            .find(|v| v.program_id.eq(program_id))
+            .ok_or_else(|| {
+                msg!("Swap program not on the allowlist: {}", program_id);
+                ArbitrageProgramError::VenueNotAllowed.into()
+            })
+    }
+
+    /// Adds a venue to the allowlist, or replaces it if it's already there
+    ///
+    /// Replacing a venue keeps its circuit breaker as it was, and its pool bump
+    /// is worked out here, whatever was provided
+    pub fn upsert_venue(&mut self, mut venue: Venue) -> ProgramResult {
+        if venue.pool_seeds.len() > MAX_POOL_SEEDS {
+            msg!(
+                "Pool seed template can hold at most {} seeds",
+                MAX_POOL_SEEDS
+            );
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        for seed in &venue.pool_seeds {
+            if let PoolSeed::Literal(bytes) = seed {
+                if bytes.len() > MAX_POOL_SEED_LEN {
+                    msg!("Pool seeds can be at most {} bytes", MAX_POOL_SEED_LEN);
+                    return Err(ArbitrageProgramError::InvalidConfig.into());
+                }
+            }
+        }
+        if venue.fee_bps > 10_000 {
+            msg!("Fee tier can be at most 10,000 basis points");
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        if let Curve::StableSwap { amplification } = venue.curve {
+            if amplification == 0 || amplification > MAX_AMPLIFICATION {
+                msg!("Amplification must be between 1 and {}", MAX_AMPLIFICATION);
+                return Err(ArbitrageProgramError::InvalidConfig.into());
+            }
+        }
+        venue.pool_bump = match venue.resolve_pool_seeds(None) {
+            Some(seeds) => {
+                let seeds: Vec<&[u8]> = seeds.iter().map(|s| s.as_slice()).collect();
+                Some(Pubkey::find_program_address(&seeds, &venue.program_id).1)
+            }
+            None => None,
+        };
+        match self
+            .venues
+            .iter_mut()
+            .find(|v| v.program_id.eq(&venue.program_id))
+        {
+            Some(existing) => {
+                *existing = Venue {
+                    disabled: existing.disabled,
+                    ..venue
+                }
+            }
+            None => {
+                if self.venues.len() >= MAX_VENUES {
+                    msg!("Allowlist can hold at most {} venues", MAX_VENUES);
+                    return Err(ArbitrageProgramError::AccountFull.into());
+                }
+                self.venues.push(venue);
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues a change to wait out the timelock - or, with no timelock, makes
+    /// it straight away
+    ///
+    /// The change is checked against the current config before it's queued,
+    /// so obviously invalid changes are refused up front
+    pub fn queue_change(
+        &mut self,
+        change: ConfigChange,
+        slot: u64,
+        registry: Option<&mut MintRegistry>,
+    ) -> ProgramResult {
+        if self.timelock_slots == 0 {
+            return self.apply_change(change, registry);
+        }
+        self.clone()
+            .apply_change(change.clone(), registry.cloned().as_mut())?;
+        if self.pending_changes.len() >= MAX_PENDING_CHANGES {
+            msg!("At most {} changes can be queued", MAX_PENDING_CHANGES);
+            return Err(ArbitrageProgramError::AccountFull.into());
+        }
+        let effective_slot = slot.saturating_add(self.timelock_slots);
+        msg!("Queued {:?} until slot {}", change, effective_slot);
+        self.pending_changes.push(PendingChange {
+            change,
+            effective_slot,
+        });
+        Ok(())
+    }
+
+    /// Applies every queued change whose timelock has passed, in the order
+    /// they were queued
+    ///
+    /// A change that's no longer valid is dropped rather than applied, so it
+    /// can't hold up the rest of the queue. Changes to the mint registry stay
+    /// queued until the registry is provided
+    pub fn apply_pending_changes(&mut self, slot: u64, mut registry: Option<&mut MintRegistry>) {
+        let (ready, pending) = std::mem::take(&mut self.pending_changes)
+            .into_iter()
+            .partition::<Vec<_>, _>(|p| {
+                p.effective_slot <= slot && (registry.is_some() || !p.change.is_mint_change())
+            });
+        self.pending_changes = pending;
+        for pending in ready {
+            if self
+                .apply_change(pending.change, registry.as_deref_mut())
+                .is_err()
+            {
+                msg!("Dropped a change that is no longer valid");
+            }
+        }
+    }
+
+    /// Makes a change to the config - or to the mint registry, which must be
+    /// provided for mint changes
+    pub fn apply_change(
+        &mut self,
+        change: ConfigChange,
+        registry: Option<&mut MintRegistry>,
+    ) -> ProgramResult {
+        msg!("Applying {:?}", change);
+        match change {
+            ConfigChange::AddVenue(venue) => self.upsert_venue(venue),
+            ConfigChange::RemoveVenue(program_id) => self.remove_venue(&program_id),
+            ConfigChange::SetLossWindow(loss_window_seconds) => {
+                if loss_window_seconds <= 0 {
+                    msg!("Loss window must be positive");
+                    return Err(ArbitrageProgramError::InvalidConfig.into());
+                }
+                self.loss_window_seconds = loss_window_seconds;
+                Ok(())
+            }
+            ConfigChange::SetPairCooldown(pair_cooldown_slots) => {
+                self.pair_cooldown_slots = pair_cooldown_slots;
+                Ok(())
+            }
+            ConfigChange::SetFeeSplit(fee_split) => self.set_fee_split(fee_split),
+            ConfigChange::SetTimelock(timelock_slots) => {
+                self.timelock_slots = timelock_slots;
+                Ok(())
+            }
+            ConfigChange::SetTemperatureBounds(temperature_bounds) => {
+                temperature_bounds.validate()?;
+                self.temperature_bounds = temperature_bounds;
+                Ok(())
+            }
+            ConfigChange::SetMint(registered) => match registry {
+                Some(registry) => registry.upsert(registered),
+                None => {
+                    msg!("Mint changes need the mint registry");
+                    Err(ArbitrageProgramError::InvalidAccountsList.into())
+                }
+            },
+        }
+    }
+
+    /// Sets how each round trip's profit is split off from the trader
+    pub fn set_fee_split(&mut self, fee_split: FeeSplit) -> ProgramResult {
+        if fee_split.treasury_bps as u32 + fee_split.keeper_bps as u32 > 10_000 {
+            msg!("Fee split can take at most 10,000 basis points of profit");
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        self.fee_split = fee_split;
+        Ok(())
+    }
+
+    /// Trips or resets a venue's circuit breaker
+    pub fn set_venue_disabled(&mut self, program_id: &Pubkey, disabled: bool) -> ProgramResult {
+        match self.venues.iter_mut().find(|v| v.program_id.eq(program_id)) {
+            Some(venue) => {
+                venue.disabled = disabled;
+                Ok(())
+            }
+            None => {
+                msg!("Swap program not on the allowlist: {}", program_id);
+                Err(ArbitrageProgramError::VenueNotAllowed.into())
+            }
+        }
+    }
+
+    /// Removes a venue from the allowlist
+    pub fn remove_venue(&mut self, program_id: &Pubkey) -> ProgramResult {
+        let len = self.venues.len();
+        self.venues.retain(|v| !v.program_id.eq(program_id));
+        if self.venues.len() == len {
+            msg!("Swap program not on the allowlist: {}", program_id);
+            return Err(ArbitrageProgramError::VenueNotAllowed.into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+//! Per-pair cooldowns, so the same pair isn't traded in back-to-back slots
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+use super::{AccountKind, ArbitrageAccount};
+
+/// The most pairs the cooldown account can track at once
+pub const MAX_PAIR_COOLDOWNS: usize = 128;
+
+/// A compact key identifying a pair of assets traded across a pair of pools:
+/// the first 8 bytes of the hash of both mints and both pools
+pub type PairKey = [u8; 8];
+
+/// Derives the key for a pair of assets traded across a pair of pools
+///
+/// Keying by pool rather than by swap program keeps two pools of the same
+/// program - or of the same pair of programs - apart
+///
+/// Both the mints and the pools are sorted first, so the key doesn't depend
+/// on which order they were passed in or which way the trade went
+pub fn pair_key(mint_a: &Pubkey, mint_b: &Pubkey, pool_a: &Pubkey, pool_b: &Pubkey) -> PairKey {
+    let (mint_a, mint_b) = if mint_a <= mint_b {
+        (mint_a, mint_b)
+    } else {
+        (mint_b, mint_a)
+    };
+    let (pool_a, pool_b) = if pool_a <= pool_b {
+        (pool_a, pool_b)
+    } else {
+        (pool_b, pool_a)
+    };
+    let hash = hashv(&[
+        mint_a.as_ref(),
+        mint_b.as_ref(),
+        pool_a.as_ref(),
+        pool_b.as_ref(),
+    ]);
+    let mut key = PairKey::default();
+    key.copy_from_slice(&hash.as_ref()[..8]);
+    key
+}
+
+/// The slot each recently traded pair was last traded in, shared by every
+/// trader so keepers can't chase each other's spreads
+///
+/// Once full, the least recently traded pair is evicted to make room
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PairCooldowns {
+    /// Bump seed of the cooldown account's PDA
+    pub bump: u8,
+    /// The recently traded pairs
+    pub pairs: Vec<PairCooldown>,
+}
+
+/// The slot a pair was last traded in
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct PairCooldown {
+    pub key: PairKey,
+    pub last_slot: u64,
+}
+
+impl PairCooldown {
+    const LEN: usize = 8 + 8;
+}
+
+impl ArbitrageAccount for PairCooldowns {
+    const KIND: AccountKind = AccountKind::PairCooldowns;
+    const LEN: usize = 1 + 1 + (4 + MAX_PAIR_COOLDOWNS * PairCooldown::LEN);
+}
+
+impl PairCooldowns {
+    pub const SEED_PREFIX: &'static [u8] = b"pair_cooldowns";
+
+    /// Derives the address of the cooldown account PDA
+    pub fn address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX], program_id)
+    }
+
+    /// Whether a pair was traded within the last `cooldown_slots` slots
+    pub fn is_cooling_down(&self, key: &PairKey, slot: u64, cooldown_slots: u64) -> bool {
+        self.pairs
+            .iter()
+            .any(|p| p.key.eq(key) && slot.saturating_sub(p.last_slot) < cooldown_slots)
+    }
+
+    /// Records that a pair was traded in the provided slot
+    pub fn record(&mut self, key: PairKey, slot: u64) {
+        if let Some(pair) = self.pairs.iter_mut().find(|p| p.key.eq(&key)) {
+            pair.last_slot = slot;
+            return;
+        }
+        if self.pairs.len() >= MAX_PAIR_COOLDOWNS {
+            if let Some(oldest) = self
+                .pairs
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, p)| p.last_slot)
+                .map(|(i, _)| i)
+            {
+                self.pairs.swap_remove(oldest);
+            }
+        }
+        self.pairs.push(PairCooldown {
+            key,
+            last_slot: slot,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_key_by_pool() {
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (pool_1, pool_2, pool_3) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let key = pair_key(&mint_a, &mint_b, &pool_1, &pool_2);
+        // Neither the order of the mints nor of the pools matters
+        assert_eq!(key, pair_key(&mint_b, &mint_a, &pool_2, &pool_1));
+        // But a different pool - even on the same swap program - is a
+        // different pair
+        assert_ne!(key, pair_key(&mint_a, &mint_b, &pool_1, &pool_3));
+    }
+}
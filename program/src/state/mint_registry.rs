@@ -0,0 +1,170 @@
+//! The registry of mints the arbitrage program is allowed to trade
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, msg, pubkey::Pubkey};
+
+use super::{AccountKind, ArbitrageAccount};
+use crate::error::ArbitrageProgramError;
+
+/// The most mints the registry can hold at once
+pub const MAX_REGISTERED_MINTS: usize = 64;
+
+/// The authority-managed registry of mints, governed by the config's
+/// authority
+///
+/// Any mint not registered as `Allowed` is dropped from the list of assets
+/// before pairs are evaluated, so it can never enter a trade
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct MintRegistry {
+    /// Bump seed of the registry's PDA
+    pub bump: u8,
+    /// The registered mints
+    pub mints: Vec<RegisteredMint>,
+}
+
+/// A mint the registry has an opinion on
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct RegisteredMint {
+    pub mint: Pubkey,
+    pub status: MintStatus,
+    pub caps: AssetCaps,
+}
+
+impl RegisteredMint {
+    const LEN: usize = 32 + 1 + AssetCaps::LEN;
+}
+
+/// Whether a registered mint may be traded
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MintStatus {
+    /// Approved for trading
+    Allowed,
+    /// Flagged - for example, for a compromised mint authority - and never
+    /// traded
+    Denied,
+}
+
+/// Limits on how much of an asset can be traded
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default)]
+pub struct AssetCaps {
+    /// The most of the asset that can be paid into a single trade
+    pub max_trade_amount: Option<u64>,
+    /// The largest fraction of the balance that can be paid into a single
+    /// trade, in basis points
+    pub max_trade_bps: Option<u16>,
+    /// A floor on the balance that must never be traded away
+    pub reserve: u64,
+    /// The most of the asset a trader can lose within the config's loss
+    /// window before all of their trades are refused
+    pub max_window_loss: Option<u64>,
+}
+
+impl AssetCaps {
+    const LEN: usize = (1 + 8) + (1 + 2) + 8 + (1 + 8);
+
+    /// Determines how much of an asset can be paid into a single trade, given
+    /// the current balance
+    ///
+    /// The reserve is set aside first, then the amount is limited by both the
+    /// fraction of the balance and the absolute maximum per trade
+    pub fn trade_size(&self, balance: u64) -> u64 {
+        let mut size = balance.saturating_sub(self.reserve);
+        if let Some(bps) = self.max_trade_bps {
+            size = size.min((balance as u128 * bps as u128 / 10_000) as u64);
+        }
+        if let Some(max) = self.max_trade_amount {
+            size = size.min(max);
+        }
+        size
+    }
+
+    /// Whether these caps are at least as strict as `other` - a cap that's
+    /// lower, or newly set, and a reserve that's no smaller
+    pub fn no_looser_than(&self, other: &AssetCaps) -> bool {
+        no_higher(self.max_trade_amount, other.max_trade_amount)
+            && no_higher(self.max_trade_bps, other.max_trade_bps)
+            && self.reserve >= other.reserve
+            && no_higher(self.max_window_loss, other.max_window_loss)
+    }
+}
+
+/// Whether an optional cap is no higher than another, where `None` is no cap
+fn no_higher<T: PartialOrd>(cap: Option<T>, other: Option<T>) -> bool {
+    match (cap, other) {
+        (_, None) => true,
+        (Some(cap), Some(other)) => cap <= other,
+        (None, Some(_)) => false,
+    }
+}
+
+impl ArbitrageAccount for MintRegistry {
+    const KIND: AccountKind = AccountKind::MintRegistry;
+    const LEN: usize = 1 + 1 + (4 + MAX_REGISTERED_MINTS * RegisteredMint::LEN);
+}
+
+impl MintRegistry {
+    pub const SEED_PREFIX: &'static [u8] = b"mint_registry";
+
+    /// Derives the address of the mint registry PDA
+    pub fn address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX], program_id)
+    }
+
+    /// Returns the caps for a mint if it's approved for trading, or `None` if
+    /// it's unregistered or denied
+    pub fn tradable(&self, mint: &Pubkey) -> Option<AssetCaps> {
+        self.mints
+            .iter()
+            .find(|m| m.mint.eq(mint) && m.status == MintStatus::Allowed)
+            .map(|m| m.caps)
+    }
+
+    /// Returns the caps for a registered mint, regardless of its status
+    pub fn caps(&self, mint: &Pubkey) -> Option<AssetCaps> {
+        self.mints.iter().find(|m| m.mint.eq(mint)).map(|m| m.caps)
+    }
+
+    /// Whether registering a mint only narrows what can be traded, so it
+    /// needn't wait out the timelock: denying a mint, or tightening the caps of
+    /// one that's already registered without allowing it anew
+    pub fn is_tightening(&self, registered: &RegisteredMint) -> bool {
+        match self.mints.iter().find(|m| m.mint.eq(&registered.mint)) {
+            Some(existing) => {
+                registered.caps.no_looser_than(&existing.caps)
+                    && (registered.status == MintStatus::Denied
+                        || existing.status == MintStatus::Allowed)
+            }
+            None => registered.status == MintStatus::Denied,
+        }
+    }
+
+    /// Registers a mint, or replaces its status and caps if it's already
+    /// registered
+    pub fn upsert(&mut self, registered: RegisteredMint) -> ProgramResult {
+        if registered.caps.max_trade_bps.unwrap_or_default() > 10_000 {
+            msg!("Max trade fraction can be at most 10,000 basis points");
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        match self.mints.iter_mut().find(|m| m.mint.eq(&registered.mint)) {
+            Some(existing) => *existing = registered,
+            None => {
+                if self.mints.len() >= MAX_REGISTERED_MINTS {
+                    msg!("Registry can hold at most {} mints", MAX_REGISTERED_MINTS);
+                    return Err(ArbitrageProgramError::AccountFull.into());
+                }
+                self.mints.push(registered);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes a mint from the registry, making it untradable
+    pub fn remove(&mut self, mint: &Pubkey) -> ProgramResult {
+        let len = self.mints.len();
+        self.mints.retain(|m| !m.mint.eq(mint));
+        if self.mints.len() == len {
+            msg!("Mint not registered: {}", mint);
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        Ok(())
+    }
+}
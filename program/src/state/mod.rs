@@ -0,0 +1,92 @@
+//! Accounts owned by the arbitrage program
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::error::ArbitrageProgramError;
+
+mod config;
+mod cooldown;
+mod mint_registry;
+mod receipts;
+mod risk;
+mod stats;
+mod strategy;
+mod vault;
+mod venue_layout;
+
+pub use config::*;
+pub use cooldown::*;
+pub use mint_registry::*;
+pub use receipts::*;
+pub use risk::*;
+pub use stats::*;
+pub use strategy::*;
+pub use vault::*;
+pub use venue_layout::*;
+
+/// The first byte of every account owned by this program, used to make sure
+/// one kind of account can never be passed off as another
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountKind {
+    Uninitialized,
+    Config,
+    MintRegistry,
+    Risk,
+    PairCooldowns,
+    Stats,
+    Vault,
+    Strategy,
+    Receipts,
+    VenueLayout,
+}
+
+/// Common behavior for the Borsh-serialized accounts owned by this program
+pub trait ArbitrageAccount: BorshSerialize + BorshDeserialize {
+    /// The kind of account, written as the first byte of its data
+    const KIND: AccountKind;
+
+    /// The maximum serialized size of the account - including the leading
+    /// `AccountKind` byte - used to allocate it up front
+    const LEN: usize;
+
+    /// Reads the account from an `AccountInfo`, asserting it's owned by this
+    /// program and is of the expected kind
+    fn load(program_id: &Pubkey, account_info: &AccountInfo) -> Result<Self, ProgramError> {
+        if !account_info.owner.eq(program_id) {
+            msg!("Account not owned by the arbitrage program");
+            msg!("Account: {}", account_info.key);
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        let data = account_info.data.borrow();
+        let mut data: &[u8] = &data;
+        match AccountKind::deserialize(&mut data) {
+            Ok(kind) if kind == Self::KIND => Self::deserialize(&mut data).map_err(|_| {
+                msg!("Failed to deserialize {:?} account", Self::KIND);
+                msg!("Account: {}", account_info.key);
+                ArbitrageProgramError::InvalidProgramAccount.into()
+            }),
+            _ => {
+                msg!("Expected a {:?} account", Self::KIND);
+                msg!("Account: {}", account_info.key);
+                Err(ArbitrageProgramError::InvalidProgramAccount.into())
+            }
+        }
+    }
+
+    /// Writes the account - prefixed with its `AccountKind` - back into the
+    /// `AccountInfo`'s data
+    fn save(&self, account_info: &AccountInfo) -> ProgramResult {
+        let mut data = account_info.data.borrow_mut();
+        let mut data: &mut [u8] = &mut data;
+        Self::KIND
+            .serialize(&mut data)
+            .and_then(|_| self.serialize(&mut data))
+            .map_err(|_| {
+                msg!("{:?} account is full", Self::KIND);
+                ArbitrageProgramError::AccountFull.into()
+            })
+    }
+}
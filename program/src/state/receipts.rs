@@ -0,0 +1,181 @@
+//! Per-trader trade receipts
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, msg, pubkey::Pubkey};
+
+use super::{AccountKind, ArbitrageAccount};
+use crate::arb::TradeOutcome;
+use crate::error::ArbitrageProgramError;
+
+/// The most receipts a receipts account holds before the oldest is
+/// overwritten
+pub const MAX_RECEIPTS: usize = 32;
+
+/// A ring buffer of a trader's most recent round trips, kept as a durable
+/// record for accounting rather than relying on transaction logs
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct TradeReceipts {
+    /// The trader whose round trips are recorded
+    pub trader: Pubkey,
+    /// The only key allowed to close the account, and who gets its rent back
+    pub authority: Pubkey,
+    /// Bump seed of the receipts account's PDA
+    pub bump: u8,
+    /// How many receipts have ever been recorded, so an exporter can tell
+    /// whether any were overwritten since it last read the account
+    pub total_recorded: u64,
+    /// Where the next receipt is written once the buffer is full - which is
+    /// also where the oldest receipt sits
+    pub head: u8,
+    /// The most recent receipts, in the order they were written until the
+    /// buffer fills, after which each new receipt overwrites the one at
+    /// `head`
+    pub receipts: Vec<TradeReceipt>,
+}
+
+/// A record of a single executed round trip
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct TradeReceipt {
+    /// This receipt's position among every receipt the account has recorded
+    pub sequence: u64,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    /// The buy leg
+    pub buy: ReceiptLeg,
+    /// The sell leg
+    pub sell: ReceiptLeg,
+    /// The realised profit - or loss, if negative - in the buy leg's pay
+    /// mint, after fees
+    pub profit: i64,
+}
+
+impl TradeReceipt {
+    const LEN: usize = 8 + 8 + 8 + ReceiptLeg::LEN * 2 + 8;
+}
+
+/// One swap of a round trip
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct ReceiptLeg {
+    /// The program ID of the venue swapped on
+    pub venue: Pubkey,
+    pub pay_mint: Pubkey,
+    pub receive_mint: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+impl ReceiptLeg {
+    const LEN: usize = 32 + 32 + 32 + 8 + 8;
+}
+
+impl ArbitrageAccount for TradeReceipts {
+    const KIND: AccountKind = AccountKind::Receipts;
+    const LEN: usize = 1 + 32 + 32 + 1 + 8 + 1 + (4 + MAX_RECEIPTS * TradeReceipt::LEN);
+}
+
+impl TradeReceipts {
+    pub const SEED_PREFIX: &'static [u8] = b"receipts";
+
+    /// Derives the address of a trader's receipts account PDA
+    pub fn address(program_id: &Pubkey, trader: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX, trader.as_ref()], program_id)
+    }
+
+    /// Asserts this receipts account belongs to the provided trader
+    pub fn assert_trader(&self, trader: &Pubkey) -> ProgramResult {
+        if !self.trader.eq(trader) {
+            msg!("Receipts account belongs to: {}", self.trader);
+            msg!("Expected:                   {}", trader);
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        Ok(())
+    }
+
+    /// Asserts the provided account is the receipts account's authority and
+    /// has signed the transaction
+    pub fn assert_authority(&self, authority: &AccountInfo) -> ProgramResult {
+        if !authority.is_signer || !authority.key.eq(&self.authority) {
+            msg!("Expected receipts authority: {}", self.authority);
+            msg!("Got:                         {}", authority.key);
+            return Err(ArbitrageProgramError::InvalidAuthority.into());
+        }
+        Ok(())
+    }
+
+    /// Records an executed round trip, overwriting the oldest receipt once
+    /// the buffer is full
+    pub fn record(&mut self, outcome: &TradeOutcome, slot: u64, unix_timestamp: i64) {
+        let receipt = TradeReceipt {
+            sequence: self.total_recorded,
+            slot,
+            unix_timestamp,
+            buy: ReceiptLeg {
+                venue: outcome.buy_venue,
+                pay_mint: outcome.mint,
+                receive_mint: outcome.received_mint,
+                amount_in: outcome.amount_in,
+                amount_out: outcome.amount_received,
+            },
+            sell: ReceiptLeg {
+                venue: outcome.sell_venue,
+                pay_mint: outcome.received_mint,
+                receive_mint: outcome.mint,
+                amount_in: outcome.amount_received,
+                amount_out: outcome.amount_out,
+            },
+            profit: outcome.profit,
+        };
+        if self.receipts.len() < MAX_RECEIPTS {
+            self.receipts.push(receipt);
+        } else {
+            self.receipts[self.head as usize] = receipt;
+            self.head = ((self.head as usize + 1) % MAX_RECEIPTS) as u8;
+        }
+        self.total_recorded = self.total_recorded.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(profit: i64) -> TradeOutcome {
+        TradeOutcome {
+            mint: Pubkey::new_unique(),
+            received_mint: Pubkey::new_unique(),
+            amount_in: 1_000,
+            amount_received: 2_000,
+            amount_out: (1_000 + profit) as u64,
+            buy_venue: Pubkey::new_unique(),
+            sell_venue: Pubkey::new_unique(),
+            profit,
+        }
+    }
+
+    #[test]
+    fn test_record_wraps_around() {
+        let mut receipts = TradeReceipts {
+            trader: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            bump: 255,
+            total_recorded: 0,
+            head: 0,
+            receipts: vec![],
+        };
+        for i in 0..MAX_RECEIPTS as i64 {
+            receipts.record(&outcome(i), i as u64, i);
+        }
+        assert_eq!(receipts.head, 0);
+        assert_eq!(receipts.receipts[0].sequence, 0);
+        // Past capacity, the oldest receipts are overwritten in place
+        for i in MAX_RECEIPTS as i64..MAX_RECEIPTS as i64 + 3 {
+            receipts.record(&outcome(i), i as u64, i);
+        }
+        assert_eq!(receipts.receipts.len(), MAX_RECEIPTS);
+        assert_eq!(receipts.head, 3);
+        assert_eq!(receipts.total_recorded, MAX_RECEIPTS as u64 + 3);
+        let sequences: Vec<u64> = receipts.receipts.iter().map(|r| r.sequence).collect();
+        assert_eq!(&sequences[..4], &[32, 33, 34, 3]);
+        assert_eq!(receipts.receipts[2].profit, 34);
+        assert_eq!(receipts.receipts[MAX_RECEIPTS - 1].sequence, 31);
+    }
+}
@@ -0,0 +1,194 @@
+//! Per-trader realised profit and loss tracking
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, msg, pubkey::Pubkey};
+
+use super::{AccountKind, ArbitrageAccount, MintRegistry};
+use crate::error::ArbitrageProgramError;
+
+/// The most assets a risk account can track at once
+pub const MAX_RISK_ASSETS: usize = 32;
+
+/// Records the realised profit or loss of every round trip a trader executes,
+/// both all-time and within the current loss window
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct RiskAccount {
+    /// The trader whose inventory this account tracks
+    pub trader: Pubkey,
+    /// Bump seed of the risk account's PDA
+    pub bump: u8,
+    /// Unix timestamp at which the current loss window started
+    pub window_start: i64,
+    /// Profit and loss per asset
+    pub assets: Vec<AssetPnl>,
+}
+
+/// Realised profit and loss for a single asset
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct AssetPnl {
+    pub mint: Pubkey,
+    /// All-time realised profit (or loss, if negative)
+    pub realised: i64,
+    /// Realised profit (or loss, if negative) within the current window
+    pub window: i64,
+}
+
+impl AssetPnl {
+    const LEN: usize = 32 + 8 + 8;
+}
+
+impl ArbitrageAccount for RiskAccount {
+    const KIND: AccountKind = AccountKind::Risk;
+    const LEN: usize = 1 + 32 + 1 + 8 + (4 + MAX_RISK_ASSETS * AssetPnl::LEN);
+}
+
+impl RiskAccount {
+    pub const SEED_PREFIX: &'static [u8] = b"risk";
+
+    /// Derives the address of a trader's risk account PDA
+    pub fn address(program_id: &Pubkey, trader: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX, trader.as_ref()], program_id)
+    }
+
+    /// Asserts this risk account tracks the provided trader
+    pub fn assert_trader(&self, trader: &Pubkey) -> ProgramResult {
+        if !self.trader.eq(trader) {
+            msg!("Risk account belongs to: {}", self.trader);
+            msg!("Expected:               {}", trader);
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        Ok(())
+    }
+
+    /// Starts a fresh loss window if the current one has elapsed
+    pub fn roll_window(&mut self, now: i64, window_seconds: i64) {
+        if now.saturating_sub(self.window_start) >= window_seconds {
+            self.window_start = now;
+            self.assets.iter_mut().for_each(|a| a.window = 0);
+        }
+    }
+
+    /// Asserts no asset's loss within the current window has exceeded the
+    /// limit set for it in the mint registry
+    pub fn assert_within_loss_limits(&self, registry: &MintRegistry) -> ProgramResult {
+        for asset in &self.assets {
+            if let Some(limit) = registry.caps(&asset.mint).and_then(|c| c.max_window_loss) {
+                if asset.window < 0 && asset.window.unsigned_abs() > limit {
+                    msg!("Loss limit exceeded for mint: {}", asset.mint);
+                    msg!("Window loss: {}", asset.window.unsigned_abs());
+                    msg!("Limit:       {}", limit);
+                    return Err(ArbitrageProgramError::LossLimitExceeded.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the realised profit or loss of a round trip
+    pub fn record(&mut self, mint: &Pubkey, pnl: i64) -> ProgramResult {
+        let asset = match self.assets.iter_mut().position(|a| a.mint.eq(mint)) {
+            Some(i) => &mut self.assets[i],
+            None => {
+                if self.assets.len() >= MAX_RISK_ASSETS {
+                    msg!("Risk account can track at most {} assets", MAX_RISK_ASSETS);
+                    return Err(ArbitrageProgramError::AccountFull.into());
+                }
+                self.assets.push(AssetPnl {
+                    mint: *mint,
+                    realised: 0,
+                    window: 0,
+                });
+                self.assets.last_mut().unwrap()
+            }
+        };
+        asset.realised = asset.realised.saturating_add(pnl);
+        asset.window = asset.window.saturating_add(pnl);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AssetCaps, MintStatus, RegisteredMint};
+
+    const WINDOW: i64 = 3_600;
+
+    fn risk_account(window_start: i64) -> RiskAccount {
+        RiskAccount {
+            trader: Pubkey::new_unique(),
+            bump: 0,
+            window_start,
+            assets: vec![],
+        }
+    }
+
+    fn registry(mint: Pubkey, max_window_loss: u64) -> MintRegistry {
+        MintRegistry {
+            bump: 0,
+            mints: vec![RegisteredMint {
+                mint,
+                status: MintStatus::Allowed,
+                caps: AssetCaps {
+                    max_window_loss: Some(max_window_loss),
+                    ..AssetCaps::default()
+                },
+            }],
+        }
+    }
+
+    #[test]
+    fn test_losses_build_up_within_window() {
+        let mint = Pubkey::new_unique();
+        let mut risk = risk_account(0);
+        for pnl in [-300, 100, -400] {
+            risk.roll_window(WINDOW - 1, WINDOW);
+            risk.record(&mint, pnl).unwrap();
+        }
+        assert_eq!(risk.window_start, 0);
+        assert_eq!(risk.assets.len(), 1);
+        assert_eq!(risk.assets[0].realised, -600);
+        assert_eq!(risk.assets[0].window, -600);
+    }
+
+    #[test]
+    fn test_window_rolls_over_after_its_span() {
+        let mint = Pubkey::new_unique();
+        let mut risk = risk_account(0);
+        risk.record(&mint, -600).unwrap();
+
+        // The window's losses are forgotten once it has run its span, but
+        // not the all-time figure
+        risk.roll_window(WINDOW, WINDOW);
+        assert_eq!(risk.window_start, WINDOW);
+        assert_eq!(risk.assets[0].window, 0);
+        assert_eq!(risk.assets[0].realised, -600);
+        risk.record(&mint, -100).unwrap();
+        assert_eq!(risk.assets[0].window, -100);
+        assert_eq!(risk.assets[0].realised, -700);
+    }
+
+    #[test]
+    fn test_loss_limit() {
+        let mint = Pubkey::new_unique();
+        let registry = registry(mint, 500);
+        let mut risk = risk_account(0);
+
+        // Losing up to the limit is allowed, but not beyond it
+        risk.record(&mint, -500).unwrap();
+        assert!(risk.assert_within_loss_limits(&registry).is_ok());
+        risk.record(&mint, -1).unwrap();
+        assert_eq!(
+            risk.assert_within_loss_limits(&registry),
+            Err(ArbitrageProgramError::LossLimitExceeded.into())
+        );
+
+        // Until the window rolls over
+        risk.roll_window(WINDOW, WINDOW);
+        assert!(risk.assert_within_loss_limits(&registry).is_ok());
+
+        // Assets with no limit can lose any amount
+        let unlimited = Pubkey::new_unique();
+        risk.record(&unlimited, i64::MIN).unwrap();
+        assert!(risk.assert_within_loss_limits(&registry).is_ok());
+    }
+}
@@ -0,0 +1,347 @@
+//! Per-trader trade statistics
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, msg, program::MAX_RETURN_DATA, pubkey::Pubkey};
+
+use super::{AccountKind, ArbitrageAccount, PairKey, TemperatureBounds};
+use crate::arb::TradeOutcome;
+use crate::error::ArbitrageProgramError;
+
+/// The most assets a stats account can track at once
+pub const MAX_STATS_ASSETS: usize = 32;
+
+/// The most pairs a stats account can track at once
+pub const MAX_STATS_PAIRS: usize = 64;
+
+/// The most assets a page of emitted stats holds
+pub const STATS_PAGE_ASSETS: usize = 8;
+
+/// The most pairs a page of emitted stats holds
+pub const STATS_PAGE_PAIRS: usize = 16;
+
+/// An on-chain record of what the program has done for a trader, so
+/// dashboards don't have to index logs
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct TradeStats {
+    /// The trader these stats belong to
+    pub trader: Pubkey,
+    /// Bump seed of the stats account's PDA
+    pub bump: u8,
+    /// Whether scans that find no arbitrage should be counted
+    ///
+    /// Since a failed instruction rolls back every account write, counting a
+    /// scan means letting it succeed - so with this set, a scan that finds
+    /// nothing no longer fails preflight
+    pub count_scans: bool,
+    /// The trader's adaptive temperature, if it's used in place of the one
+    /// each instruction provides
+    pub adaptive_temperature: Option<AdaptiveTemperature>,
+    /// How many round trips have been executed
+    pub trade_count: u64,
+    /// How many scans found no arbitrage
+    pub no_arbitrage_scans: u64,
+    /// Volume and realised profit per asset
+    pub assets: Vec<AssetStats>,
+    /// Trade counts and last-trade slot per pair
+    ///
+    /// Once full, the least recently traded pair is evicted to make room
+    pub pairs: Vec<PairStats>,
+}
+
+/// A temperature that tunes itself to the trader's recent outcomes: it falls
+/// after a run of unprofitable trades, making the bot pickier, and rises
+/// after a run of profitable ones
+///
+/// Reverted trades, such as those missing the strategy's minimum profit,
+/// can't be counted, since a failed instruction rolls back every account
+/// write. Only trades that were kept move the temperature
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct AdaptiveTemperature {
+    pub temperature: u8,
+    /// How many profitable round trips in a row have been made since the
+    /// temperature last moved
+    pub win_streak: u8,
+    /// How many unprofitable round trips in a row have been made since the
+    /// temperature last moved
+    pub loss_streak: u8,
+}
+
+impl AdaptiveTemperature {
+    const LEN: usize = 1 + 1 + 1;
+
+    /// Counts a kept round trip towards the current streak, moving the
+    /// temperature a step once the streak is long enough
+    pub fn record(&mut self, profit: i64, bounds: &TemperatureBounds) {
+        if profit > 0 {
+            self.loss_streak = 0;
+            self.win_streak = self.win_streak.saturating_add(1);
+            if self.win_streak >= bounds.streak {
+                self.win_streak = 0;
+                self.temperature = self.temperature.saturating_add(bounds.step);
+            }
+        } else {
+            self.win_streak = 0;
+            self.loss_streak = self.loss_streak.saturating_add(1);
+            if self.loss_streak >= bounds.streak {
+                self.loss_streak = 0;
+                self.temperature = self.temperature.saturating_sub(bounds.step);
+            }
+        }
+        self.temperature = bounds.clamp(self.temperature);
+    }
+}
+
+/// Volume and realised profit for a single asset
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct AssetStats {
+    pub mint: Pubkey,
+    /// Total amount of the asset paid into either leg of a trade
+    pub volume: u64,
+    /// All-time realised profit (or loss, if negative)
+    pub realised_profit: i64,
+}
+
+impl AssetStats {
+    const LEN: usize = 32 + 8 + 8;
+}
+
+/// Trade count and last-trade slot for a single pair
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct PairStats {
+    pub key: PairKey,
+    pub trade_count: u64,
+    pub last_slot: u64,
+}
+
+impl PairStats {
+    const LEN: usize = 8 + 8 + 8;
+}
+
+/// One page of a trader's stats, small enough to be an instruction's return
+/// data
+///
+/// Page `n` holds the `n`th run of `STATS_PAGE_ASSETS` assets and
+/// `STATS_PAGE_PAIRS` pairs, alongside the totals every page repeats
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct StatsPage {
+    pub trader: Pubkey,
+    pub adaptive_temperature: Option<AdaptiveTemperature>,
+    pub trade_count: u64,
+    pub no_arbitrage_scans: u64,
+    pub page: u8,
+    /// How many pages it takes to emit every asset and pair
+    pub page_count: u8,
+    pub assets: Vec<AssetStats>,
+    pub pairs: Vec<PairStats>,
+}
+
+impl StatsPage {
+    /// The largest a page can be, serialized
+    pub const MAX_LEN: usize = 32
+        + (1 + AdaptiveTemperature::LEN)
+        + 8
+        + 8
+        + 1
+        + 1
+        + (4 + STATS_PAGE_ASSETS * AssetStats::LEN)
+        + (4 + STATS_PAGE_PAIRS * PairStats::LEN);
+}
+
+// A full page has to fit in the runtime's return data
+const _: () = assert!(StatsPage::MAX_LEN <= MAX_RETURN_DATA);
+
+impl ArbitrageAccount for TradeStats {
+    const KIND: AccountKind = AccountKind::Stats;
+    const LEN: usize = 1
+        + 32
+        + 1
+        + 1
+        + (1 + AdaptiveTemperature::LEN)
+        + 8
+        + 8
+        + (4 + MAX_STATS_ASSETS * AssetStats::LEN)
+        + (4 + MAX_STATS_PAIRS * PairStats::LEN);
+}
+
+impl TradeStats {
+    pub const SEED_PREFIX: &'static [u8] = b"stats";
+
+    /// Derives the address of a trader's stats account PDA
+    pub fn address(program_id: &Pubkey, trader: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX, trader.as_ref()], program_id)
+    }
+
+    /// Asserts this stats account belongs to the provided trader
+    pub fn assert_trader(&self, trader: &Pubkey) -> ProgramResult {
+        if !self.trader.eq(trader) {
+            msg!("Stats account belongs to: {}", self.trader);
+            msg!("Expected:                {}", trader);
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        Ok(())
+    }
+
+    /// The temperature to trade at: the adaptive one - kept within the
+    /// config's current bounds - if there is one, otherwise the one provided
+    pub fn temperature(&self, provided: u8, bounds: &TemperatureBounds) -> u8 {
+        match &self.adaptive_temperature {
+            Some(adaptive) => bounds.clamp(adaptive.temperature),
+            None => provided,
+        }
+    }
+
+    /// Finds an asset's stats, adding an entry if it isn't tracked yet
+    fn asset_mut(&mut self, mint: &Pubkey) -> Result<&mut AssetStats, ArbitrageProgramError> {
+        match self.assets.iter().position(|a| a.mint.eq(mint)) {
+            Some(i) => Ok(&mut self.assets[i]),
+            None => {
+                if self.assets.len() >= MAX_STATS_ASSETS {
+                    msg!(
+                        "Stats account can track at most {} assets",
+                        MAX_STATS_ASSETS
+                    );
+                    return Err(ArbitrageProgramError::AccountFull);
+                }
+                self.assets.push(AssetStats {
+                    mint: *mint,
+                    volume: 0,
+                    realised_profit: 0,
+                });
+                Ok(self.assets.last_mut().unwrap())
+            }
+        }
+    }
+
+    /// Records an executed round trip of the provided pair
+    pub fn record_trade(
+        &mut self,
+        outcome: &TradeOutcome,
+        pair: PairKey,
+        slot: u64,
+    ) -> ProgramResult {
+        self.trade_count = self.trade_count.saturating_add(1);
+        let asset = self.asset_mut(&outcome.mint)?;
+        asset.volume = asset.volume.saturating_add(outcome.amount_in);
+        asset.realised_profit = asset.realised_profit.saturating_add(outcome.profit);
+        let asset = self.asset_mut(&outcome.received_mint)?;
+        asset.volume = asset.volume.saturating_add(outcome.amount_received);
+        match self.pairs.iter_mut().find(|p| p.key.eq(&pair)) {
+            Some(stats) => {
+                stats.trade_count = stats.trade_count.saturating_add(1);
+                stats.last_slot = slot;
+            }
+            None => {
+                if self.pairs.len() >= MAX_STATS_PAIRS {
+                    if let Some(oldest) = self
+                        .pairs
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, p)| p.last_slot)
+                        .map(|(i, _)| i)
+                    {
+                        self.pairs.swap_remove(oldest);
+                    }
+                }
+                self.pairs.push(PairStats {
+                    key: pair,
+                    trade_count: 1,
+                    last_slot: slot,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes one page of the stats, or `None` if there's no such page
+    pub fn page(&self, page: u8) -> Option<StatsPage> {
+        // There's always at least one page, for the totals
+        let page_count = 1
+            + (self.assets.len().saturating_sub(1) / STATS_PAGE_ASSETS)
+                .max(self.pairs.len().saturating_sub(1) / STATS_PAGE_PAIRS);
+        if page as usize >= page_count {
+            return None;
+        }
+        let assets = self
+            .assets
+            .iter()
+            .skip(page as usize * STATS_PAGE_ASSETS)
+            .take(STATS_PAGE_ASSETS);
+        let pairs = self
+            .pairs
+            .iter()
+            .skip(page as usize * STATS_PAGE_PAIRS)
+            .take(STATS_PAGE_PAIRS);
+        Some(StatsPage {
+            trader: self.trader,
+            adaptive_temperature: self.adaptive_temperature.clone(),
+            trade_count: self.trade_count,
+            no_arbitrage_scans: self.no_arbitrage_scans,
+            page,
+            page_count: page_count as u8,
+            assets: assets.cloned().collect(),
+            pairs: pairs.cloned().collect(),
+        })
+    }
+}
+
+impl StatsPage {
+    /// Logs the page
+    pub fn log(&self) {
+        msg!("Trader: {}", self.trader);
+        msg!("Page {} of {}", self.page + 1, self.page_count);
+        msg!("Trades: {}", self.trade_count);
+        msg!("No arbitrage scans: {}", self.no_arbitrage_scans);
+        if let Some(adaptive) = &self.adaptive_temperature {
+            msg!("Adaptive temperature: {}", adaptive.temperature);
+        }
+        for asset in &self.assets {
+            msg!(
+                "Asset {}: volume {}, realised profit {}",
+                asset.mint,
+                asset.volume,
+                asset.realised_profit
+            );
+        }
+        for pair in &self.pairs {
+            msg!(
+                "Pair {:?}: trades {}, last slot {}",
+                pair.key,
+                pair.trade_count,
+                pair.last_slot
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_temperature_streaks() {
+        let bounds = TemperatureBounds {
+            min: 20,
+            max: 80,
+            step: 10,
+            streak: 2,
+        };
+        let mut adaptive = AdaptiveTemperature {
+            temperature: 25,
+            win_streak: 0,
+            loss_streak: 0,
+        };
+        let mut record = |profit| {
+            adaptive.record(profit, &bounds);
+            adaptive.temperature
+        };
+        // A loss breaks a run of wins, and a run of losses cools the trader
+        // down no further than the minimum
+        assert_eq!(record(5), 25);
+        assert_eq!(record(-5), 25);
+        assert_eq!(record(5), 25);
+        assert_eq!(record(5), 35);
+        assert_eq!(record(0), 35);
+        assert_eq!(record(-1), 25);
+        assert_eq!(record(-1), 25);
+        assert_eq!(record(-1), 20);
+    }
+}
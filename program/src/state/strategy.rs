@@ -0,0 +1,129 @@
+//! Per-user bot profiles
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, msg, pubkey::Pubkey};
+
+use super::{AccountKind, ArbitrageAccount};
+use crate::error::ArbitrageProgramError;
+
+/// Whose token accounts an arbitrage attempt trades, and who signs for them
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradingAs {
+    /// The payer's own token accounts
+    Payer,
+    /// A vault's token accounts, signed for by the vault's PDA
+    Vault,
+    /// Token accounts that have approved the payer as their delegate
+    Delegate,
+}
+
+/// Everything that shapes an arbitrage attempt, other than the accounts
+/// it's given
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct StrategyParams {
+    /// The program ID of the first swap to inspect for arbitrage
+    pub swap_1_program_id: Pubkey,
+    /// The program ID of the second swap to inspect for arbitrage
+    pub swap_2_program_id: Pubkey,
+    /// How many assets to evaluate combinations of at one time
+    pub concurrency: u8,
+    /// How aggressive the model will be when identifying arbitrage
+    /// opportunities
+    pub temperature: u8,
+    pub trading_as: TradingAs,
+    /// The highest fee tier - in basis points - either venue may charge
+    pub max_venue_fee_bps: u16,
+    /// The smallest profit worth keeping, in basis points of the amount paid
+    /// into the first leg - any trade making less is reverted, unless this is
+    /// zero
+    pub min_profit_bps: u16,
+    /// The share of each balance to trade, in basis points - the mint
+    /// registry's caps apply to the whole balance, and the smaller of the two
+    /// sizes is traded
+    pub trade_size_bps: u16,
+}
+
+impl StrategyParams {
+    const LEN: usize = 32 + 32 + 1 + 1 + 1 + 2 + 2 + 2;
+
+    /// Parameters that leave fees, profit and sizing to the config and the
+    /// mint registry alone
+    pub fn unrestricted(
+        swap_1_program_id: Pubkey,
+        swap_2_program_id: Pubkey,
+        concurrency: u8,
+        temperature: u8,
+        trading_as: TradingAs,
+    ) -> Self {
+        Self {
+            swap_1_program_id,
+            swap_2_program_id,
+            concurrency,
+            temperature,
+            trading_as,
+            max_venue_fee_bps: 10_000,
+            min_profit_bps: 0,
+            trade_size_bps: 10_000,
+        }
+    }
+
+    /// Asserts every basis point setting is at most 100%
+    pub fn validate(&self) -> ProgramResult {
+        if self.max_venue_fee_bps > 10_000
+            || self.min_profit_bps > 10_000
+            || self.trade_size_bps > 10_000
+        {
+            msg!("Strategy settings can be at most 10,000 basis points");
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        Ok(())
+    }
+
+    /// Scales a balance down to the share of it the strategy trades
+    pub fn trade_size(&self, balance: u64) -> u64 {
+        (balance as u128 * self.trade_size_bps as u128 / 10_000) as u64
+    }
+
+    /// Whether a round trip made enough profit to keep
+    pub fn is_profitable_enough(&self, amount_in: u64, profit: i64) -> bool {
+        self.min_profit_bps == 0
+            || profit as i128 * 10_000 >= amount_in as i128 * self.min_profit_bps as i128
+    }
+}
+
+/// A saved bot profile, so a running bot can be retuned on-chain and its
+/// instructions only need to reference it
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Strategy {
+    /// The only key allowed to modify - or trade with - this strategy
+    pub owner: Pubkey,
+    /// Distinguishes an owner's strategies from each other
+    pub id: u8,
+    /// Bump seed of the strategy's PDA
+    pub bump: u8,
+    pub params: StrategyParams,
+}
+
+impl ArbitrageAccount for Strategy {
+    const KIND: AccountKind = AccountKind::Strategy;
+    const LEN: usize = 1 + 32 + 1 + 1 + StrategyParams::LEN;
+}
+
+impl Strategy {
+    pub const SEED_PREFIX: &'static [u8] = b"strategy";
+
+    /// Derives the address of one of an owner's strategy PDAs
+    pub fn address(program_id: &Pubkey, owner: &Pubkey, id: u8) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX, owner.as_ref(), &[id]], program_id)
+    }
+
+    /// Asserts the provided account is the strategy's owner and has signed
+    /// the transaction
+    pub fn assert_owner(&self, owner: &AccountInfo) -> ProgramResult {
+        if !owner.is_signer || !owner.key.eq(&self.owner) {
+            msg!("Expected strategy owner: {}", self.owner);
+            msg!("Got:                     {}", owner.key);
+            return Err(ArbitrageProgramError::InvalidAuthority.into());
+        }
+        Ok(())
+    }
+}
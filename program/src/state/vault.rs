@@ -0,0 +1,262 @@
+//! Program-owned inventory vaults
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::{AccountKind, ArbitrageAccount};
+use crate::error::ArbitrageProgramError;
+
+/// A vault holding trading inventory on behalf of its owner
+///
+/// The vault's PDA owns its token accounts - the associated token accounts of
+/// the vault's address - and signs for the swap CPIs when trading from them,
+/// so the keeper running the bot never custodies any funds
+///
+/// A pooled vault holds capital from many depositors instead, who are issued
+/// share tokens in exchange - its owner only manages its keeper and assets
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Vault {
+    /// The only key allowed to deposit into or withdraw from the vault - or,
+    /// if it's pooled, to manage it
+    pub owner: Pubkey,
+    /// The hot key allowed to trade the vault's inventory
+    pub keeper: Pubkey,
+    /// Whether anyone - not just the keeper - may trade the vault's
+    /// inventory, being paid the config's keeper fee for any profit they make
+    /// and never being allowed to lose the vault money
+    pub permissionless: bool,
+    /// Bump seed of the vault's PDA
+    pub bump: u8,
+    /// The vault's share token accounting, if it's pooled
+    pub shares: Option<VaultShares>,
+}
+
+/// The most assets a pooled vault can hold
+pub const MAX_POOLED_ASSETS: usize = 8;
+
+/// How far, in basis points, an asset's reference price can follow the
+/// pricing pool's spot price for each slot since it last moved
+pub const MAX_PRICE_MOVE_BPS_PER_SLOT: u128 = 5;
+
+/// The most slots' worth of movement a reference price can catch up on at
+/// once, so a long quiet spell doesn't let a single update drag it anywhere
+pub const MAX_PRICE_MOVE_SLOTS: u64 = 20;
+
+/// Shares and quote value credited to nobody, so the first depositor can't
+/// inflate the share price - by donating to the vault - until later deposits
+/// round down to nothing
+pub const VIRTUAL_SHARES: u128 = 1_000;
+
+/// Share token accounting for a pooled vault
+///
+/// Shares are priced by valuing every asset the vault holds in its quote
+/// asset. The pricing venue's spot price can be moved within a transaction,
+/// so each asset also keeps a reference price that can only follow the spot
+/// price a little each slot - and deposits are valued at whichever of the two
+/// is worse for the depositor
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct VaultShares {
+    /// The share token's mint, whose mint authority is the vault
+    pub mint: Pubkey,
+    /// Bump seed of the share mint's PDA
+    pub mint_bump: u8,
+    /// The venue whose pool prices the vault's assets
+    pub pricing_venue: Pubkey,
+    /// The assets the vault holds - the first being the quote asset
+    pub assets: Vec<PooledAsset>,
+}
+
+/// One of a pooled vault's assets, and the price it's valued at
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct PooledAsset {
+    pub mint: Pubkey,
+    /// The quote asset per unit of the asset, as a 64.64 fixed-point number -
+    /// unused for the quote asset itself
+    pub reference_price: u128,
+    /// The slot the reference price last moved in
+    pub reference_slot: u64,
+}
+
+impl PooledAsset {
+    const LEN: usize = 32 + 16 + 8;
+
+    /// Moves the reference price towards the spot price, by at most
+    /// `MAX_PRICE_MOVE_BPS_PER_SLOT` for each slot since it last moved
+    ///
+    /// It can't move twice in a slot, so it can only be dragged away from the
+    /// market by manipulating the pricing pool over many slots
+    pub fn follow(&mut self, spot_price: u128, slot: u64) {
+        let slots = slot
+            .saturating_sub(self.reference_slot)
+            .min(MAX_PRICE_MOVE_SLOTS);
+        let band = self.reference_price / 10_000 * MAX_PRICE_MOVE_BPS_PER_SLOT * slots as u128;
+        self.reference_price = spot_price.clamp(
+            self.reference_price.saturating_sub(band),
+            self.reference_price.saturating_add(band),
+        );
+        self.reference_slot = slot;
+    }
+}
+
+/// The spot price of a pool holding `pool_quote` of the quote asset and
+/// `pool_balance` of another, as a 64.64 fixed-point number
+///
+/// Returns `None` if the pool holds none of the other asset
+pub fn spot_price(pool_quote: u64, pool_balance: u64) -> Option<u128> {
+    ((pool_quote as u128) << 64).checked_div(pool_balance as u128)
+}
+
+impl VaultShares {
+    pub const SEED_PREFIX: &'static [u8] = b"vault_shares";
+    const LEN: usize = 32 + 1 + 32 + (4 + MAX_POOLED_ASSETS * PooledAsset::LEN);
+
+    /// Derives the address of a pooled vault's share mint PDA
+    pub fn mint_address(program_id: &Pubkey, vault: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX, vault.as_ref()], program_id)
+    }
+
+    /// Whether the vault holds an asset
+    pub fn holds(&self, mint: &Pubkey) -> bool {
+        self.assets.iter().any(|a| a.mint.eq(mint))
+    }
+
+    /// Adds an asset to the vault's holdings, if it isn't held already,
+    /// starting its reference price at the current spot price
+    pub fn add_asset(&mut self, mint: Pubkey, spot_price: u128, slot: u64) -> ProgramResult {
+        if self.holds(&mint) {
+            return Ok(());
+        }
+        if self.assets.len() >= MAX_POOLED_ASSETS {
+            msg!(
+                "A pooled vault can hold at most {} assets",
+                MAX_POOLED_ASSETS
+            );
+            return Err(ArbitrageProgramError::AccountFull.into());
+        }
+        self.assets.push(PooledAsset {
+            mint,
+            reference_price: spot_price,
+            reference_slot: slot,
+        });
+        Ok(())
+    }
+}
+
+impl ArbitrageAccount for Vault {
+    const KIND: AccountKind = AccountKind::Vault;
+    const LEN: usize = 1 + 32 + 32 + 1 + 1 + (1 + VaultShares::LEN);
+}
+
+impl Vault {
+    pub const SEED_PREFIX: &'static [u8] = b"vault";
+
+    /// Derives the address of an owner's vault PDA
+    pub fn address(program_id: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX, owner.as_ref()], program_id)
+    }
+
+    /// Asserts the provided account is the vault's owner and has signed the
+    /// transaction
+    pub fn assert_owner(&self, owner: &AccountInfo) -> ProgramResult {
+        if !owner.is_signer || !owner.key.eq(&self.owner) {
+            msg!("Expected vault owner: {}", self.owner);
+            msg!("Got:                  {}", owner.key);
+            return Err(ArbitrageProgramError::InvalidAuthority.into());
+        }
+        Ok(())
+    }
+
+    /// Asserts the vault isn't pooled, since only a single-owner vault's
+    /// owner may move its funds directly
+    pub fn assert_not_pooled(&self) -> ProgramResult {
+        if self.shares.is_some() {
+            msg!("Pooled vaults can only be deposited into or withdrawn from for shares");
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        Ok(())
+    }
+
+    /// Returns the vault's share token accounting, asserting it's pooled
+    pub fn shares(&self) -> Result<&VaultShares, ProgramError> {
+        self.shares.as_ref().ok_or_else(|| {
+            msg!("Vault is not pooled");
+            ArbitrageProgramError::InvalidProgramAccount.into()
+        })
+    }
+
+    /// Returns the vault's share token accounting mutably, asserting it's
+    /// pooled
+    pub fn shares_mut(&mut self) -> Result<&mut VaultShares, ProgramError> {
+        self.shares.as_mut().ok_or_else(|| {
+            msg!("Vault is not pooled");
+            ArbitrageProgramError::InvalidProgramAccount.into()
+        })
+    }
+
+    /// Whether the vault may hold - and therefore trade - the provided asset
+    ///
+    /// A pooled vault only holds the assets its shares are priced on, so
+    /// nothing it trades into goes unvalued
+    pub fn holds(&self, mint: &Pubkey) -> bool {
+        match &self.shares {
+            Some(shares) => shares.holds(mint),
+            None => true,
+        }
+    }
+
+    /// Asserts the provided account may trade the vault's inventory - either
+    /// its keeper or its owner, or anyone if the vault is permissionless -
+    /// and has signed the transaction
+    pub fn assert_can_trade(&self, keeper: &AccountInfo) -> ProgramResult {
+        if !keeper.is_signer
+            || !(self.permissionless || keeper.key.eq(&self.keeper) || keeper.key.eq(&self.owner))
+        {
+            msg!("Expected vault keeper: {}", self.keeper);
+            msg!("Got:                   {}", keeper.key);
+            return Err(ArbitrageProgramError::InvalidAuthority.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(reference_price: u128) -> PooledAsset {
+        PooledAsset {
+            mint: Pubkey::new_unique(),
+            reference_price,
+            reference_slot: 100,
+        }
+    }
+
+    #[test]
+    fn test_reference_price_follows_slowly() {
+        let one = 1u128 << 64;
+        // Within a slot it can't move at all
+        let mut pooled = asset(one);
+        pooled.follow(2 * one, 100);
+        assert_eq!(pooled.reference_price, one);
+        // Ten slots on, it moves at most 50 bps
+        pooled.follow(2 * one, 110);
+        assert_eq!(pooled.reference_price, one + one / 10_000 * 50);
+        assert_eq!(pooled.reference_slot, 110);
+        // And a long quiet spell only counts for so many slots
+        let mut pooled = asset(one);
+        pooled.follow(0, 1_000_000);
+        assert_eq!(pooled.reference_price, one - one / 10_000 * 100);
+        // Within the band, it's the spot price
+        let mut pooled = asset(one);
+        pooled.follow(one + 1, 101);
+        assert_eq!(pooled.reference_price, one + 1);
+    }
+
+    #[test]
+    fn test_spot_price() {
+        assert_eq!(spot_price(2_000, 1_000), Some(2 << 64));
+        assert_eq!(spot_price(1_000, 0), None);
+    }
+}
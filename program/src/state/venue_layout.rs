@@ -0,0 +1,184 @@
+//! Per-venue layouts describing how to trade on a swap program, so compatible
+//! forks can be onboarded without upgrading the arbitrage program
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, msg, pubkey::Pubkey};
+
+use super::{AccountKind, ArbitrageAccount, Curve};
+use crate::error::ArbitrageProgramError;
+use crate::stable_swap::MAX_AMPLIFICATION;
+
+/// The longest instruction discriminator a layout can hold
+pub const MAX_DISCRIMINATOR_LEN: usize = 8;
+
+/// The most accounts a layout's swap instruction can take
+pub const MAX_LAYOUT_ACCOUNTS: usize = 24;
+
+/// The most arguments a layout's swap instruction can take
+pub const MAX_LAYOUT_ARGS: usize = 4;
+
+/// The most further accounts a layout's swap instruction can need beyond the
+/// ones every venue is given
+pub const MAX_LAYOUT_VENUE_ACCOUNTS: usize = 8;
+
+/// How to build a swap program's `swap` instruction and quote its pools,
+/// interpreted at runtime for venues of the `Registered` kind
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct VenueLayout {
+    /// The swap program the layout describes
+    pub program_id: Pubkey,
+    /// Bump seed of the layout's PDA
+    pub bump: u8,
+    /// The bytes the swap instruction's data starts with
+    pub discriminator: Vec<u8>,
+    /// The swap instruction's accounts, in order
+    pub accounts: Vec<AccountSlot>,
+    /// The swap instruction's arguments, in order, after the discriminator
+    pub args: Vec<ArgSlot>,
+    /// The further accounts the swap instruction needs, passed after the
+    /// mints as the venue's accounts, and what each must be
+    pub venue_accounts: Vec<VenueAccountCheck>,
+    /// How the venue charges its fee
+    pub fee: FeeModel,
+    /// The curve the venue's pools price with
+    pub curve: Curve,
+    /// The first slot the layout can be traded by, once the config's timelock
+    /// has passed since it was written
+    pub effective_slot: u64,
+}
+
+/// A symbolic account in a layout's account order, filled in from the leg
+/// being traded
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountSlot {
+    Pool,
+    PayMint,
+    ReceiveMint,
+    /// The pool's token account for the asset paid
+    PoolPay,
+    /// The pool's token account for the asset received
+    PoolReceive,
+    /// The trader's token account for the asset paid
+    PayerPay,
+    /// The trader's token account for the asset received
+    PayerReceive,
+    /// The owner of the trader's token accounts, who authorizes the swap
+    Authority,
+    TokenProgram,
+    SystemProgram,
+    AssociatedTokenProgram,
+    /// One of the venue's further accounts, by its position
+    VenueAccount(u8),
+}
+
+/// What one of a layout's venue accounts must be, checked before it's
+/// forwarded to the venue
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VenueAccountCheck {
+    /// Whatever account is passed, trusted as-is
+    Any,
+    /// Exactly this account
+    Key(Pubkey),
+    /// Any account owned by this program
+    Owner(Pubkey),
+}
+
+impl VenueAccountCheck {
+    const LEN: usize = 1 + 32;
+}
+
+/// A symbolic argument in a layout's instruction data
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgSlot {
+    /// The amount to pay, as a little-endian `u64`
+    Amount,
+    /// No minimum amount out - the arbitrage checks its own profit - as a
+    /// little-endian `u64` zero
+    MinimumAmountOut,
+}
+
+/// How a venue takes its fee
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeModel {
+    /// Basis points of the amount paid, taken before the swap
+    OnInput { bps: u16 },
+    /// Basis points of the amount received, taken after the swap
+    OnOutput { bps: u16 },
+}
+
+impl FeeModel {
+    /// The fee, in basis points, whichever side it's taken from
+    pub fn bps(&self) -> u16 {
+        match self {
+            FeeModel::OnInput { bps } | FeeModel::OnOutput { bps } => *bps,
+        }
+    }
+}
+
+impl ArbitrageAccount for VenueLayout {
+    const KIND: AccountKind = AccountKind::VenueLayout;
+    const LEN: usize = 1
+        + 32
+        + 1
+        + (4 + MAX_DISCRIMINATOR_LEN)
+        + (4 + MAX_LAYOUT_ACCOUNTS * (1 + 1))
+        + (4 + MAX_LAYOUT_ARGS)
+        + (4 + MAX_LAYOUT_VENUE_ACCOUNTS * VenueAccountCheck::LEN)
+        + (1 + 2)
+        + (1 + 8)
+        + 8;
+}
+
+impl VenueLayout {
+    pub const SEED_PREFIX: &'static [u8] = b"venue_layout";
+
+    /// Derives the address of a swap program's layout PDA
+    pub fn address(program_id: &Pubkey, venue_program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::SEED_PREFIX, venue_program_id.as_ref()], program_id)
+    }
+
+    /// Asserts the layout fits its account and only refers to accounts the
+    /// venue is given
+    pub fn validate(&self) -> ProgramResult {
+        if self.discriminator.len() > MAX_DISCRIMINATOR_LEN {
+            msg!(
+                "Discriminator can be at most {} bytes",
+                MAX_DISCRIMINATOR_LEN
+            );
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        if self.accounts.len() > MAX_LAYOUT_ACCOUNTS || self.args.len() > MAX_LAYOUT_ARGS {
+            msg!(
+                "Layout can hold at most {} accounts and {} arguments",
+                MAX_LAYOUT_ACCOUNTS,
+                MAX_LAYOUT_ARGS
+            );
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        if self.venue_accounts.len() > MAX_LAYOUT_VENUE_ACCOUNTS {
+            msg!(
+                "Layout can need at most {} venue accounts",
+                MAX_LAYOUT_VENUE_ACCOUNTS
+            );
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        for slot in &self.accounts {
+            if let AccountSlot::VenueAccount(i) = slot {
+                if *i as usize >= self.venue_accounts.len() {
+                    msg!("Venue account {} is beyond the venue's accounts", i);
+                    return Err(ArbitrageProgramError::InvalidConfig.into());
+                }
+            }
+        }
+        if self.fee.bps() > 10_000 {
+            msg!("Fee can be at most 10,000 basis points");
+            return Err(ArbitrageProgramError::InvalidConfig.into());
+        }
+        if let Curve::StableSwap { amplification } = self.curve {
+            if amplification == 0 || amplification > MAX_AMPLIFICATION {
+                msg!("Amplification must be between 1 and {}", MAX_AMPLIFICATION);
+                return Err(ArbitrageProgramError::InvalidConfig.into());
+            }
+        }
+        Ok(())
+    }
+}
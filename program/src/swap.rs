@@ -8,7 +8,7 @@ use crate::error::ArbitrageProgramError;
 /// receiving asset that can be returned in exchange for the amount of the paid
 /// asset offered
 ///
-/// ```
+/// ```text
 /// K = a * b * c * d * P * R
 /// K = a * b * c * d * (P + p) * (R - r)
 ///
@@ -67,3 +67,9 @@ fn convert_to_float(value: u64, decimals: u8) -> f32 {
 fn convert_from_float(value: f32, decimals: u8) -> u64 {
     value.mul(f32::powf(10.0, decimals as f32)) as u64
 }
+
+/// Deducts a swap's fee - in basis points of the amount paid - leaving the
+/// amount that actually trades against the pool's liquidity
+pub fn apply_fee(pay_amount: u64, fee_bps: u16) -> u64 {
+    (pay_amount as u128 * 10_000u128.saturating_sub(fee_bps as u128) / 10_000) as u64
+}
@@ -0,0 +1,1003 @@
+//! An in-memory bank for running the program's instructions off-chain
+//!
+//! Instructions are processed against accounts held by the bank, with the
+//! syscalls stubbed out to play the parts of the clock, the system program,
+//! the token programs, the associated token program and any fake swap
+//! programs a test registers
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Once;
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::AccountInfo,
+    bpf_loader_upgradeable,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::MAX_RETURN_DATA,
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::{
+    instruction::TokenInstruction,
+    state::{Account, AccountState, Mint},
+};
+
+use crate::state::{
+    ArbitrageAccount, ArbitrageConfig, AssetCaps, Curve, MintRegistry, MintStatus, PairCooldowns,
+    PoolSeed, RiskAccount, StrategyParams, TradeStats, TradingAs, VenueKind,
+};
+use crate::swap::determine_swap_receive;
+use crate::ArbitrageProgramInstruction;
+
+/// A fake program, called in place of a CPI into it
+pub type FakeProgram = fn(&Instruction, &[AccountInfo]) -> ProgramResult;
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+    static CALLER: RefCell<Pubkey> = RefCell::new(Pubkey::default());
+    static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+    static CPIS: RefCell<Vec<Instruction>> = const { RefCell::new(vec![]) };
+    static FAKE_PROGRAMS: RefCell<HashMap<Pubkey, FakeProgram>> = RefCell::new(HashMap::new());
+}
+
+static INSTALL_STUBS: Once = Once::new();
+
+/// Sets the slot and time the clock reports
+pub fn set_clock(slot: u64, unix_timestamp: i64) {
+    CLOCK.with(|c| {
+        let mut clock = c.borrow_mut();
+        clock.slot = slot;
+        clock.unix_timestamp = unix_timestamp;
+    });
+}
+
+/// Routes CPIs into `program_id` to a fake program
+pub fn register_program(program_id: Pubkey, program: FakeProgram) {
+    FAKE_PROGRAMS.with(|p| p.borrow_mut().insert(program_id, program));
+}
+
+/// The data most recently set as return data
+pub fn return_data() -> Option<Vec<u8>> {
+    RETURN_DATA.with(|r| r.borrow().as_ref().map(|(_, data)| data.clone()))
+}
+
+/// Every CPI made since the bank was created, in order
+pub fn cpis() -> Vec<Instruction> {
+    CPIS.with(|c| c.borrow().clone())
+}
+
+struct TestSyscalls;
+
+impl SyscallStubs for TestSyscalls {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = CLOCK.with(|c| c.borrow().clone());
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|r| r.borrow().clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        // The runtime fails the instruction outright
+        assert!(
+            data.len() <= MAX_RETURN_DATA,
+            "{} bytes of return data is over the limit",
+            data.len()
+        );
+        let caller = CALLER.with(|c| *c.borrow());
+        RETURN_DATA.with(|r| *r.borrow_mut() = Some((caller, data.to_vec())));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        // Only what the caller signed for - itself, or with a PDA's seeds -
+        // can be passed on as a signer
+        let caller = CALLER.with(|c| *c.borrow());
+        for meta in instruction.accounts.iter().filter(|m| m.is_signer) {
+            let signed = find(account_infos, &meta.pubkey)?.is_signer
+                || signers_seeds
+                    .iter()
+                    .any(|seeds| Pubkey::create_program_address(seeds, &caller) == Ok(meta.pubkey));
+            if !signed {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+        }
+        CPIS.with(|c| c.borrow_mut().push(instruction.clone()));
+        let fake = FAKE_PROGRAMS.with(|p| p.borrow().get(&instruction.program_id).copied());
+        if let Some(program) = fake {
+            return program(instruction, account_infos);
+        }
+        if instruction.program_id == system_program::id() {
+            system(instruction, account_infos)
+        } else if instruction.program_id == spl_token::id()
+            || instruction.program_id == spl_token_2022::id()
+        {
+            token(instruction, account_infos)
+        } else if instruction.program_id == spl_associated_token_account::id() {
+            associated_token(instruction, account_infos)
+        } else {
+            Err(ProgramError::IncorrectProgramId)
+        }
+    }
+}
+
+/// Finds the account passed along with a CPI for one of its metas
+fn find<'a, 'b>(
+    infos: &'a [AccountInfo<'b>],
+    key: &Pubkey,
+) -> Result<&'a AccountInfo<'b>, ProgramError> {
+    infos
+        .iter()
+        .find(|info| info.key.eq(key))
+        .ok_or(ProgramError::NotEnoughAccountKeys)
+}
+
+/// The account passed for the instruction's `index`th meta
+fn account<'a, 'b>(
+    instruction: &Instruction,
+    infos: &'a [AccountInfo<'b>],
+    index: usize,
+) -> Result<&'a AccountInfo<'b>, ProgramError> {
+    let meta = instruction
+        .accounts
+        .get(index)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    find(infos, &meta.pubkey)
+}
+
+/// Whether the instruction's `index`th meta signs
+fn signs(instruction: &Instruction, index: usize) -> bool {
+    matches!(instruction.accounts.get(index), Some(meta) if meta.is_signer)
+}
+
+/// Plays the system program's `CreateAccount`
+fn system(instruction: &Instruction, infos: &[AccountInfo]) -> ProgramResult {
+    let data = &instruction.data;
+    if data.get(..4) != Some(&[0, 0, 0, 0][..]) || data.len() < 52 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let lamports = u64::from_le_bytes(data[4..12].try_into().unwrap());
+    let space = u64::from_le_bytes(data[12..20].try_into().unwrap()) as usize;
+    let owner = Pubkey::new_from_array(data[20..52].try_into().unwrap());
+    let from = account(instruction, infos, 0)?;
+    let to = account(instruction, infos, 1)?;
+    if !signs(instruction, 0) || !signs(instruction, 1) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !to.data_is_empty() || to.lamports() > 0 || !to.owner.eq(&system_program::id()) {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if from.lamports() < lamports {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    **from.lamports.borrow_mut() -= lamports;
+    **to.lamports.borrow_mut() += lamports;
+    *to.data.borrow_mut() = Box::leak(vec![0; space].into_boxed_slice());
+    to.assign(&owner);
+    Ok(())
+}
+
+/// Plays the parts of the token program the arbitrage program uses
+fn token(instruction: &Instruction, infos: &[AccountInfo]) -> ProgramResult {
+    match TokenInstruction::unpack(&instruction.data)? {
+        TokenInstruction::Transfer { amount } => transfer(instruction, infos, 0, 1, 2, amount),
+        TokenInstruction::TransferChecked { amount, decimals } => {
+            let mint = Mint::unpack(&account(instruction, infos, 1)?.data.borrow())?;
+            if mint.decimals != decimals {
+                return Err(ProgramError::InvalidArgument);
+            }
+            transfer(instruction, infos, 0, 2, 3, amount)
+        }
+        TokenInstruction::MintTo { amount } => {
+            let mint_info = account(instruction, infos, 0)?;
+            let mut mint = Mint::unpack(&mint_info.data.borrow())?;
+            if mint.mint_authority != COption::Some(instruction.accounts[2].pubkey)
+                || !signs(instruction, 2)
+            {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            mint.supply = mint.supply.checked_add(amount).unwrap();
+            Mint::pack(mint, &mut mint_info.data.borrow_mut())?;
+            update_token_account(account(instruction, infos, 1)?, |a| {
+                a.amount += amount;
+                Ok(())
+            })
+        }
+        TokenInstruction::Burn { amount } => {
+            let mint_info = account(instruction, infos, 1)?;
+            authorize(instruction, infos, 0, 2, amount)?;
+            update_token_account(account(instruction, infos, 0)?, |a| {
+                a.amount = a
+                    .amount
+                    .checked_sub(amount)
+                    .ok_or(ProgramError::InsufficientFunds)?;
+                Ok(())
+            })?;
+            let mut mint = Mint::unpack(&mint_info.data.borrow())?;
+            mint.supply -= amount;
+            Mint::pack(mint, &mut mint_info.data.borrow_mut())
+        }
+        TokenInstruction::InitializeMint2 {
+            decimals,
+            mint_authority,
+            freeze_authority,
+        } => {
+            let mint_info = account(instruction, infos, 0)?;
+            Mint::pack(
+                Mint {
+                    mint_authority: COption::Some(mint_authority),
+                    supply: 0,
+                    decimals,
+                    is_initialized: true,
+                    freeze_authority,
+                },
+                &mut mint_info.data.borrow_mut(),
+            )
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+/// Moves tokens between two token accounts, as authorised by the owner of
+/// the source - or its delegate, within its delegated amount
+fn transfer(
+    instruction: &Instruction,
+    infos: &[AccountInfo],
+    source: usize,
+    destination: usize,
+    authority: usize,
+    amount: u64,
+) -> ProgramResult {
+    authorize(instruction, infos, source, authority, amount)?;
+    let destination = account(instruction, infos, destination)?;
+    let source = account(instruction, infos, source)?;
+    let mint = Account::unpack(&source.data.borrow())?.mint;
+    update_token_account(source, |a| {
+        a.amount = a
+            .amount
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        Ok(())
+    })?;
+    update_token_account(destination, |a| {
+        if a.mint != mint {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        a.amount += amount;
+        Ok(())
+    })
+}
+
+/// Asserts a token account's owner - or delegate - signed to spend from it,
+/// using up the delegate's allowance
+fn authorize(
+    instruction: &Instruction,
+    infos: &[AccountInfo],
+    source: usize,
+    authority: usize,
+    amount: u64,
+) -> ProgramResult {
+    if !signs(instruction, authority) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let authority = instruction.accounts[authority].pubkey;
+    update_token_account(account(instruction, infos, source)?, |a| {
+        if a.owner == authority {
+            Ok(())
+        } else if a.delegate == COption::Some(authority) {
+            a.delegated_amount = a
+                .delegated_amount
+                .checked_sub(amount)
+                .ok_or(ProgramError::InsufficientFunds)?;
+            Ok(())
+        } else {
+            Err(ProgramError::MissingRequiredSignature)
+        }
+    })
+}
+
+/// Plays the associated token program's `Create` and `CreateIdempotent`
+fn associated_token(instruction: &Instruction, infos: &[AccountInfo]) -> ProgramResult {
+    let associated = account(instruction, infos, 1)?;
+    let wallet = instruction.accounts[2].pubkey;
+    let mint = instruction.accounts[3].pubkey;
+    if associated.key != &get_associated_token_address(&wallet, &mint) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if associated.owner.eq(&spl_token::id()) {
+        return match instruction.data.first() {
+            Some(1) => Ok(()),
+            _ => Err(ProgramError::AccountAlreadyInitialized),
+        };
+    }
+    *associated.data.borrow_mut() = Box::leak(vec![0; Account::LEN].into_boxed_slice());
+    associated.assign(&spl_token::id());
+    Account::pack(
+        Account {
+            mint,
+            owner: wallet,
+            state: AccountState::Initialized,
+            ..Account::default()
+        },
+        &mut associated.data.borrow_mut(),
+    )
+}
+
+/// Reads, changes and writes back a token account
+fn update_token_account(
+    info: &AccountInfo,
+    f: impl FnOnce(&mut Account) -> ProgramResult,
+) -> ProgramResult {
+    let mut token_account = Account::unpack(&info.data.borrow())?;
+    f(&mut token_account)?;
+    Account::pack(token_account, &mut info.data.borrow_mut())
+}
+
+/// A fake of the tutorial's Anchor swap, pricing each swap on the constant
+/// product curve with no fee
+pub fn fake_anchor_swap(instruction: &Instruction, infos: &[AccountInfo]) -> ProgramResult {
+    let amount = u64::from_le_bytes(
+        instruction
+            .data
+            .get(8..16)
+            .ok_or(ProgramError::InvalidInstructionData)?
+            .try_into()
+            .unwrap(),
+    );
+    let decimals = |index| -> Result<u8, ProgramError> {
+        Ok(Mint::unpack(&account(instruction, infos, index)?.data.borrow())?.decimals)
+    };
+    let balance = |index| -> Result<u64, ProgramError> {
+        Ok(Account::unpack(&account(instruction, infos, index)?.data.borrow())?.amount)
+    };
+    let receive =
+        determine_swap_receive(balance(2)?, decimals(1)?, balance(5)?, decimals(4)?, amount)?;
+    // The payer pays the pool, then the pool - signing as itself - pays out
+    transfer(instruction, infos, 6, 5, 7, amount)?;
+    let pool = account(instruction, infos, 0)?;
+    update_token_account(account(instruction, infos, 2)?, |a| {
+        if a.owner != *pool.key {
+            return Err(ProgramError::IllegalOwner);
+        }
+        a.amount = a
+            .amount
+            .checked_sub(receive)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        Ok(())
+    })?;
+    update_token_account(account(instruction, infos, 3)?, |a| {
+        a.amount += receive;
+        Ok(())
+    })
+}
+
+/// An account held by the bank
+#[derive(Clone, Debug)]
+pub struct TestAccount {
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub executable: bool,
+}
+
+impl Default for TestAccount {
+    fn default() -> Self {
+        Self {
+            owner: system_program::id(),
+            lamports: 0,
+            data: vec![],
+            executable: false,
+        }
+    }
+}
+
+/// The accounts the program's instructions are processed against
+#[derive(Default)]
+pub struct Bank {
+    pub accounts: HashMap<Pubkey, TestAccount>,
+}
+
+impl Bank {
+    /// Creates an empty bank, resetting the clock and any CPIs recorded by
+    /// earlier tests on this thread
+    pub fn new() -> Self {
+        INSTALL_STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(TestSyscalls));
+        });
+        set_clock(1, 1_700_000_000);
+        RETURN_DATA.with(|r| *r.borrow_mut() = None);
+        CPIS.with(|c| c.borrow_mut().clear());
+        FAKE_PROGRAMS.with(|p| p.borrow_mut().clear());
+        let mut bank = Self::default();
+        for program_id in [
+            system_program::id(),
+            spl_token::id(),
+            spl_associated_token_account::id(),
+        ] {
+            bank.add_program(program_id);
+        }
+        bank
+    }
+
+    /// Adds an executable program account
+    pub fn add_program(&mut self, program_id: Pubkey) {
+        self.accounts.insert(
+            program_id,
+            TestAccount {
+                executable: true,
+                lamports: 1,
+                ..TestAccount::default()
+            },
+        );
+    }
+
+    /// Adds the Program Data account the upgradeable loader keeps for
+    /// `program_id`, with `authority` as its upgrade authority
+    pub fn add_program_data(&mut self, program_id: &Pubkey, authority: Option<Pubkey>) -> Pubkey {
+        let (key, _) =
+            Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+        let mut data = vec![0; 45];
+        data[..4].copy_from_slice(&3u32.to_le_bytes());
+        if let Some(authority) = authority {
+            data[12] = 1;
+            data[13..].copy_from_slice(authority.as_ref());
+        }
+        self.accounts.insert(
+            key,
+            TestAccount {
+                owner: bpf_loader_upgradeable::id(),
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                executable: false,
+            },
+        );
+        key
+    }
+
+    /// Adds a funded wallet
+    pub fn add_wallet(&mut self) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.accounts.insert(
+            key,
+            TestAccount {
+                lamports: 100_000_000_000,
+                ..TestAccount::default()
+            },
+        );
+        key
+    }
+
+    /// Adds a mint
+    pub fn add_mint(&mut self, decimals: u8) -> Pubkey {
+        let key = Pubkey::new_unique();
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(
+            Mint {
+                mint_authority: COption::None,
+                supply: 0,
+                decimals,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            &mut data,
+        )
+        .unwrap();
+        self.add_token_program_account(key, data);
+        key
+    }
+
+    /// Adds a token account at `key`
+    pub fn add_token_account_at(
+        &mut self,
+        key: Pubkey,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) {
+        let mut data = vec![0; Account::LEN];
+        Account::pack(
+            Account {
+                mint: *mint,
+                owner: *owner,
+                amount,
+                state: AccountState::Initialized,
+                ..Account::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        self.add_token_program_account(key, data);
+    }
+
+    /// Adds a wallet's associated token account
+    pub fn add_ata(&mut self, wallet: &Pubkey, mint: &Pubkey, amount: u64) -> Pubkey {
+        let key = get_associated_token_address(wallet, mint);
+        self.add_token_account_at(key, mint, wallet, amount);
+        key
+    }
+
+    fn add_token_program_account(&mut self, key: Pubkey, data: Vec<u8>) {
+        self.accounts.insert(
+            key,
+            TestAccount {
+                owner: spl_token::id(),
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                executable: false,
+            },
+        );
+    }
+
+    /// Approves `delegate` to spend up to `amount` of a token account
+    pub fn approve(&mut self, key: &Pubkey, delegate: &Pubkey, amount: u64) {
+        let data = &mut self.accounts.get_mut(key).unwrap().data;
+        let mut token_account = Account::unpack(data).unwrap();
+        token_account.delegate = COption::Some(*delegate);
+        token_account.delegated_amount = amount;
+        Account::pack(token_account, data).unwrap();
+    }
+
+    /// Sets a token account's balance, as if it had been sent or spent
+    pub fn set_balance(&mut self, key: &Pubkey, amount: u64) {
+        let data = &mut self.accounts.get_mut(key).unwrap().data;
+        let mut token_account = Account::unpack(data).unwrap();
+        token_account.amount = amount;
+        Account::pack(token_account, data).unwrap();
+    }
+
+    /// A token account's state
+    pub fn token_account(&self, key: &Pubkey) -> Account {
+        Account::unpack(&self.accounts[key].data).unwrap()
+    }
+
+    /// A token account's balance
+    pub fn balance(&self, key: &Pubkey) -> u64 {
+        Account::unpack(&self.accounts[key].data).unwrap().amount
+    }
+
+    /// Writes one of the program's accounts straight into the bank
+    pub fn store<T: ArbitrageAccount>(&mut self, program_id: &Pubkey, key: Pubkey, state: &T) {
+        let mut account = TestAccount {
+            owner: *program_id,
+            lamports: Rent::default().minimum_balance(T::LEN),
+            data: vec![0; T::LEN],
+            executable: false,
+        };
+        let mut data = &mut account.data[..];
+        T::KIND.serialize(&mut data).unwrap();
+        state.serialize(&mut data).unwrap();
+        self.accounts.insert(key, account);
+    }
+
+    /// Reads one of the program's accounts out of the bank
+    pub fn load<T: ArbitrageAccount>(&self, program_id: &Pubkey, key: &Pubkey) -> T {
+        let mut account = self.accounts[key].clone();
+        let mut lamports = account.lamports;
+        let info = AccountInfo::new(
+            key,
+            false,
+            false,
+            &mut lamports,
+            &mut account.data,
+            &account.owner,
+            false,
+            0,
+        );
+        T::load(program_id, &info).unwrap()
+    }
+
+    /// Processes one of the program's instructions, as the runtime would:
+    /// every change is kept if it succeeds, and none are if it fails
+    ///
+    /// Accounts the bank doesn't hold yet are passed as empty system accounts
+    pub fn process(
+        &mut self,
+        program_id: &Pubkey,
+        metas: &[AccountMeta],
+        instruction: &ArbitrageProgramInstruction,
+    ) -> ProgramResult {
+        CALLER.with(|c| *c.borrow_mut() = *program_id);
+        let mut unique: Vec<(Pubkey, TestAccount, bool, bool)> = vec![];
+        for meta in metas {
+            match unique.iter_mut().find(|(key, ..)| key.eq(&meta.pubkey)) {
+                Some((_, _, is_signer, is_writable)) => {
+                    *is_signer |= meta.is_signer;
+                    *is_writable |= meta.is_writable;
+                }
+                None => unique.push((
+                    meta.pubkey,
+                    self.accounts.get(&meta.pubkey).cloned().unwrap_or_default(),
+                    meta.is_signer,
+                    meta.is_writable,
+                )),
+            }
+        }
+        let infos: Vec<AccountInfo> = unique
+            .iter_mut()
+            .map(|(key, account, is_signer, is_writable)| {
+                AccountInfo::new(
+                    key,
+                    *is_signer,
+                    *is_writable,
+                    &mut account.lamports,
+                    &mut account.data,
+                    &account.owner,
+                    account.executable,
+                    0,
+                )
+            })
+            .collect();
+        // Duplicated metas share one account, as they do on chain
+        let accounts: Vec<AccountInfo> = metas
+            .iter()
+            .map(|meta| find(&infos, &meta.pubkey).unwrap().clone())
+            .collect();
+        crate::process(program_id, &accounts, &instruction.try_to_vec().unwrap())?;
+        let changed: Vec<(Pubkey, TestAccount)> = infos
+            .iter()
+            .map(|info| {
+                (
+                    *info.key,
+                    TestAccount {
+                        owner: *info.owner,
+                        lamports: info.lamports(),
+                        data: info.data.borrow().to_vec(),
+                        executable: info.executable,
+                    },
+                )
+            })
+            .collect();
+        drop(accounts);
+        drop(infos);
+        self.accounts.extend(changed);
+        Ok(())
+    }
+}
+
+/// A writable account meta
+pub fn writable(key: Pubkey) -> AccountMeta {
+    AccountMeta::new(key, false)
+}
+
+/// A read-only account meta
+pub fn readonly(key: Pubkey) -> AccountMeta {
+    AccountMeta::new_readonly(key, false)
+}
+
+/// A writable, signing account meta
+pub fn signer(key: Pubkey) -> AccountMeta {
+    AccountMeta::new(key, true)
+}
+
+/// The seed the tutorial's Anchor swap derives its Liquidity Pool from
+pub const LIQUIDITY_POOL_SEED: &[u8] = b"liquidity_pool";
+
+/// The seed a fork with a pool per pair of mints derives its pools from,
+/// followed by the two mints
+pub const PER_PAIR_POOL_SEED: &[u8] = b"pool";
+
+/// Two Anchor swaps pricing the same two mints differently, both approved,
+/// with a payer holding some of each and every account it needs to trade
+pub struct Market {
+    pub bank: Bank,
+    pub program_id: Pubkey,
+    /// The config's authority, and the trader
+    pub payer: Pubkey,
+    pub mints: [Pubkey; 2],
+    pub swaps: [Pubkey; 2],
+    pub pools: [Pubkey; 2],
+}
+
+impl Market {
+    /// The payer's opening balance of each mint
+    pub const BALANCE: u64 = 100_000_000;
+
+    /// Swap #2 holds 30% more of the second mint than Swap #1, so paying the
+    /// first mint into Swap #2 and selling back into Swap #1 makes money
+    pub fn new() -> Self {
+        Self::build(false)
+    }
+
+    /// The same market, but with both pools on one swap program that has a
+    /// pool for each ordering of a pair of mints
+    pub fn on_one_program() -> Self {
+        Self::build(true)
+    }
+
+    fn build(one_program: bool) -> Self {
+        let mut bank = Bank::new();
+        let program_id = Pubkey::new_unique();
+        let payer = bank.add_wallet();
+        let mints = [bank.add_mint(6), bank.add_mint(6)];
+        let swaps = if one_program {
+            [Pubkey::new_unique(); 2]
+        } else {
+            [Pubkey::new_unique(), Pubkey::new_unique()]
+        };
+        let pool_seeds = if one_program {
+            vec![
+                PoolSeed::Literal(PER_PAIR_POOL_SEED.to_vec()),
+                PoolSeed::MintA,
+                PoolSeed::MintB,
+            ]
+        } else {
+            vec![PoolSeed::Literal(LIQUIDITY_POOL_SEED.to_vec())]
+        };
+        // A per-pair pool records its mints in the order it was derived with
+        let pool_mints = [[mints[0], mints[1]], [mints[1], mints[0]]];
+        let pools = [0, 1].map(|i| {
+            if one_program {
+                let [mint_a, mint_b] = pool_mints[i];
+                Pubkey::find_program_address(
+                    &[PER_PAIR_POOL_SEED, mint_a.as_ref(), mint_b.as_ref()],
+                    &swaps[i],
+                )
+            } else {
+                Pubkey::find_program_address(&[LIQUIDITY_POOL_SEED], &swaps[i])
+            }
+        });
+        let mut market = Self {
+            bank,
+            program_id,
+            payer,
+            mints,
+            swaps,
+            pools: pools.map(|(pool, _)| pool),
+        };
+        market.initialize_config();
+        for (i, (swap, (pool, bump))) in swaps.into_iter().zip(pools).enumerate() {
+            let mut data = vec![0; 8];
+            data.extend((pool_mints[i].to_vec(), bump).try_to_vec().unwrap());
+            market.bank.accounts.insert(
+                pool,
+                TestAccount {
+                    owner: swap,
+                    lamports: 1,
+                    data,
+                    executable: false,
+                },
+            );
+            market.bank.add_ata(&pool, &mints[0], 1_000_000_000);
+            market
+                .bank
+                .add_ata(&pool, &mints[1], 1_000_000_000 + 300_000_000 * i as u64);
+            if market.bank.accounts.contains_key(&swap) {
+                continue;
+            }
+            market.bank.add_program(swap);
+            register_program(swap, fake_anchor_swap);
+            market
+                .config(ArbitrageProgramInstruction::AddVenue {
+                    program_id: swap,
+                    pool_seeds: pool_seeds.clone(),
+                    fee_bps: 0,
+                    kind: VenueKind::AnchorSwap,
+                    curve: Curve::ConstantProduct,
+                })
+                .unwrap();
+        }
+        let (registry, _) = MintRegistry::address(&program_id);
+        market
+            .bank
+            .process(
+                &program_id,
+                &[
+                    signer(payer),
+                    readonly(ArbitrageConfig::address(&program_id).0),
+                    writable(registry),
+                    readonly(system_program::id()),
+                ],
+                &ArbitrageProgramInstruction::InitializeMintRegistry,
+            )
+            .unwrap();
+        for mint in mints {
+            market.set_mint(mint, AssetCaps::default());
+            market.bank.add_ata(&payer, &mint, Self::BALANCE);
+        }
+        market
+            .bank
+            .process(
+                &program_id,
+                &[
+                    signer(payer),
+                    readonly(ArbitrageConfig::address(&program_id).0),
+                    writable(PairCooldowns::address(&program_id).0),
+                    readonly(system_program::id()),
+                ],
+                &ArbitrageProgramInstruction::InitializePairCooldowns,
+            )
+            .unwrap();
+        market.initialize_trader(None);
+        market
+    }
+
+    /// Creates the config, with the payer as its authority - and as the
+    /// program's upgrade authority
+    fn initialize_config(&mut self) {
+        let program_data = self
+            .bank
+            .add_program_data(&self.program_id, Some(self.payer));
+        self.bank
+            .process(
+                &self.program_id,
+                &[
+                    signer(self.payer),
+                    writable(ArbitrageConfig::address(&self.program_id).0),
+                    readonly(program_data),
+                    readonly(system_program::id()),
+                ],
+                &ArbitrageProgramInstruction::InitializeConfig,
+            )
+            .unwrap();
+    }
+
+    /// Creates the risk and stats accounts of the payer - or of the payer's
+    /// vault
+    pub fn initialize_trader(&mut self, vault: Option<Pubkey>) {
+        let trader = vault.unwrap_or(self.payer);
+        for (key, instruction) in [
+            (
+                RiskAccount::address(&self.program_id, &trader).0,
+                ArbitrageProgramInstruction::InitializeRiskAccount,
+            ),
+            (
+                TradeStats::address(&self.program_id, &trader).0,
+                ArbitrageProgramInstruction::InitializeStats { count_scans: false },
+            ),
+        ] {
+            let mut metas = vec![
+                signer(self.payer),
+                writable(key),
+                readonly(system_program::id()),
+            ];
+            metas.extend(vault.map(readonly));
+            self.bank
+                .process(&self.program_id, &metas, &instruction)
+                .unwrap();
+        }
+    }
+
+    /// Sends one of the config authority's instructions
+    pub fn config(&mut self, instruction: ArbitrageProgramInstruction) -> ProgramResult {
+        self.bank.process(
+            &self.program_id,
+            &[
+                signer(self.payer),
+                writable(ArbitrageConfig::address(&self.program_id).0),
+            ],
+            &instruction,
+        )
+    }
+
+    /// Approves a mint for trading, within the caps given
+    pub fn set_mint(&mut self, mint: Pubkey, caps: AssetCaps) {
+        self.bank
+            .process(
+                &self.program_id,
+                &[
+                    signer(self.payer),
+                    writable(ArbitrageConfig::address(&self.program_id).0),
+                    writable(MintRegistry::address(&self.program_id).0),
+                ],
+                &ArbitrageProgramInstruction::SetMint {
+                    mint,
+                    status: MintStatus::Allowed,
+                    caps,
+                },
+            )
+            .unwrap();
+    }
+
+    /// The accounts `TryArbitrage` needs to trade the payer's token accounts,
+    /// through to the mints
+    pub fn trade_accounts(&self) -> Vec<AccountMeta> {
+        self.trade_accounts_for(&self.payer, &[])
+    }
+
+    /// The accounts a trade needs for `trader`'s token accounts, with
+    /// `extra` - the strategy, vault or venue layouts - between the stats
+    /// account and the token accounts
+    pub fn trade_accounts_for(&self, trader: &Pubkey, extra: &[AccountMeta]) -> Vec<AccountMeta> {
+        let program_id = &self.program_id;
+        let mut metas = vec![
+            signer(self.payer),
+            readonly(spl_token::id()),
+            readonly(system_program::id()),
+            readonly(spl_associated_token_account::id()),
+            readonly(self.swaps[0]),
+            readonly(self.swaps[1]),
+            writable(self.pools[0]),
+            writable(self.pools[1]),
+            readonly(ArbitrageConfig::address(program_id).0),
+            readonly(MintRegistry::address(program_id).0),
+            writable(RiskAccount::address(program_id, trader).0),
+            writable(PairCooldowns::address(program_id).0),
+            writable(TradeStats::address(program_id, trader).0),
+        ];
+        metas.extend_from_slice(extra);
+        for owner in [trader, &self.pools[0], &self.pools[1]] {
+            metas.extend(
+                self.mints
+                    .iter()
+                    .map(|mint| writable(get_associated_token_address(owner, mint))),
+            );
+        }
+        metas.extend(self.mints.iter().copied().map(readonly));
+        metas
+    }
+
+    /// `TryArbitrage` across the two swaps, at the temperature given
+    pub fn try_arbitrage(&self, temperature: u8) -> ArbitrageProgramInstruction {
+        ArbitrageProgramInstruction::TryArbitrage {
+            swap_1_program_id: self.swaps[0],
+            swap_2_program_id: self.swaps[1],
+            concurrency: 2,
+            temperature,
+        }
+    }
+
+    /// Unrestricted parameters for trading across the two swaps
+    pub fn params(&self, trading_as: TradingAs) -> StrategyParams {
+        StrategyParams::unrestricted(self.swaps[0], self.swaps[1], 2, 90, trading_as)
+    }
+
+    /// A trader's balance of one of the mints
+    pub fn balance(&self, owner: &Pubkey, mint: usize) -> u64 {
+        self.bank
+            .balance(&get_associated_token_address(owner, &self.mints[mint]))
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_market_trades_through_fake_swaps() {
+        let mut market = Market::new();
+        let accounts = market.trade_accounts_for(&market.payer, &[]);
+        market
+            .bank
+            .process(
+                &market.program_id,
+                &accounts,
+                &ArbitrageProgramInstruction::TryArbitrage {
+                    swap_1_program_id: market.swaps[0],
+                    swap_2_program_id: market.swaps[1],
+                    concurrency: 2,
+                    temperature: 90,
+                },
+            )
+            .unwrap();
+        // Both legs went through the fake swaps, and the round trip paid
+        let swaps: Vec<Pubkey> = cpis()
+            .iter()
+            .map(|ix| ix.program_id)
+            .filter(|program_id| market.swaps.contains(program_id))
+            .collect();
+        assert_eq!(swaps.len(), 2);
+        assert!(market.balance(&market.payer, 0) > Market::BALANCE);
+    }
+}
@@ -1,23 +1,55 @@
 //! Util functions for arbitrage bot
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, instruction::AccountMeta,
-    program_error::ProgramError, pubkey::Pubkey,
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::AccountMeta, msg,
+    program::invoke_signed, program_error::ProgramError, pubkey::Pubkey, rent::Rent,
+    system_instruction, sysvar::Sysvar,
 };
 
 use crate::error::ArbitrageProgramError;
 
-// Asserts the pool address provided is in fact derived from the program ID
-// provided
-pub fn check_pool_address(program_id: &Pubkey, pool: &Pubkey) -> ProgramResult {
-    if !Pubkey::find_program_address(&[b"liquidity_pool"], program_id)
-        .0
-        .eq(pool)
-    {
-        return Err(solana_program::program_error::ProgramError::InvalidInstructionData);
+/// Creates a rent-exempt account at a PDA owned by this program, funded by
+/// the payer and signed for with the PDA's seeds
+pub fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    new_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let lamports = Rent::get()?.minimum_balance(space);
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            new_account.key,
+            lamports,
+            space as u64,
+            program_id,
+        ),
+        &[payer.clone(), new_account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )
+}
+
+/// Asserts the provided account is the token program, before a vault signs
+/// anything for it or any fee is swept through it
+pub fn assert_token_program(token_program: &AccountInfo) -> ProgramResult {
+    if !token_program.key.eq(&spl_token::id()) {
+        msg!("Expected token program: {}", spl_token::id());
+        return Err(ArbitrageProgramError::InvalidProgramAccount.into());
     }
     Ok(())
 }
 
+/// Keeps only the items whose matching entry in `mask` is `true`
+pub fn retain_by_mask<T>(items: Vec<T>, mask: &[bool]) -> Vec<T> {
+    items
+        .into_iter()
+        .zip(mask)
+        .filter_map(|(item, keep)| keep.then_some(item))
+        .collect()
+}
+
 /// Trait used to unpack `Option<T>` values for smoother algorithm code
 pub trait ArbitrageEvaluateOption<T> {
     fn ok_or_arb_err(self) -> Result<T, ProgramError>;
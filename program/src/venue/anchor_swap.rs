@@ -0,0 +1,291 @@
+//! The Anchor swap program from the Solana Developers tutorial
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::{SwapLeg, SwapVenue};
+use crate::error::ArbitrageProgramError;
+use crate::idl::swap_program::swap;
+use crate::stable_swap::determine_stable_swap_receive;
+use crate::state::{Curve, Venue};
+use crate::swap::{apply_fee, determine_swap_receive};
+
+/// The `LiquidityPool` state, after its 8-byte Anchor discriminator
+#[derive(BorshDeserialize, Debug)]
+struct LiquidityPool {
+    assets: Vec<Pubkey>,
+    bump: u8,
+}
+
+/// A swap - constant-product, or StableSwap if the venue says so - whose
+/// Liquidity Pools are derived from the venue's pool seeds
+///
+/// The tutorial's swap has a single pool holding every asset it trades, but
+/// a fork might derive a pool for each pair of mints
+pub struct AnchorSwap<'v> {
+    pub venue: &'v Venue,
+}
+
+impl AnchorSwap<'_> {
+    /// Reads a per-pair pool's two mints and the bump it was derived with
+    fn read_pool(&self, pool: &AccountInfo) -> Result<(Pubkey, Pubkey, u8), ProgramError> {
+        let data = pool.data.borrow();
+        let state = if pool.owner.eq(&self.venue.program_id) && data.len() > 8 {
+            LiquidityPool::deserialize(&mut &data[8..]).ok()
+        } else {
+            None
+        };
+        match state {
+            Some(LiquidityPool { assets, bump }) if assets.len() == 2 => {
+                Ok((assets[0], assets[1], bump))
+            }
+            _ => {
+                msg!("Not a two-asset Liquidity Pool of the venue: {}", pool.key);
+                Err(ArbitrageProgramError::InvalidProgramAccount.into())
+            }
+        }
+    }
+}
+
+/// Prices a payment into a pool holding the leg's pool balances on a curve,
+/// before any fee on the amount received
+pub fn curve_receive(curve: &Curve, leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError> {
+    match curve {
+        Curve::ConstantProduct => determine_swap_receive(
+            leg.pool_receive.3,
+            leg.receive_mint.1,
+            leg.pool_pay.3,
+            leg.pay_mint.1,
+            pay_amount,
+        ),
+        Curve::StableSwap { amplification } => determine_stable_swap_receive(
+            *amplification,
+            leg.pool_receive.3,
+            leg.receive_mint.1,
+            leg.pool_pay.3,
+            leg.pay_mint.1,
+            pay_amount,
+        ),
+    }
+}
+
+impl SwapVenue for AnchorSwap<'_> {
+    /// Asserts the pool address is derived from the program ID and the
+    /// venue's pool seeds - filled in with the pool's own mints, if the venue
+    /// has a pool for each pair
+    fn check_pool(&self, pool: &AccountInfo) -> ProgramResult {
+        let (seeds, bump) = match self.venue.pool_bump {
+            Some(bump) => (self.venue.resolve_pool_seeds(None), bump),
+            None => {
+                let (mint_a, mint_b, bump) = self.read_pool(pool)?;
+                (
+                    self.venue.resolve_pool_seeds(Some((&mint_a, &mint_b))),
+                    bump,
+                )
+            }
+        };
+        let mut seeds = seeds.ok_or(ProgramError::InvalidInstructionData)?;
+        seeds.push(vec![bump]);
+        let seeds: Vec<&[u8]> = seeds.iter().map(|s| s.as_slice()).collect();
+        let expected = Pubkey::create_program_address(&seeds, &self.venue.program_id)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        if !expected.eq(pool.key) {
+            msg!("Expected pool address: {}", expected);
+            msg!("Got:                   {}", pool.key);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(())
+    }
+
+    fn quote(&self, leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError> {
+        curve_receive(
+            &self.venue.curve,
+            leg,
+            apply_fee(pay_amount, self.venue.fee_bps),
+        )
+    }
+
+    /// The accounts the IDL's `swap` names, in its order
+    fn swap_accounts<'a, 'b>(&self, leg: &SwapLeg<'a, 'b>) -> Vec<AccountInfo<'b>> {
+        swap::Accounts {
+            pool: leg.pool,
+            receive_mint: leg.receive_mint.0,
+            pool_receive_token_account: leg.pool_receive.0,
+            payer_receive_token_account: leg.user_receive.0,
+            pay_mint: leg.pay_mint.0,
+            pool_pay_token_account: leg.pool_pay.0,
+            payer_pay_token_account: leg.user_pay.0,
+            payer: leg.authority,
+            token_program: leg.token_program,
+            system_program: leg.system_program,
+            associated_token_program: leg.associated_token_program,
+        }
+        .to_account_infos()
+    }
+
+    /// Anchor's sighash for `swap`, followed by the amount to pay
+    fn swap_data(&self, amount: u64) -> Vec<u8> {
+        swap::Args {
+            amount_to_swap: amount,
+        }
+        .data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+    use solana_program::hash::hash;
+    use spl_associated_token_account::get_associated_token_address;
+
+    use super::*;
+    use crate::partial_state::{ArbitrageMintInfo, ArbitrageTokenAccountInfo};
+    use crate::state::{PoolSeed, VenueKind};
+
+    fn venue(program_id: Pubkey, pool_seeds: Vec<PoolSeed>, pool_bump: Option<u8>) -> Venue {
+        Venue {
+            program_id,
+            pool_seeds,
+            pool_bump,
+            fee_bps: 30,
+            disabled: false,
+            kind: VenueKind::AnchorSwap,
+            curve: Curve::ConstantProduct,
+        }
+    }
+
+    /// An account for the adapter to read
+    fn account(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            true,
+            Box::leak(Box::new(0)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    /// A per-pair `LiquidityPool`, behind an Anchor discriminator
+    fn pool_data(mint_a: Pubkey, mint_b: Pubkey, bump: u8) -> Vec<u8> {
+        let mut data = vec![0; 8];
+        data.extend((vec![mint_a, mint_b], bump).try_to_vec().unwrap());
+        data
+    }
+
+    #[test]
+    fn test_check_pool() {
+        let program_id = Pubkey::new_unique();
+
+        // The tutorial's single pool, whose bump is cached on the venue
+        let (address, bump) = Pubkey::find_program_address(&[b"liquidity_pool"], &program_id);
+        let single = venue(
+            program_id,
+            vec![PoolSeed::Literal(b"liquidity_pool".to_vec())],
+            Some(bump),
+        );
+        let adapter = AnchorSwap { venue: &single };
+        let pool = account(address, program_id, vec![]);
+        assert_eq!(adapter.check_pool(&pool), Ok(()));
+        assert_eq!(adapter.pool_token_owner(&pool), Ok(address));
+        let impostor = account(Pubkey::new_unique(), program_id, vec![]);
+        assert_eq!(
+            adapter.check_pool(&impostor),
+            Err(ProgramError::InvalidInstructionData)
+        );
+
+        // A pool per pair, derived from the mints it records
+        let per_pair = venue(
+            program_id,
+            vec![
+                PoolSeed::Literal(b"pool".to_vec()),
+                PoolSeed::MintA,
+                PoolSeed::MintB,
+            ],
+            None,
+        );
+        let adapter = AnchorSwap { venue: &per_pair };
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) =
+            Pubkey::find_program_address(&[b"pool", mint_a.as_ref(), mint_b.as_ref()], &program_id);
+        let pool = account(address, program_id, pool_data(mint_a, mint_b, bump));
+        assert_eq!(adapter.check_pool(&pool), Ok(()));
+        let swapped = account(address, program_id, pool_data(mint_b, mint_a, bump));
+        assert!(adapter.check_pool(&swapped).is_err());
+        let foreign = account(
+            address,
+            Pubkey::new_unique(),
+            pool_data(mint_a, mint_b, bump),
+        );
+        assert_eq!(
+            adapter.check_pool(&foreign),
+            Err(ArbitrageProgramError::InvalidProgramAccount.into())
+        );
+
+        // The pool's token accounts are its associated token accounts
+        let ata = account(
+            get_associated_token_address(&address, &mint_a),
+            spl_token::id(),
+            vec![],
+        );
+        assert_eq!(
+            adapter.check_pool_token_account(&pool, &ata, &mint_a),
+            Ok(())
+        );
+        assert_eq!(
+            adapter.check_pool_token_account(&pool, &ata, &mint_b),
+            Err(ArbitrageProgramError::InvalidAccountsList.into())
+        );
+    }
+
+    #[test]
+    fn test_quote_and_swap_instruction() {
+        let program_id = Pubkey::new_unique();
+        let venue = venue(program_id, vec![], Some(0));
+        let adapter = AnchorSwap { venue: &venue };
+        let keys = [(); 11].map(|_| Pubkey::new_unique());
+        let [pool, mint_a, mint_b, pool_a, pool_b, user_a, user_b, user, token, system, ata] =
+            keys.map(|key| account(key, Pubkey::default(), vec![]));
+        let program = account(program_id, Pubkey::default(), vec![]);
+        let a: ArbitrageMintInfo = (&mint_a, 6);
+        let b: ArbitrageMintInfo = (&mint_b, 6);
+        let pool_pay: ArbitrageTokenAccountInfo = (&pool_a, keys[1], keys[0], 1_000_000);
+        let pool_receive: ArbitrageTokenAccountInfo = (&pool_b, keys[2], keys[0], 2_000_000);
+        let user_pay: ArbitrageTokenAccountInfo = (&user_a, keys[1], keys[7], 50_000);
+        let user_receive: ArbitrageTokenAccountInfo = (&user_b, keys[2], keys[7], 0);
+        let leg = SwapLeg {
+            program: &program,
+            pool: &pool,
+            pay_mint: a,
+            receive_mint: b,
+            pool_pay,
+            pool_receive,
+            user_pay,
+            user_receive,
+            authority: &user,
+            token_program: &token,
+            system_program: &system,
+            associated_token_program: &ata,
+            venue_accounts: &[],
+        };
+
+        // 10,000 less the 0.3% fee, against 1,000,000 x 2,000,000
+        assert_eq!(adapter.quote(&leg, 10_000), Ok(19_743));
+        assert_eq!(adapter.amount_paid(&leg, 10_000), Ok(10_000));
+
+        // The accounts in the order the IDL names them, and the data Anchor
+        // expects
+        let accounts: Vec<Pubkey> = adapter.swap_accounts(&leg).iter().map(|a| *a.key).collect();
+        assert_eq!(
+            accounts,
+            [0, 2, 4, 6, 1, 3, 5, 7, 8, 9, 10].map(|i| keys[i]).to_vec()
+        );
+        let mut data = hash(b"global:swap").to_bytes()[..8].to_vec();
+        data.extend_from_slice(&10_000u64.to_le_bytes());
+        assert_eq!(adapter.swap_data(10_000), data);
+    }
+}
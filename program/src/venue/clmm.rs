@@ -0,0 +1,294 @@
+//! Concentrated-liquidity pools, laid out like the mock CLMM pool program in
+//! `mocks/clmm-pool`
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::{SwapLeg, SwapVenue};
+use crate::clmm::{swap_receive, ClmmState, ClmmTick};
+use crate::error::ArbitrageProgramError;
+use crate::state::Venue;
+
+/// How many tick arrays are passed along with each CLMM pool
+pub const TICK_ARRAYS_PER_SWAP: usize = 3;
+
+/// The first byte of a pool account
+const POOL_KIND: u8 = 1;
+
+/// The first byte of a tick array account
+const TICK_ARRAY_KIND: u8 = 2;
+
+/// The seed prefix of a pool PDA, followed by its two mints
+const POOL_SEED_PREFIX: &[u8] = b"clmm_pool";
+
+/// The `Swap` instruction's tag
+const SWAP_INSTRUCTION_TAG: u8 = 2;
+
+/// A CLMM pool's state
+#[derive(BorshDeserialize, Debug)]
+struct ClmmPool {
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    vault_a: Pubkey,
+    vault_b: Pubkey,
+    bump: u8,
+    fee_bps: u16,
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    tick_current: i32,
+}
+
+/// A set of a CLMM pool's initialized ticks
+#[derive(BorshDeserialize, Debug)]
+struct TickArray {
+    pool: Pubkey,
+    ticks: Vec<Tick>,
+}
+
+#[derive(BorshDeserialize, Debug)]
+struct Tick {
+    index: i32,
+    sqrt_price_x64: u128,
+    liquidity_net: i128,
+}
+
+/// A concentrated-liquidity pool, quoted by walking the initialized ticks in
+/// the tick arrays passed along with it
+///
+/// Its `Swap` instruction needs the tick arrays - `TICK_ARRAYS_PER_SWAP` of
+/// them, covering the ticks the price might cross in either direction
+pub struct Clmm<'v> {
+    pub venue: &'v Venue,
+}
+
+impl Clmm<'_> {
+    /// Reads one of the venue's accounts, asserting its kind
+    fn read<T: BorshDeserialize>(&self, info: &AccountInfo, kind: u8) -> Result<T, ProgramError> {
+        let data = info.data.borrow();
+        if !info.owner.eq(&self.venue.program_id) || data.first() != Some(&kind) {
+            msg!("Not a CLMM account of the venue: {}", info.key);
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        T::deserialize(&mut &data[1..]).map_err(|_| {
+            msg!("Failed to deserialize CLMM account: {}", info.key);
+            ArbitrageProgramError::InvalidProgramAccount.into()
+        })
+    }
+
+    /// Reads every initialized tick from the tick arrays, sorted by index
+    fn ticks(
+        &self,
+        pool: &AccountInfo,
+        tick_arrays: &[AccountInfo],
+    ) -> Result<Vec<ClmmTick>, ProgramError> {
+        let mut ticks = vec![];
+        for info in tick_arrays {
+            let tick_array: TickArray = self.read(info, TICK_ARRAY_KIND)?;
+            if !tick_array.pool.eq(pool.key) {
+                msg!("Tick array {} belongs to another pool", info.key);
+                return Err(ArbitrageProgramError::InvalidAccountsList.into());
+            }
+            ticks.extend(tick_array.ticks.into_iter().map(|t| ClmmTick {
+                index: t.index,
+                sqrt_price_x64: t.sqrt_price_x64,
+                liquidity_net: t.liquidity_net,
+            }));
+        }
+        ticks.sort_by_key(|t| t.index);
+        Ok(ticks)
+    }
+}
+
+impl SwapVenue for Clmm<'_> {
+    /// Asserts the pool is the venue's PDA for the two mints it records, so
+    /// an account merely owned by the venue can't pass for one
+    fn check_pool(&self, pool: &AccountInfo) -> ProgramResult {
+        let state: ClmmPool = self.read(pool, POOL_KIND)?;
+        match Pubkey::create_program_address(
+            &[
+                POOL_SEED_PREFIX,
+                state.mint_a.as_ref(),
+                state.mint_b.as_ref(),
+                &[state.bump],
+            ],
+            &self.venue.program_id,
+        ) {
+            Ok(address) if address.eq(pool.key) => Ok(()),
+            _ => {
+                msg!("Not the venue's pool for its mints: {}", pool.key);
+                Err(ArbitrageProgramError::InvalidProgramAccount.into())
+            }
+        }
+    }
+
+    /// Asserts the token account is one of the pool's two vaults
+    fn check_pool_token_account(
+        &self,
+        pool: &AccountInfo,
+        token_account: &AccountInfo,
+        _mint: &Pubkey,
+    ) -> ProgramResult {
+        let state = self.read::<ClmmPool>(pool, POOL_KIND)?;
+        if !(token_account.key.eq(&state.vault_a) || token_account.key.eq(&state.vault_b)) {
+            msg!(
+                "Not a token account of pool {}: {}",
+                pool.key,
+                token_account.key
+            );
+            return Err(ArbitrageProgramError::InvalidAccountsList.into());
+        }
+        Ok(())
+    }
+
+    fn venue_accounts_len(&self) -> usize {
+        TICK_ARRAYS_PER_SWAP
+    }
+
+    fn check_venue_accounts(&self, pool: &AccountInfo, accounts: &[AccountInfo]) -> ProgramResult {
+        self.ticks(pool, accounts).map(|_| ())
+    }
+
+    /// Walks the pool's ticks, or quotes nothing if the leg's assets aren't
+    /// the two the pool holds - or the tick arrays can't fill the payment
+    fn quote(&self, leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError> {
+        let pool: ClmmPool = self.read(leg.pool, POOL_KIND)?;
+        let a_to_b = if leg.pool_pay.0.key.eq(&pool.vault_a)
+            && leg.pool_receive.0.key.eq(&pool.vault_b)
+        {
+            true
+        } else if leg.pool_pay.0.key.eq(&pool.vault_b) && leg.pool_receive.0.key.eq(&pool.vault_a) {
+            false
+        } else {
+            return Ok(0);
+        };
+        let state = ClmmState {
+            sqrt_price_x64: pool.sqrt_price_x64,
+            liquidity: pool.liquidity,
+            tick_current: pool.tick_current,
+            fee_bps: pool.fee_bps,
+        };
+        let ticks = self.ticks(leg.pool, leg.venue_accounts)?;
+        Ok(swap_receive(&state, &ticks, a_to_b, pay_amount).unwrap_or(0))
+    }
+
+    fn swap_accounts<'a, 'b>(&self, leg: &SwapLeg<'a, 'b>) -> Vec<AccountInfo<'b>> {
+        let mut accounts = vec![
+            leg.pool.to_owned(),
+            leg.authority.to_owned(),
+            leg.user_pay.0.to_owned(),
+            leg.user_receive.0.to_owned(),
+            leg.pool_pay.0.to_owned(),
+            leg.pool_receive.0.to_owned(),
+            leg.pay_mint.0.to_owned(),
+            leg.receive_mint.0.to_owned(),
+            leg.token_program.to_owned(),
+        ];
+        accounts.extend(leg.venue_accounts.iter().cloned());
+        accounts
+    }
+
+    /// The `Swap` tag, the amount to pay, and no minimum amount out
+    fn swap_data(&self, amount: u64) -> Vec<u8> {
+        let mut data = vec![SWAP_INSTRUCTION_TAG];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+
+    use super::*;
+    use crate::state::{Curve, VenueKind};
+
+    #[derive(BorshSerialize)]
+    struct PoolData {
+        kind: u8,
+        mint_a: Pubkey,
+        mint_b: Pubkey,
+        vault_a: Pubkey,
+        vault_b: Pubkey,
+        bump: u8,
+        fee_bps: u16,
+        sqrt_price_x64: u128,
+        liquidity: u128,
+        tick_current: i32,
+    }
+
+    fn venue(program_id: Pubkey) -> Venue {
+        Venue {
+            program_id,
+            pool_seeds: vec![],
+            pool_bump: None,
+            fee_bps: 0,
+            disabled: false,
+            kind: VenueKind::Clmm,
+            curve: Curve::ConstantProduct,
+        }
+    }
+
+    fn pool_data(mint_a: Pubkey, mint_b: Pubkey, bump: u8) -> Vec<u8> {
+        PoolData {
+            kind: POOL_KIND,
+            mint_a,
+            mint_b,
+            vault_a: Pubkey::new_unique(),
+            vault_b: Pubkey::new_unique(),
+            bump,
+            fee_bps: 0,
+            sqrt_price_x64: crate::clmm::Q64,
+            liquidity: 1_000_000,
+            tick_current: 0,
+        }
+        .try_to_vec()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_pool_derivation() {
+        let program_id = Pubkey::new_unique();
+        let venue = venue(program_id);
+        let clmm = Clmm { venue: &venue };
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) = Pubkey::find_program_address(
+            &[POOL_SEED_PREFIX, mint_a.as_ref(), mint_b.as_ref()],
+            &program_id,
+        );
+        let mut lamports = 0;
+        let mut data = pool_data(mint_a, mint_b, bump);
+        let pool = AccountInfo::new(
+            &address,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &program_id,
+            false,
+            0,
+        );
+        assert_eq!(clmm.check_pool(&pool), Ok(()));
+
+        // The same state at any other address of the venue's is refused
+        let impostor = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = pool_data(mint_a, mint_b, bump);
+        let pool = AccountInfo::new(
+            &impostor,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &program_id,
+            false,
+            0,
+        );
+        assert_eq!(
+            clmm.check_pool(&pool),
+            Err(ArbitrageProgramError::InvalidProgramAccount.into())
+        );
+    }
+}
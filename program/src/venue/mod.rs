@@ -0,0 +1,133 @@
+//! Adapters for the swap programs (venues) the arbitrage program trades on
+//!
+//! Everything that differs from one DEX to the next - how its pools are
+//! found, how it prices a swap and how its `swap` instruction is laid out -
+//! lives behind the `SwapVenue` trait, so the arbitrage algorithm never has to
+//! know which venue it's trading on
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::error::ArbitrageProgramError;
+use crate::partial_state::{ArbitrageMintInfo, ArbitrageTokenAccountInfo};
+use crate::state::{Venue, VenueKind, VenueLayout};
+
+mod anchor_swap;
+mod clmm;
+mod order_book;
+mod registered;
+mod spl_token_swap;
+
+pub use anchor_swap::*;
+pub use clmm::*;
+pub use order_book::*;
+pub use registered::*;
+pub use spl_token_swap::*;
+
+/// One leg of a round trip: a payment of one asset into a venue's pool in
+/// return for another, along with every account the swap might need
+#[derive(Clone, Copy)]
+pub struct SwapLeg<'a, 'b> {
+    /// The venue's swap program
+    pub program: &'a AccountInfo<'b>,
+    pub pool: &'a AccountInfo<'b>,
+    pub pay_mint: ArbitrageMintInfo<'a, 'b>,
+    pub receive_mint: ArbitrageMintInfo<'a, 'b>,
+    /// The pool's token account for the asset paid
+    pub pool_pay: ArbitrageTokenAccountInfo<'a, 'b>,
+    /// The pool's token account for the asset received
+    pub pool_receive: ArbitrageTokenAccountInfo<'a, 'b>,
+    /// The trader's token account for the asset paid
+    pub user_pay: ArbitrageTokenAccountInfo<'a, 'b>,
+    /// The trader's token account for the asset received
+    pub user_receive: ArbitrageTokenAccountInfo<'a, 'b>,
+    /// The owner of the trader's token accounts, who authorizes the swap
+    pub authority: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub associated_token_program: &'a AccountInfo<'b>,
+    /// Any further accounts the venue's `swap` instruction needs
+    pub venue_accounts: &'a [AccountInfo<'b>],
+}
+
+/// A swap program the arbitrage program knows how to quote and trade on
+pub trait SwapVenue {
+    /// Asserts the provided pool is one this venue trades against
+    fn check_pool(&self, pool: &AccountInfo) -> ProgramResult;
+
+    /// The owner of the pool's token accounts
+    fn pool_token_owner(&self, pool: &AccountInfo) -> Result<Pubkey, ProgramError> {
+        Ok(*pool.key)
+    }
+
+    /// Asserts a token account is the pool's own account for a mint - by
+    /// default, the associated token account of the pool's token owner
+    fn check_pool_token_account(
+        &self,
+        pool: &AccountInfo,
+        token_account: &AccountInfo,
+        mint: &Pubkey,
+    ) -> ProgramResult {
+        let expected = get_associated_token_address(&self.pool_token_owner(pool)?, mint);
+        if !token_account.key.eq(&expected) {
+            msg!("Expected pool token account: {}", expected);
+            return Err(ArbitrageProgramError::InvalidAccountsList.into());
+        }
+        Ok(())
+    }
+
+    /// How many further accounts the venue's `swap` instruction needs, beyond
+    /// the ones every venue is given
+    fn venue_accounts_len(&self) -> usize {
+        0
+    }
+
+    /// Asserts the further accounts provided are the ones the pool's `swap`
+    /// instruction needs
+    fn check_venue_accounts(
+        &self,
+        _pool: &AccountInfo,
+        _accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Ok(())
+    }
+
+    /// Quotes how much of the leg's receiving asset a payment of `pay_amount`
+    /// returns, after the venue's fee
+    fn quote(&self, leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError>;
+
+    /// How much of a payment of `pay_amount` the venue takes - all of it,
+    /// unless it can only fill part
+    fn amount_paid(&self, _leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError> {
+        Ok(pay_amount)
+    }
+
+    /// Lays out the accounts the venue's `swap` instruction expects, in order
+    fn swap_accounts<'a, 'b>(&self, leg: &SwapLeg<'a, 'b>) -> Vec<AccountInfo<'b>>;
+
+    /// Encodes the venue's `swap` instruction data for a payment of `amount`
+    fn swap_data(&self, amount: u64) -> Vec<u8>;
+}
+
+/// Picks the adapter for an approved venue - along with its layout, if it's
+/// a registered venue
+pub fn adapter<'v>(
+    venue: &'v Venue,
+    layout: Option<&'v VenueLayout>,
+) -> Result<Box<dyn SwapVenue + 'v>, ProgramError> {
+    Ok(match venue.kind {
+        VenueKind::AnchorSwap => Box::new(AnchorSwap { venue }),
+        VenueKind::SplTokenSwap => Box::new(SplTokenSwap { venue }),
+        VenueKind::Clmm => Box::new(Clmm { venue }),
+        VenueKind::OrderBook => Box::new(OrderBook { venue }),
+        VenueKind::Registered => match layout {
+            Some(layout) => Box::new(Registered { venue, layout }),
+            None => {
+                msg!("Venue {} needs its layout to trade", venue.program_id);
+                return Err(ArbitrageProgramError::InvalidAccountsList.into());
+            }
+        },
+    })
+}
@@ -0,0 +1,195 @@
+//! Central limit order books, laid out like the mock order-book program in
+//! `mocks/order-book`
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::{SwapLeg, SwapVenue};
+use crate::error::ArbitrageProgramError;
+use crate::order_book::{fill, BookLevel, Fill};
+use crate::state::Venue;
+
+/// The first byte of a market account
+const MARKET_KIND: u8 = 1;
+
+/// The first byte of a slab account
+const SLAB_KIND: u8 = 2;
+
+/// The `PlaceIocOrder` instruction's tag
+const PLACE_IOC_ORDER_INSTRUCTION_TAG: u8 = 2;
+
+/// A market's state
+#[derive(BorshDeserialize, Debug)]
+struct Market {
+    _authority: Pubkey,
+    _base_mint: Pubkey,
+    _quote_mint: Pubkey,
+    base_vault: Pubkey,
+    quote_vault: Pubkey,
+    bids: Pubkey,
+    asks: Pubkey,
+    _bump: u8,
+    base_lot_size: u64,
+    fee_bps: u16,
+}
+
+/// One side of a market's book, best price first
+#[derive(BorshDeserialize, Debug)]
+struct Slab {
+    _market: Pubkey,
+    _is_bids: bool,
+    levels: Vec<Level>,
+}
+
+#[derive(BorshDeserialize, Debug)]
+struct Level {
+    price: u64,
+    quantity: u64,
+}
+
+/// An order book, whose market account stands in for the pool - it owns the
+/// vaults - and which is traded on with immediate-or-cancel orders
+///
+/// Its `PlaceIocOrder` instruction needs the market's bid and ask slabs. The
+/// book only takes what fills in whole lots, so a leg might leave some of its
+/// payment with the trader
+pub struct OrderBook<'v> {
+    pub venue: &'v Venue,
+}
+
+impl OrderBook<'_> {
+    /// Reads one of the venue's accounts, asserting its kind
+    fn read<T: BorshDeserialize>(&self, info: &AccountInfo, kind: u8) -> Result<T, ProgramError> {
+        let data = info.data.borrow();
+        if !info.owner.eq(&self.venue.program_id) || data.first() != Some(&kind) {
+            msg!("Not an order-book account of the venue: {}", info.key);
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        T::deserialize(&mut &data[1..]).map_err(|_| {
+            msg!("Failed to deserialize order-book account: {}", info.key);
+            ArbitrageProgramError::InvalidProgramAccount.into()
+        })
+    }
+
+    /// Fills a payment against the side of the book the leg takes from, or
+    /// nothing if the leg's assets aren't the market's two
+    fn walk(&self, leg: &SwapLeg, pay_amount: u64) -> Result<Option<Fill>, ProgramError> {
+        let market: Market = self.read(leg.pool, MARKET_KIND)?;
+        let selling_base = if leg.pool_pay.0.key.eq(&market.base_vault)
+            && leg.pool_receive.0.key.eq(&market.quote_vault)
+        {
+            true
+        } else if leg.pool_pay.0.key.eq(&market.quote_vault)
+            && leg.pool_receive.0.key.eq(&market.base_vault)
+        {
+            false
+        } else {
+            return Ok(None);
+        };
+        // Selling base takes from the bids, buying it takes from the asks
+        let slab_info = match leg.venue_accounts {
+            [bids, asks] => {
+                if selling_base {
+                    bids
+                } else {
+                    asks
+                }
+            }
+            _ => return Err(ArbitrageProgramError::InvalidAccountsList.into()),
+        };
+        let slab: Slab = self.read(slab_info, SLAB_KIND)?;
+        let levels: Vec<BookLevel> = slab
+            .levels
+            .iter()
+            .map(|l| BookLevel {
+                price: l.price,
+                quantity: l.quantity,
+            })
+            .collect();
+        Ok(fill(
+            &levels,
+            market.base_lot_size,
+            market.fee_bps,
+            selling_base,
+            pay_amount,
+        ))
+    }
+}
+
+impl SwapVenue for OrderBook<'_> {
+    fn check_pool(&self, pool: &AccountInfo) -> ProgramResult {
+        self.read::<Market>(pool, MARKET_KIND).map(|_| ())
+    }
+
+    /// Asserts the token account is one of the market's two vaults
+    fn check_pool_token_account(
+        &self,
+        pool: &AccountInfo,
+        token_account: &AccountInfo,
+        _mint: &Pubkey,
+    ) -> ProgramResult {
+        let market = self.read::<Market>(pool, MARKET_KIND)?;
+        if !(token_account.key.eq(&market.base_vault) || token_account.key.eq(&market.quote_vault))
+        {
+            msg!(
+                "Not a token account of market {}: {}",
+                pool.key,
+                token_account.key
+            );
+            return Err(ArbitrageProgramError::InvalidAccountsList.into());
+        }
+        Ok(())
+    }
+
+    fn venue_accounts_len(&self) -> usize {
+        2
+    }
+
+    fn check_venue_accounts(&self, pool: &AccountInfo, accounts: &[AccountInfo]) -> ProgramResult {
+        let market: Market = self.read(pool, MARKET_KIND)?;
+        match accounts {
+            [bids, asks] if bids.key.eq(&market.bids) && asks.key.eq(&market.asks) => Ok(()),
+            _ => {
+                msg!("Expected the bid and ask slabs of market {}", pool.key);
+                Err(ArbitrageProgramError::InvalidAccountsList.into())
+            }
+        }
+    }
+
+    /// Walks the side of the book the leg takes from, or quotes nothing if
+    /// the leg's assets aren't the market's two
+    fn quote(&self, leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError> {
+        Ok(self.walk(leg, pay_amount)?.map_or(0, |f| f.amount_received))
+    }
+
+    /// Only what fills in whole lots
+    fn amount_paid(&self, leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError> {
+        Ok(self.walk(leg, pay_amount)?.map_or(0, |f| f.amount_paid))
+    }
+
+    fn swap_accounts<'a, 'b>(&self, leg: &SwapLeg<'a, 'b>) -> Vec<AccountInfo<'b>> {
+        let mut accounts = vec![
+            leg.pool.to_owned(),
+            leg.authority.to_owned(),
+            leg.user_pay.0.to_owned(),
+            leg.user_receive.0.to_owned(),
+            leg.pool_pay.0.to_owned(),
+            leg.pool_receive.0.to_owned(),
+            leg.pay_mint.0.to_owned(),
+            leg.receive_mint.0.to_owned(),
+            leg.token_program.to_owned(),
+        ];
+        accounts.extend(leg.venue_accounts.iter().cloned());
+        accounts
+    }
+
+    /// The `PlaceIocOrder` tag, the amount to pay, and no minimum amount out
+    fn swap_data(&self, amount: u64) -> Vec<u8> {
+        let mut data = vec![PLACE_IOC_ORDER_INSTRUCTION_TAG];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data
+    }
+}
@@ -0,0 +1,104 @@
+//! Anchor-style swap forks described by an on-chain `VenueLayout`
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+};
+
+use super::{curve_receive, AnchorSwap, SwapLeg, SwapVenue};
+use crate::error::ArbitrageProgramError;
+use crate::state::{AccountSlot, ArgSlot, FeeModel, Venue, VenueAccountCheck, VenueLayout};
+use crate::swap::apply_fee;
+
+/// A swap whose `swap` instruction and pricing are read from its layout at
+/// runtime, so operations can onboard a fork by writing data
+///
+/// Its pools are derived from the venue's pool seeds, as the Anchor swap's
+/// are
+pub struct Registered<'v> {
+    pub venue: &'v Venue,
+    pub layout: &'v VenueLayout,
+}
+
+impl SwapVenue for Registered<'_> {
+    fn check_pool(&self, pool: &AccountInfo) -> ProgramResult {
+        AnchorSwap { venue: self.venue }.check_pool(pool)
+    }
+
+    fn venue_accounts_len(&self) -> usize {
+        self.layout.venue_accounts.len()
+    }
+
+    /// Checks each venue account against the key or owner its layout pins,
+    /// if any - an account the layout leaves as `Any` is forwarded as passed
+    fn check_venue_accounts(&self, _pool: &AccountInfo, accounts: &[AccountInfo]) -> ProgramResult {
+        for (i, (check, account)) in self.layout.venue_accounts.iter().zip(accounts).enumerate() {
+            match check {
+                VenueAccountCheck::Any => {}
+                VenueAccountCheck::Key(key) => {
+                    if !account.key.eq(key) {
+                        msg!("Expected venue account {}: {}", i, key);
+                        return Err(ArbitrageProgramError::InvalidAccountsList.into());
+                    }
+                }
+                VenueAccountCheck::Owner(owner) => {
+                    if !account.owner.eq(owner) {
+                        msg!("Venue account {} must be owned by: {}", i, owner);
+                        return Err(ArbitrageProgramError::InvalidAccountsList.into());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Prices the payment on the layout's curve, taking its fee from
+    /// whichever side the layout says
+    fn quote(&self, leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError> {
+        match self.layout.fee {
+            FeeModel::OnInput { bps } => {
+                curve_receive(&self.layout.curve, leg, apply_fee(pay_amount, bps))
+            }
+            FeeModel::OnOutput { bps } => {
+                curve_receive(&self.layout.curve, leg, pay_amount).map(|r| apply_fee(r, bps))
+            }
+        }
+    }
+
+    /// Fills in the layout's account slots from the leg
+    fn swap_accounts<'a, 'b>(&self, leg: &SwapLeg<'a, 'b>) -> Vec<AccountInfo<'b>> {
+        self.layout
+            .accounts
+            .iter()
+            .map(|slot| {
+                match slot {
+                    AccountSlot::Pool => leg.pool,
+                    AccountSlot::PayMint => leg.pay_mint.0,
+                    AccountSlot::ReceiveMint => leg.receive_mint.0,
+                    AccountSlot::PoolPay => leg.pool_pay.0,
+                    AccountSlot::PoolReceive => leg.pool_receive.0,
+                    AccountSlot::PayerPay => leg.user_pay.0,
+                    AccountSlot::PayerReceive => leg.user_receive.0,
+                    AccountSlot::Authority => leg.authority,
+                    AccountSlot::TokenProgram => leg.token_program,
+                    AccountSlot::SystemProgram => leg.system_program,
+                    AccountSlot::AssociatedTokenProgram => leg.associated_token_program,
+                    // Validated against the venue's accounts when the layout
+                    // was written, and checked as they were passed
+                    AccountSlot::VenueAccount(i) => &leg.venue_accounts[*i as usize],
+                }
+                .to_owned()
+            })
+            .collect()
+    }
+
+    /// The layout's discriminator, followed by its arguments
+    fn swap_data(&self, amount: u64) -> Vec<u8> {
+        let mut data = self.layout.discriminator.clone();
+        for arg in &self.layout.args {
+            match arg {
+                ArgSlot::Amount => data.extend_from_slice(&amount.to_le_bytes()),
+                ArgSlot::MinimumAmountOut => data.extend_from_slice(&0u64.to_le_bytes()),
+            }
+        }
+        data
+    }
+}
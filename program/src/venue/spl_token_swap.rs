@@ -0,0 +1,511 @@
+//! The canonical SPL Token Swap program
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::{SwapLeg, SwapVenue};
+use crate::error::ArbitrageProgramError;
+use crate::stable_swap;
+use crate::state::Venue;
+
+/// The size of a `SwapVersion::SwapV1` account, including its version byte
+const SWAP_V1_LEN: usize = 324;
+
+/// SPL Token Swap's `Swap` instruction tag
+const SWAP_INSTRUCTION_TAG: u8 = 1;
+
+/// The curves an SPL Token Swap pool can price with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplCurveType {
+    ConstantProduct,
+    /// Token B is worth a fixed amount of token A
+    ConstantPrice,
+    Stable,
+    /// A constant product curve with a fixed amount added to token B's
+    /// balance
+    Offset,
+}
+
+/// A fee as a fraction of the amount paid
+#[derive(Clone, Copy, Debug)]
+pub struct SplFee {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl SplFee {
+    /// The fee on an amount, rounded up to at least 1 as the swap program
+    /// does
+    fn of(&self, amount: u64) -> Option<u64> {
+        if self.numerator == 0 || amount == 0 {
+            return Some(0);
+        }
+        let fee = (amount as u128)
+            .checked_mul(self.numerator as u128)?
+            .checked_div(self.denominator as u128)?;
+        Some(fee.max(1) as u64)
+    }
+}
+
+/// The parts of SPL Token Swap's `SwapV1` state the arbitrage program needs,
+/// read straight from the swap account's data
+#[derive(Debug)]
+pub struct SplSwapState {
+    pub bump_seed: u8,
+    pub token_a: Pubkey,
+    pub token_b: Pubkey,
+    pub pool_mint: Pubkey,
+    pub pool_fee_account: Pubkey,
+    pub trade_fee: SplFee,
+    pub owner_trade_fee: SplFee,
+    pub curve_type: SplCurveType,
+    /// The curve's single parameter: token B's price for a constant price
+    /// curve, the amplification coefficient for a stable curve, or token B's
+    /// offset for an offset curve
+    pub curve_parameter: u64,
+}
+
+impl SplSwapState {
+    /// Reads a swap account's state, asserting it's an initialized `SwapV1`
+    pub fn try_deserialize(swap: &AccountInfo) -> Result<Self, ProgramError> {
+        let data = swap.data.borrow();
+        // Version 1, initialized
+        if data.len() < SWAP_V1_LEN || data[0] != 1 || data[1] != 1 {
+            msg!("Not an initialized SPL Token Swap account: {}", swap.key);
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        let pubkey = |offset: usize| {
+            let mut bytes = [0u8; 32];
+            bytes.copy_from_slice(&data[offset..offset + 32]);
+            Pubkey::new_from_array(bytes)
+        };
+        let u64_at = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&data[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let curve_type = match data[291] {
+            0 => SplCurveType::ConstantProduct,
+            1 => SplCurveType::ConstantPrice,
+            2 => SplCurveType::Stable,
+            3 => SplCurveType::Offset,
+            _ => {
+                msg!("Unknown SPL Token Swap curve: {}", data[291]);
+                return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+            }
+        };
+        Ok(Self {
+            bump_seed: data[2],
+            token_a: pubkey(35),
+            token_b: pubkey(67),
+            pool_mint: pubkey(99),
+            pool_fee_account: pubkey(195),
+            trade_fee: SplFee {
+                numerator: u64_at(227),
+                denominator: u64_at(235),
+            },
+            owner_trade_fee: SplFee {
+                numerator: u64_at(243),
+                denominator: u64_at(251),
+            },
+            curve_type,
+            curve_parameter: u64_at(292),
+        })
+    }
+
+    /// The swap authority PDA, which owns the pool's token accounts
+    pub fn authority(&self, program_id: &Pubkey, swap: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(&[swap.as_ref(), &[self.bump_seed]], program_id).map_err(
+            |_| {
+                msg!("Invalid SPL Token Swap authority bump for: {}", swap);
+                ArbitrageProgramError::InvalidProgramAccount.into()
+            },
+        )
+    }
+
+    /// Quotes a swap the way the swap program would, trading the whole
+    /// amount after fees against its curve
+    ///
+    /// Returns `None` if the amount can't be traded
+    fn swap_receive(
+        &self,
+        a_to_b: bool,
+        pool_pay_balance: u64,
+        pool_receive_balance: u64,
+        pay_amount: u64,
+    ) -> Option<u64> {
+        let fees = self
+            .trade_fee
+            .of(pay_amount)?
+            .checked_add(self.owner_trade_fee.of(pay_amount)?)?;
+        let p = pay_amount.checked_sub(fees)? as u128;
+        let (big_p, big_r) = (pool_pay_balance as u128, pool_receive_balance as u128);
+        let r = match self.curve_type {
+            SplCurveType::ConstantProduct => constant_product(big_p, big_r, p)?,
+            SplCurveType::Offset => {
+                // The offset is added to token B's balance, wherever it sits
+                let offset = self.curve_parameter as u128;
+                let (big_p, big_r) = if a_to_b {
+                    (big_p, big_r.checked_add(offset)?)
+                } else {
+                    (big_p.checked_add(offset)?, big_r)
+                };
+                constant_product(big_p, big_r, p)?
+            }
+            SplCurveType::ConstantPrice => {
+                let price = self.curve_parameter as u128;
+                if a_to_b {
+                    p.checked_div(price)?
+                } else {
+                    p.checked_mul(price)?
+                }
+            }
+            SplCurveType::Stable => {
+                stable_swap::swap_receive(self.curve_parameter, big_p, big_r, p)?
+            }
+        };
+        // Never quote more than the pool actually holds
+        (r <= pool_receive_balance as u128).then_some(r as u64)
+    }
+}
+
+/// The constant-product curve in integer math, rounding the pool's new
+/// balance up - and so the amount received down - as the swap program does
+fn constant_product(big_p: u128, big_r: u128, p: u128) -> Option<u128> {
+    let invariant = big_p.checked_mul(big_r)?;
+    let new_p = big_p.checked_add(p)?;
+    let new_r = invariant
+        .checked_add(new_p.checked_sub(1)?)?
+        .checked_div(new_p)?;
+    big_r.checked_sub(new_r)
+}
+
+/// A pool on the SPL Token Swap program: a swap account holding two assets,
+/// whose token accounts belong to the swap's authority PDA
+///
+/// The pool's own fees - not the venue's fee tier - are used when quoting,
+/// and its `Swap` instruction also needs the swap authority, the pool mint
+/// and the pool's fee account, in that order
+pub struct SplTokenSwap<'v> {
+    pub venue: &'v Venue,
+}
+
+impl SplTokenSwap<'_> {
+    /// Reads the pool's state, asserting it's owned by the venue's program
+    fn state(&self, pool: &AccountInfo) -> Result<SplSwapState, ProgramError> {
+        if !pool.owner.eq(&self.venue.program_id) {
+            msg!("Pool not owned by the SPL Token Swap venue: {}", pool.key);
+            return Err(ArbitrageProgramError::InvalidProgramAccount.into());
+        }
+        SplSwapState::try_deserialize(pool)
+    }
+}
+
+impl SwapVenue for SplTokenSwap<'_> {
+    /// Asserts the pool is an initialized swap account of the venue's program
+    fn check_pool(&self, pool: &AccountInfo) -> ProgramResult {
+        self.state(pool).map(|_| ())
+    }
+
+    fn pool_token_owner(&self, pool: &AccountInfo) -> Result<Pubkey, ProgramError> {
+        self.state(pool)?
+            .authority(&self.venue.program_id, pool.key)
+    }
+
+    /// Asserts the token account is one of the pool's two token accounts
+    fn check_pool_token_account(
+        &self,
+        pool: &AccountInfo,
+        token_account: &AccountInfo,
+        _mint: &Pubkey,
+    ) -> ProgramResult {
+        let state = self.state(pool)?;
+        if !(token_account.key.eq(&state.token_a) || token_account.key.eq(&state.token_b)) {
+            msg!(
+                "Not a token account of pool {}: {}",
+                pool.key,
+                token_account.key
+            );
+            return Err(ArbitrageProgramError::InvalidAccountsList.into());
+        }
+        Ok(())
+    }
+
+    fn venue_accounts_len(&self) -> usize {
+        3
+    }
+
+    /// Asserts the swap authority, pool mint and pool fee account are the
+    /// pool's own
+    fn check_venue_accounts(&self, pool: &AccountInfo, accounts: &[AccountInfo]) -> ProgramResult {
+        let state = self.state(pool)?;
+        let expected = [
+            state.authority(&self.venue.program_id, pool.key)?,
+            state.pool_mint,
+            state.pool_fee_account,
+        ];
+        if accounts.len() != expected.len()
+            || accounts.iter().zip(&expected).any(|(a, e)| !a.key.eq(e))
+        {
+            msg!("Expected SPL Token Swap accounts: {:?}", expected);
+            return Err(ArbitrageProgramError::InvalidAccountsList.into());
+        }
+        Ok(())
+    }
+
+    /// Quotes with the pool's curve and fees, or quotes nothing if the leg's
+    /// assets aren't the two the pool holds
+    fn quote(&self, leg: &SwapLeg, pay_amount: u64) -> Result<u64, ProgramError> {
+        let state = self.state(leg.pool)?;
+        let a_to_b = if leg.pool_pay.0.key.eq(&state.token_a)
+            && leg.pool_receive.0.key.eq(&state.token_b)
+        {
+            true
+        } else if leg.pool_pay.0.key.eq(&state.token_b) && leg.pool_receive.0.key.eq(&state.token_a)
+        {
+            false
+        } else {
+            return Ok(0);
+        };
+        Ok(state
+            .swap_receive(a_to_b, leg.pool_pay.3, leg.pool_receive.3, pay_amount)
+            .unwrap_or(0))
+    }
+
+    fn swap_accounts<'a, 'b>(&self, leg: &SwapLeg<'a, 'b>) -> Vec<AccountInfo<'b>> {
+        let mut accounts = vec![
+            leg.pool.to_owned(),
+            leg.venue_accounts[0].to_owned(),
+            leg.authority.to_owned(),
+            leg.user_pay.0.to_owned(),
+            leg.pool_pay.0.to_owned(),
+            leg.pool_receive.0.to_owned(),
+            leg.user_receive.0.to_owned(),
+        ];
+        accounts.extend(leg.venue_accounts[1..].iter().cloned());
+        accounts.push(leg.token_program.to_owned());
+        accounts
+    }
+
+    /// The `Swap` tag, the amount to pay, and - as with the Anchor swap - no
+    /// minimum amount out
+    fn swap_data(&self, amount: u64) -> Vec<u8> {
+        let mut data = vec![SWAP_INSTRUCTION_TAG];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::instruction::AccountMeta;
+
+    use super::*;
+    use crate::partial_state::{ArbitrageMintInfo, ArbitrageTokenAccountInfo};
+    use crate::state::{Curve, VenueKind};
+    use crate::util::ToAccountMeta;
+
+    /// The keys a `SwapV1` account refers to
+    struct Keys {
+        program_id: Pubkey,
+        swap: Pubkey,
+        bump_seed: u8,
+        token_a: Pubkey,
+        token_b: Pubkey,
+        pool_mint: Pubkey,
+        token_a_mint: Pubkey,
+        token_b_mint: Pubkey,
+        pool_fee_account: Pubkey,
+    }
+
+    impl Keys {
+        fn new() -> Self {
+            let program_id = Pubkey::new_unique();
+            let swap = Pubkey::new_unique();
+            let (_, bump_seed) = Pubkey::find_program_address(&[swap.as_ref()], &program_id);
+            Self {
+                program_id,
+                swap,
+                bump_seed,
+                token_a: Pubkey::new_unique(),
+                token_b: Pubkey::new_unique(),
+                pool_mint: Pubkey::new_unique(),
+                token_a_mint: Pubkey::new_unique(),
+                token_b_mint: Pubkey::new_unique(),
+                pool_fee_account: Pubkey::new_unique(),
+            }
+        }
+    }
+
+    /// A `SwapVersion::SwapV1` account as the swap program packs it: a
+    /// constant product pool with 0.25% trade and 0.05% owner trade fees
+    fn swap_v1_fixture(keys: &Keys) -> Vec<u8> {
+        let mut data = vec![1, 1, keys.bump_seed];
+        for key in [
+            &spl_token::id(),
+            &keys.token_a,
+            &keys.token_b,
+            &keys.pool_mint,
+            &keys.token_a_mint,
+            &keys.token_b_mint,
+            &keys.pool_fee_account,
+        ] {
+            data.extend_from_slice(key.as_ref());
+        }
+        // Trade, owner trade, owner withdraw and host fees
+        for fee in [25u64, 10_000, 5, 10_000, 0, 0, 20, 100] {
+            data.extend_from_slice(&fee.to_le_bytes());
+        }
+        // Constant product, whose curve has no parameters
+        data.push(0);
+        data.extend_from_slice(&[0; 32]);
+        assert_eq!(data.len(), SWAP_V1_LEN);
+        data
+    }
+
+    fn venue(program_id: Pubkey) -> Venue {
+        Venue {
+            program_id,
+            pool_seeds: vec![],
+            pool_bump: None,
+            fee_bps: 0,
+            disabled: false,
+            kind: VenueKind::SplTokenSwap,
+            curve: Curve::ConstantProduct,
+        }
+    }
+
+    /// An account for the adapter to read - only the swap account's data
+    /// is, since the leg carries the balances
+    fn account(
+        key: Pubkey,
+        owner: Pubkey,
+        data: Vec<u8>,
+        is_writable: bool,
+    ) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            is_writable,
+            Box::leak(Box::new(0)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_swap_v1_fixture() {
+        let keys = Keys::new();
+        let venue = venue(keys.program_id);
+        let adapter = SplTokenSwap { venue: &venue };
+        let authority = Pubkey::create_program_address(
+            &[keys.swap.as_ref(), &[keys.bump_seed]],
+            &keys.program_id,
+        )
+        .unwrap();
+        let user = Pubkey::new_unique();
+        let user_a = Pubkey::new_unique();
+        let user_b = Pubkey::new_unique();
+        let token_program = spl_token::id();
+
+        let pool = account(keys.swap, keys.program_id, swap_v1_fixture(&keys), true);
+        let program = account(keys.program_id, Pubkey::default(), vec![], false);
+        let authority_info = account(authority, Pubkey::default(), vec![], false);
+        let pool_mint = account(keys.pool_mint, token_program, vec![], true);
+        let pool_fee_account = account(keys.pool_fee_account, token_program, vec![], true);
+        let token_a = account(keys.token_a, token_program, vec![], true);
+        let token_b = account(keys.token_b, token_program, vec![], true);
+        let user_a_info = account(user_a, token_program, vec![], true);
+        let user_b_info = account(user_b, token_program, vec![], true);
+        let mint_a = account(keys.token_a_mint, token_program, vec![], false);
+        let mint_b = account(keys.token_b_mint, token_program, vec![], false);
+        let mut user_info = account(user, Pubkey::default(), vec![], true);
+        user_info.is_signer = true;
+        let token_program_info = account(token_program, Pubkey::default(), vec![], false);
+        let venue_accounts = [authority_info, pool_mint, pool_fee_account];
+
+        let state = SplSwapState::try_deserialize(&pool).unwrap();
+        assert_eq!(state.curve_type, SplCurveType::ConstantProduct);
+        assert_eq!(state.token_a, keys.token_a);
+        assert_eq!(state.token_b, keys.token_b);
+        assert_eq!(state.pool_mint, keys.pool_mint);
+        assert_eq!(state.pool_fee_account, keys.pool_fee_account);
+        adapter.check_pool(&pool).unwrap();
+        assert_eq!(adapter.pool_token_owner(&pool).unwrap(), authority);
+        adapter
+            .check_venue_accounts(&pool, &venue_accounts)
+            .unwrap();
+        assert!(adapter
+            .check_venue_accounts(
+                &pool,
+                &[
+                    venue_accounts[1].clone(),
+                    venue_accounts[0].clone(),
+                    venue_accounts[2].clone()
+                ]
+            )
+            .is_err());
+
+        let a: ArbitrageMintInfo = (&mint_a, 6);
+        let b: ArbitrageMintInfo = (&mint_b, 6);
+        let pool_a: ArbitrageTokenAccountInfo = (&token_a, keys.token_a_mint, authority, 1_000_000);
+        let pool_b: ArbitrageTokenAccountInfo = (&token_b, keys.token_b_mint, authority, 2_000_000);
+        let user_pay_a: ArbitrageTokenAccountInfo = (&user_a_info, keys.token_a_mint, user, 50_000);
+        let user_pay_b: ArbitrageTokenAccountInfo = (&user_b_info, keys.token_b_mint, user, 50_000);
+        let leg =
+            |pay_mint, receive_mint, pool_pay, pool_receive, user_pay, user_receive| SwapLeg {
+                program: &program,
+                pool: &pool,
+                pay_mint,
+                receive_mint,
+                pool_pay,
+                pool_receive,
+                user_pay,
+                user_receive,
+                authority: &user_info,
+                token_program: &token_program_info,
+                system_program: &token_program_info,
+                associated_token_program: &token_program_info,
+                venue_accounts: &venue_accounts,
+            };
+        let a_to_b = leg(a, b, pool_a, pool_b, user_pay_a, user_pay_b);
+        let b_to_a = leg(b, a, pool_b, pool_a, user_pay_b, user_pay_a);
+
+        // 10,000 less 25 and 5 in fees, against 1,000,000 x 2,000,000
+        assert_eq!(adapter.quote(&a_to_b, 10_000).unwrap(), 19_743);
+        // 5,000 less 12 and 2 in fees - each rounded down
+        assert_eq!(adapter.quote(&b_to_a, 5_000).unwrap(), 2_486);
+        // A leg whose token accounts aren't the pool's quotes nothing
+        let wrong = leg(a, b, user_pay_a, pool_b, user_pay_a, user_pay_b);
+        assert_eq!(adapter.quote(&wrong, 10_000).unwrap(), 0);
+
+        let metas: Vec<AccountMeta> = adapter
+            .swap_accounts(&a_to_b)
+            .iter()
+            .map(|a| a.to_account_meta())
+            .collect();
+        assert_eq!(
+            metas,
+            vec![
+                AccountMeta::new(keys.swap, false),
+                AccountMeta::new_readonly(authority, false),
+                AccountMeta::new(user, true),
+                AccountMeta::new(user_a, false),
+                AccountMeta::new(keys.token_a, false),
+                AccountMeta::new(keys.token_b, false),
+                AccountMeta::new(user_b, false),
+                AccountMeta::new(keys.pool_mint, false),
+                AccountMeta::new(keys.pool_fee_account, false),
+                AccountMeta::new_readonly(token_program, false),
+            ]
+        );
+        let mut data = vec![1];
+        data.extend_from_slice(&10_000u64.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        assert_eq!(adapter.swap_data(10_000), data);
+    }
+}
@@ -3,12 +3,31 @@ import {
     getAssociatedTokenAddressSync,
     TokenAccountNotFoundError,
     createAccount as createTokenAccount,
+    createMint,
+    getOrCreateAssociatedTokenAccount,
+    mintTo,
 } from '@solana/spl-token'
-import { PublicKey, SendTransactionError } from '@solana/web3.js'
+import { Keypair, PublicKey, SendTransactionError } from '@solana/web3.js'
+import { expect } from 'chai'
 import { sleepSeconds } from './util'
 import assetsConfig from './util/assets.json'
 import { ARBITRAGE_PROGRAM, CONNECTION, PAYER } from './util/const'
-import { createArbitrageInstruction, getPoolAddress } from './util/instruction'
+import {
+    createAddVenueInstruction,
+    createAllowMintInstruction,
+    createArbitrageInstruction,
+    createInitializeConfigInstruction,
+    createInitializeMintRegistryInstruction,
+    createInitializePairCooldownsInstruction,
+    createInitializeRiskAccountInstruction,
+    createInitializeStatsInstruction,
+    getConfigAddress,
+    getMintRegistryAddress,
+    getPairCooldownsAddress,
+    getRiskAddress,
+    getStatsAddress,
+    getPoolAddress,
+} from './util/instruction'
 import { mintExistingTokens } from './util/token'
 import {
     buildTransactionV0,
@@ -129,6 +148,251 @@ describe('Arbitrage Bot', async () => {
         }
     )
 
+    /**
+     * Creates the program's config - if it doesn't exist yet - and approves
+     * both swap programs as venues
+     */
+    it('Approve swap programs', async () => {
+        const instructions = []
+        if (
+            (await connection.getAccountInfo(
+                getConfigAddress(arbProgram.publicKey)
+            )) === null
+        ) {
+            instructions.push(
+                createInitializeConfigInstruction(
+                    arbProgram.publicKey,
+                    payer.publicKey
+                )
+            )
+        }
+        if (
+            (await connection.getAccountInfo(
+                getPairCooldownsAddress(arbProgram.publicKey)
+            )) === null
+        ) {
+            instructions.push(
+                createInitializePairCooldownsInstruction(
+                    arbProgram.publicKey,
+                    payer.publicKey
+                )
+            )
+        }
+        for (const swapProgram of [SWAP_PROGRAM_1, SWAP_PROGRAM_2]) {
+            instructions.push(
+                createAddVenueInstruction(
+                    arbProgram.publicKey,
+                    payer.publicKey,
+                    swapProgram,
+                    0
+                )
+            )
+        }
+        const tx = await buildTransactionV0(
+            connection,
+            instructions,
+            payer.publicKey,
+            [payer]
+        )
+        await connection.sendTransaction(tx)
+        await sleepSeconds(2)
+    })
+
+    /**
+     * Creates the mint registry - if it doesn't exist yet - and approves
+     * every asset for trading
+     */
+    it('Approve mints', async () => {
+        if (
+            (await connection.getAccountInfo(
+                getMintRegistryAddress(arbProgram.publicKey)
+            )) === null
+        ) {
+            const tx = await buildTransactionV0(
+                connection,
+                [
+                    createInitializeMintRegistryInstruction(
+                        arbProgram.publicKey,
+                        payer.publicKey
+                    ),
+                ],
+                payer.publicKey,
+                [payer]
+            )
+            await connection.sendTransaction(tx)
+            await sleepSeconds(2)
+        }
+        // Approve a handful of mints per transaction to stay under the
+        // transaction size limit
+        for (let i = 0; i < mints.length; i += 8) {
+            const tx = await buildTransactionV0(
+                connection,
+                mints
+                    .slice(i, i + 8)
+                    .map((m) =>
+                        createAllowMintInstruction(
+                            arbProgram.publicKey,
+                            payer.publicKey,
+                            m
+                        )
+                    ),
+                payer.publicKey,
+                [payer]
+            )
+            await connection.sendTransaction(tx)
+            await sleepSeconds(2)
+        }
+    })
+
+    /**
+     * Creates the payer's risk and stats accounts if they don't exist yet
+     */
+    it('Create risk & stats accounts', async () => {
+        const instructions = []
+        if (
+            (await connection.getAccountInfo(
+                getRiskAddress(arbProgram.publicKey, payer.publicKey)
+            )) === null
+        ) {
+            instructions.push(
+                createInitializeRiskAccountInstruction(
+                    arbProgram.publicKey,
+                    payer.publicKey
+                )
+            )
+        }
+        if (
+            (await connection.getAccountInfo(
+                getStatsAddress(arbProgram.publicKey, payer.publicKey)
+            )) === null
+        ) {
+            instructions.push(
+                createInitializeStatsInstruction(
+                    arbProgram.publicKey,
+                    payer.publicKey,
+                    false
+                )
+            )
+        }
+        if (instructions.length > 0) {
+            const tx = await buildTransactionV0(
+                connection,
+                instructions,
+                payer.publicKey,
+                [payer]
+            )
+            await connection.sendTransaction(tx)
+            await sleepSeconds(2)
+        }
+    })
+
+    /**
+     * Sends a lone arbitrage instruction with preflight on, returning the
+     * error it fails with
+     */
+    async function simulateArbitrageError(
+        swapProgram1: PublicKey,
+        swapProgram2: PublicKey,
+        tokenAccountsUserSubList: PublicKey[],
+        tokenAccountsSwap1SubList: PublicKey[],
+        tokenAccountsSwap2SubList: PublicKey[],
+        mintsSubList: PublicKey[]
+    ): Promise<SendTransactionError> {
+        const ix = createArbitrageInstruction(
+            arbProgram.publicKey,
+            payer.publicKey,
+            tokenAccountsUserSubList,
+            tokenAccountsSwap1SubList,
+            tokenAccountsSwap2SubList,
+            mintsSubList,
+            mintsSubList.length,
+            temperature,
+            swapProgram1,
+            swapProgram2
+        )
+        const tx = await buildTransactionV0(
+            connection,
+            [ix],
+            payer.publicKey,
+            [payer]
+        )
+        try {
+            await connection.sendTransaction(tx)
+        } catch (error) {
+            expect(error).to.be.instanceOf(SendTransactionError)
+            return error as SendTransactionError
+        }
+        throw new Error('Arbitrage instruction should have failed')
+    }
+
+    /**
+     * A swap program that was never approved as a venue is refused before
+     * anything is traded
+     */
+    it('Reject a venue outside the allowlist', async () => {
+        const error = await simulateArbitrageError(
+            SWAP_PROGRAM_1,
+            Keypair.generate().publicKey,
+            tokenAccountsUser.slice(0, 2),
+            tokenAccountsSwap1.slice(0, 2),
+            tokenAccountsSwap2.slice(0, 2),
+            mints.slice(0, 2)
+        )
+        // `ArbitrageProgramError::VenueNotAllowed`
+        expect(error.message).to.include('custom program error: 0x4')
+    })
+
+    /**
+     * A mint that was never approved is dropped before any pair is
+     * evaluated, so it can't be traded even when paired with an approved one
+     */
+    it('Reject a mint outside the allowlist', async () => {
+        const mint = await createMint(
+            connection,
+            payer,
+            payer.publicKey,
+            null,
+            6
+        )
+        const tokenAccounts = []
+        for (const owner of [
+            payer.publicKey,
+            getPoolAddress(SWAP_PROGRAM_1),
+            getPoolAddress(SWAP_PROGRAM_2),
+        ]) {
+            const tokenAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                payer,
+                mint,
+                owner,
+                true
+            )
+            await mintTo(
+                connection,
+                payer,
+                mint,
+                tokenAccount.address,
+                payer,
+                1_000_000_000
+            )
+            tokenAccounts.push(tokenAccount.address)
+        }
+        const error = await simulateArbitrageError(
+            SWAP_PROGRAM_1,
+            SWAP_PROGRAM_2,
+            [tokenAccountsUser[0], tokenAccounts[0]],
+            [tokenAccountsSwap1[0], tokenAccounts[1]],
+            [tokenAccountsSwap2[0], tokenAccounts[2]],
+            [mints[0], mint]
+        )
+        // `ArbitrageProgramError::NoArbitrage`, with nothing left to pair
+        expect(error.message).to.include('custom program error: 0x3')
+        const skipped = `Skipping unapproved mint: ${mint.toBase58()}`
+        expect(error.logs?.some((log) => log.includes(skipped))).to.be.true
+        const after = await getTokenAccount(connection, tokenAccounts[0])
+        expect(after.amount).to.equal(BigInt(1_000_000_000))
+    })
+
     /**
      * Creates the Address Lookup Table for our arbitrage instruction
      */
@@ -0,0 +1,168 @@
+import {
+    createMint,
+    getAccount as getTokenAccount,
+    getOrCreateAssociatedTokenAccount,
+    mintTo,
+} from '@solana/spl-token'
+import { PublicKey } from '@solana/web3.js'
+import { expect } from 'chai'
+import { before, describe, it } from 'mocha'
+import { sleepSeconds } from './util'
+import { CONNECTION, MOCK_CLMM_PROGRAM, PAYER } from './util/const'
+import {
+    createClmmSwapInstruction,
+    createInitializeClmmPoolInstruction,
+    createInitializeTickArrayInstruction,
+    getClmmPoolAddress,
+    getTickArrayAddress,
+    Q64,
+} from './util/mocks'
+import { buildTransactionV0 } from './util/transaction'
+
+/**
+ * Trade through the mock CLMM pool, checking it fills exactly as the
+ * arbitrage program's CLMM quoting (`program/src/clmm.rs`) says it should
+ */
+describe('Mock CLMM pool', async () => {
+    const connection = CONNECTION
+    const payer = PAYER
+    const clmmProgram = MOCK_CLMM_PROGRAM.publicKey
+
+    let mintA: PublicKey
+    let mintB: PublicKey
+    let pool: PublicKey
+    let vaultA: PublicKey
+    let vaultB: PublicKey
+    let userA: PublicKey
+    let userB: PublicKey
+
+    /**
+     * Creates two fresh mints, the pool's vaults and the payer's token
+     * accounts, and funds them all
+     */
+    before('Create mints & token accounts', async () => {
+        mintA = await createMint(connection, payer, payer.publicKey, null, 6)
+        mintB = await createMint(connection, payer, payer.publicKey, null, 6)
+        pool = getClmmPoolAddress(clmmProgram, mintA, mintB)
+        vaultA = (
+            await getOrCreateAssociatedTokenAccount(
+                connection,
+                payer,
+                mintA,
+                pool,
+                true
+            )
+        ).address
+        vaultB = (
+            await getOrCreateAssociatedTokenAccount(
+                connection,
+                payer,
+                mintB,
+                pool,
+                true
+            )
+        ).address
+        userA = (
+            await getOrCreateAssociatedTokenAccount(
+                connection,
+                payer,
+                mintA,
+                payer.publicKey
+            )
+        ).address
+        userB = (
+            await getOrCreateAssociatedTokenAccount(
+                connection,
+                payer,
+                mintB,
+                payer.publicKey
+            )
+        ).address
+        for (const [mint, account] of [
+            [mintA, vaultA],
+            [mintB, vaultB],
+            [mintB, userB],
+        ]) {
+            await mintTo(connection, payer, mint, account, payer, 1_000_000_000)
+        }
+    })
+
+    /**
+     * Creates the pool at a price of 1.0 with 1,000,000 of liquidity, and a
+     * tick at 1.1 - where the liquidity doubles - and another at 2.0
+     */
+    it('Create the pool & its ticks', async () => {
+        const tx = await buildTransactionV0(
+            connection,
+            [
+                createInitializeClmmPoolInstruction(
+                    clmmProgram,
+                    payer.publicKey,
+                    mintA,
+                    mintB,
+                    vaultA,
+                    vaultB,
+                    0,
+                    Q64,
+                    BigInt(1_000_000),
+                    0
+                ),
+                createInitializeTickArrayInstruction(
+                    clmmProgram,
+                    payer.publicKey,
+                    pool,
+                    0,
+                    [
+                        {
+                            index: 100,
+                            sqrtPriceX64: Q64 + Q64 / BigInt(10),
+                            liquidityNet: BigInt(1_000_000),
+                        },
+                        {
+                            index: 200,
+                            sqrtPriceX64: BigInt(2) * Q64,
+                            liquidityNet: BigInt(0),
+                        },
+                    ]
+                ),
+            ],
+            payer.publicKey,
+            [payer]
+        )
+        await connection.sendTransaction(tx)
+        await sleepSeconds(2)
+    })
+
+    /**
+     * Pays 300,000 of B - crossing the tick at 1.1 - and checks the pool
+     * returns the 90,909 + 151,515 of A the program quotes
+     */
+    it('Swap across a tick', async () => {
+        const before = (await getTokenAccount(connection, userA)).amount
+        const tx = await buildTransactionV0(
+            connection,
+            [
+                createClmmSwapInstruction(
+                    clmmProgram,
+                    pool,
+                    payer.publicKey,
+                    userB,
+                    userA,
+                    vaultB,
+                    vaultA,
+                    mintB,
+                    mintA,
+                    [getTickArrayAddress(clmmProgram, pool, 0)],
+                    BigInt(300_000),
+                    BigInt(0)
+                ),
+            ],
+            payer.publicKey,
+            [payer]
+        )
+        await connection.sendTransaction(tx)
+        await sleepSeconds(2)
+        const after = (await getTokenAccount(connection, userA)).amount
+        expect(after - before).to.equal(BigInt(242_424))
+    })
+})
@@ -16,3 +16,8 @@ export const PAYER = loadKeypairFromFile(os.homedir + '/.config/solana/id.json')
 export const ARBITRAGE_PROGRAM = loadKeypairFromFile(
     './target/deploy/arb_program-keypair.json'
 )
+
+// Mock CLMM pool program
+export const MOCK_CLMM_PROGRAM = loadKeypairFromFile(
+    './target/deploy/mock_clmm_pool-keypair.json'
+)
@@ -22,6 +22,79 @@ export function getPoolAddress(programId: PublicKey): PublicKey {
     )[0]
 }
 
+/**
+ * Get the PDA of the Arbitrage Program's config
+ */
+export function getConfigAddress(programId: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        programId
+    )[0]
+}
+
+/**
+ * The upgradeable BPF loader, which keeps each program's Program Data
+ */
+export const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
+    'BPFLoaderUpgradeab1e11111111111111111111111'
+)
+
+/**
+ * Get the Program Data account holding a program's upgrade authority
+ */
+export function getProgramDataAddress(programId: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+        [programId.toBuffer()],
+        BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+    )[0]
+}
+
+/**
+ * Get the PDA of the Arbitrage Program's mint registry
+ */
+export function getMintRegistryAddress(programId: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from('mint_registry')],
+        programId
+    )[0]
+}
+
+/**
+ * Get the PDA of a trader's risk account
+ */
+export function getRiskAddress(
+    programId: PublicKey,
+    trader: PublicKey
+): PublicKey {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from('risk'), trader.toBuffer()],
+        programId
+    )[0]
+}
+
+/**
+ * Get the PDA of the Arbitrage Program's shared pair cooldowns
+ */
+export function getPairCooldownsAddress(programId: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from('pair_cooldowns')],
+        programId
+    )[0]
+}
+
+/**
+ * Get the PDA of a trader's stats account
+ */
+export function getStatsAddress(
+    programId: PublicKey,
+    trader: PublicKey
+): PublicKey {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from('stats'), trader.toBuffer()],
+        programId
+    )[0]
+}
+
 /**
  * Arbitrage program instructions
  */
@@ -66,6 +139,29 @@ const ArbitrageProgramInstructionSchema = new Map([
     ],
 ])
 
+/**
+ * Arbitrage program instructions that carry no data besides their index
+ */
+class UnitInstruction {
+    instruction: number
+    constructor(instruction: number) {
+        this.instruction = instruction
+    }
+    toBuffer() {
+        return Buffer.from(borsh.serialize(UnitInstructionSchema, this))
+    }
+}
+
+const UnitInstructionSchema = new Map([
+    [
+        UnitInstruction,
+        {
+            kind: 'struct',
+            fields: [['instruction', 'u8']],
+        },
+    ],
+])
+
 /**
  *
  * "Default" `AccountMeta` (marks as mutable non-signer)
@@ -134,6 +230,24 @@ export function createArbitrageInstruction(
         defaultAccountMeta(swapPool1),
         // Liquidity Pool for Swap #2
         defaultAccountMeta(swapPool2),
+        // Config
+        {
+            pubkey: getConfigAddress(programId),
+            isSigner: false,
+            isWritable: false,
+        },
+        // Mint Registry
+        {
+            pubkey: getMintRegistryAddress(programId),
+            isSigner: false,
+            isWritable: false,
+        },
+        // Risk Account
+        defaultAccountMeta(getRiskAddress(programId, payer)),
+        // Pair Cooldowns
+        defaultAccountMeta(getPairCooldownsAddress(programId)),
+        // Stats
+        defaultAccountMeta(getStatsAddress(programId, payer)),
     ]
     // [Token Accounts for User]
     tokenAccountsUser.forEach((a) => keys.push(defaultAccountMeta(a)))
@@ -150,3 +264,439 @@ export function createArbitrageInstruction(
         data,
     })
 }
+
+/**
+ *
+ * Creates the instruction to initialize the Arbitrage Program's config
+ *
+ * @param programId Arbitrage program ID
+ * @param authority The config's authority (also pays for the account), which
+ * must be the program's upgrade authority
+ * @returns `TransactionInstruction`
+ */
+export function createInitializeConfigInstruction(
+    programId: PublicKey,
+    authority: PublicKey
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: authority, isSigner: true, isWritable: true },
+            defaultAccountMeta(getConfigAddress(programId)),
+            {
+                pubkey: getProgramDataAddress(programId),
+                isSigner: false,
+                isWritable: false,
+            },
+            {
+                pubkey: SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+            },
+        ],
+        programId,
+        data: new UnitInstruction(1).toBuffer(),
+    })
+}
+
+/**
+ * The kinds of swap program a venue can be
+ */
+export enum VenueKind {
+    AnchorSwap = 0,
+    SplTokenSwap = 1,
+    Clmm = 2,
+    OrderBook = 3,
+    Registered = 4,
+}
+
+/**
+ * One seed of a venue's pool seed template: fixed bytes, one of the pool's
+ * two mints, or the venue's fee tier
+ */
+export type PoolSeed = { literal: Buffer } | 'mintA' | 'mintB' | 'feeTier'
+
+/**
+ * The tutorial swap's pool seed template, naming its single Liquidity Pool
+ */
+export const LIQUIDITY_POOL_SEEDS: PoolSeed[] = [
+    { literal: Buffer.from('liquidity_pool') },
+]
+
+/**
+ * Serializes a pool seed template as the `Vec<PoolSeed>` the program expects
+ */
+function poolSeedsToBuffer(seeds: PoolSeed[]): Buffer {
+    const len = Buffer.alloc(4)
+    len.writeUInt32LE(seeds.length)
+    return Buffer.concat([
+        len,
+        ...seeds.map((seed) => {
+            if (seed === 'mintA') return Buffer.from([1])
+            if (seed === 'mintB') return Buffer.from([2])
+            if (seed === 'feeTier') return Buffer.from([3])
+            const literalLen = Buffer.alloc(4)
+            literalLen.writeUInt32LE(seed.literal.length)
+            return Buffer.concat([Buffer.from([0]), literalLen, seed.literal])
+        }),
+    ])
+}
+
+/**
+ * Arbitrage program `AddVenue` instruction
+ */
+class AddVenueInstruction {
+    instruction: number
+    program_id: Uint8Array
+    pool_seeds: PoolSeed[]
+    fee_bps: number
+    kind: number
+    amplification?: bigint
+    constructor(props: {
+        swapProgram: PublicKey
+        poolSeeds: PoolSeed[]
+        feeBps: number
+        kind: VenueKind
+        amplification?: bigint
+    }) {
+        this.instruction = 2
+        this.program_id = props.swapProgram.toBuffer()
+        this.pool_seeds = props.poolSeeds
+        this.fee_bps = props.feeBps
+        this.kind = props.kind
+        this.amplification = props.amplification
+    }
+    toBuffer() {
+        const feeAndKind = Buffer.alloc(3)
+        feeAndKind.writeUInt16LE(this.fee_bps, 0)
+        feeAndKind.writeUInt8(this.kind, 2)
+        // The `Curve` enum: a constant product, or StableSwap with its
+        // amplification coefficient
+        let curve = Buffer.from([0])
+        if (this.amplification !== undefined) {
+            curve = Buffer.alloc(9)
+            curve.writeUInt8(1, 0)
+            curve.writeBigUInt64LE(this.amplification, 1)
+        }
+        return Buffer.concat([
+            Buffer.from([this.instruction]),
+            Buffer.from(this.program_id),
+            poolSeedsToBuffer(this.pool_seeds),
+            feeAndKind,
+            curve,
+        ])
+    }
+}
+
+/**
+ *
+ * Creates the instruction to approve a swap program as a venue
+ *
+ * @param programId Arbitrage program ID
+ * @param authority The config's authority
+ * @param swapProgram The swap program to approve
+ * @param feeBps The swap's fee tier in basis points
+ * @param kind Which kind of swap program it is
+ * @param amplification The StableSwap amplification coefficient, if its
+ * pools price on the StableSwap curve
+ * @param poolSeeds The seeds the swap program derives its pools from
+ * @returns `TransactionInstruction`
+ */
+export function createAddVenueInstruction(
+    programId: PublicKey,
+    authority: PublicKey,
+    swapProgram: PublicKey,
+    feeBps: number,
+    kind: VenueKind = VenueKind.AnchorSwap,
+    amplification?: bigint,
+    poolSeeds: PoolSeed[] = LIQUIDITY_POOL_SEEDS
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: authority, isSigner: true, isWritable: false },
+            defaultAccountMeta(getConfigAddress(programId)),
+        ],
+        programId,
+        data: new AddVenueInstruction({
+            swapProgram,
+            poolSeeds,
+            feeBps,
+            kind,
+            amplification,
+        }).toBuffer(),
+    })
+}
+
+/**
+ *
+ * Creates the instruction to initialize the Arbitrage Program's mint registry
+ *
+ * @param programId Arbitrage program ID
+ * @param authority The config's authority (also pays for the account)
+ * @returns `TransactionInstruction`
+ */
+export function createInitializeMintRegistryInstruction(
+    programId: PublicKey,
+    authority: PublicKey
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: authority, isSigner: true, isWritable: true },
+            {
+                pubkey: getConfigAddress(programId),
+                isSigner: false,
+                isWritable: false,
+            },
+            defaultAccountMeta(getMintRegistryAddress(programId)),
+            {
+                pubkey: SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+            },
+        ],
+        programId,
+        data: new UnitInstruction(6).toBuffer(),
+    })
+}
+
+/**
+ * Arbitrage program `SetMint` instruction, registering a mint as allowed
+ * with no caps
+ */
+class AllowMintInstruction {
+    instruction: number
+    mint: Uint8Array
+    status: number
+    max_trade_amount: null
+    max_trade_bps: null
+    reserve: number
+    max_window_loss: null
+    constructor(props: { mint: PublicKey }) {
+        this.instruction = 7
+        this.mint = props.mint.toBuffer()
+        this.status = 0
+        this.max_trade_amount = null
+        this.max_trade_bps = null
+        this.reserve = 0
+        this.max_window_loss = null
+    }
+    toBuffer() {
+        return Buffer.from(borsh.serialize(AllowMintInstructionSchema, this))
+    }
+}
+
+const AllowMintInstructionSchema = new Map([
+    [
+        AllowMintInstruction,
+        {
+            kind: 'struct',
+            fields: [
+                ['instruction', 'u8'],
+                ['mint', [32]],
+                ['status', 'u8'],
+                ['max_trade_amount', { kind: 'option', type: 'u64' }],
+                ['max_trade_bps', { kind: 'option', type: 'u16' }],
+                ['reserve', 'u64'],
+                ['max_window_loss', { kind: 'option', type: 'u64' }],
+            ],
+        },
+    ],
+])
+
+/**
+ *
+ * Creates the instruction to approve a mint for trading
+ *
+ * @param programId Arbitrage program ID
+ * @param authority The config's authority
+ * @param mint The mint to approve
+ * @returns `TransactionInstruction`
+ */
+export function createAllowMintInstruction(
+    programId: PublicKey,
+    authority: PublicKey,
+    mint: PublicKey
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: authority, isSigner: true, isWritable: false },
+            // Writable, since approving a mint is queued behind the timelock
+            defaultAccountMeta(getConfigAddress(programId)),
+            defaultAccountMeta(getMintRegistryAddress(programId)),
+        ],
+        programId,
+        data: new AllowMintInstruction({ mint }).toBuffer(),
+    })
+}
+
+/**
+ *
+ * Creates the instruction to initialize the payer's risk account
+ *
+ * @param programId Arbitrage program ID
+ * @param payer The trader the risk account tracks
+ * @returns `TransactionInstruction`
+ */
+export function createInitializeRiskAccountInstruction(
+    programId: PublicKey,
+    payer: PublicKey
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: payer, isSigner: true, isWritable: true },
+            defaultAccountMeta(getRiskAddress(programId, payer)),
+            {
+                pubkey: SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+            },
+        ],
+        programId,
+        data: new UnitInstruction(9).toBuffer(),
+    })
+}
+
+/**
+ *
+ * Creates the instruction to initialize the shared pair cooldowns
+ *
+ * @param programId Arbitrage program ID
+ * @param authority The config's authority (also pays for the account)
+ * @returns `TransactionInstruction`
+ */
+export function createInitializePairCooldownsInstruction(
+    programId: PublicKey,
+    authority: PublicKey
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: authority, isSigner: true, isWritable: true },
+            {
+                pubkey: getConfigAddress(programId),
+                isSigner: false,
+                isWritable: false,
+            },
+            defaultAccountMeta(getPairCooldownsAddress(programId)),
+            {
+                pubkey: SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+            },
+        ],
+        programId,
+        data: new UnitInstruction(11).toBuffer(),
+    })
+}
+
+/**
+ * Arbitrage program `InitializeStats` instruction
+ */
+class InitializeStatsInstruction {
+    instruction: number
+    count_scans: number
+    constructor(props: { countScans: boolean }) {
+        this.instruction = 13
+        this.count_scans = props.countScans ? 1 : 0
+    }
+    toBuffer() {
+        return Buffer.from(
+            borsh.serialize(InitializeStatsInstructionSchema, this)
+        )
+    }
+}
+
+const InitializeStatsInstructionSchema = new Map([
+    [
+        InitializeStatsInstruction,
+        {
+            kind: 'struct',
+            fields: [
+                ['instruction', 'u8'],
+                ['count_scans', 'u8'],
+            ],
+        },
+    ],
+])
+
+/**
+ *
+ * Creates the instruction to initialize the payer's stats account
+ *
+ * @param programId Arbitrage program ID
+ * @param payer The trader the stats account belongs to
+ * @param countScans Whether to count (and succeed) scans that find nothing
+ * @returns `TransactionInstruction`
+ */
+export function createInitializeStatsInstruction(
+    programId: PublicKey,
+    payer: PublicKey,
+    countScans: boolean
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: payer, isSigner: true, isWritable: true },
+            defaultAccountMeta(getStatsAddress(programId, payer)),
+            {
+                pubkey: SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+            },
+        ],
+        programId,
+        data: new InitializeStatsInstruction({ countScans }).toBuffer(),
+    })
+}
+
+/**
+ * Arbitrage program `EmitStats` instruction
+ */
+class EmitStatsInstruction {
+    instruction: number
+    page: number
+    constructor(props: { page: number }) {
+        this.instruction = 14
+        this.page = props.page
+    }
+    toBuffer() {
+        return Buffer.from(borsh.serialize(EmitStatsInstructionSchema, this))
+    }
+}
+
+const EmitStatsInstructionSchema = new Map([
+    [
+        EmitStatsInstruction,
+        {
+            kind: 'struct',
+            fields: [
+                ['instruction', 'u8'],
+                ['page', 'u8'],
+            ],
+        },
+    ],
+])
+
+/**
+ *
+ * Creates the instruction to emit a page of a trader's stats
+ *
+ * @param programId Arbitrage program ID
+ * @param trader The trader whose stats to emit
+ * @param page Which page to emit, from zero
+ * @returns `TransactionInstruction`
+ */
+export function createEmitStatsInstruction(
+    programId: PublicKey,
+    trader: PublicKey,
+    page: number = 0
+): TransactionInstruction {
+    return new TransactionInstruction({
+        keys: [
+            {
+                pubkey: getStatsAddress(programId, trader),
+                isSigner: false,
+                isWritable: false,
+            },
+        ],
+        programId,
+        data: new EmitStatsInstruction({ page }).toBuffer(),
+    })
+}
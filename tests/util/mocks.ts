@@ -0,0 +1,224 @@
+import { Buffer } from 'buffer'
+import {
+    PublicKey,
+    SystemProgram,
+    TransactionInstruction,
+} from '@solana/web3.js'
+import { TOKEN_PROGRAM_ID } from '@solana/spl-token'
+import { defaultAccountMeta } from './instruction'
+
+/**
+ * `1.0` in Q64.64
+ */
+export const Q64 = BigInt(1) << BigInt(64)
+
+/**
+ * An initialized tick of a mock CLMM pool
+ */
+export type ClmmTick = {
+    index: number
+    sqrtPriceX64: bigint
+    liquidityNet: bigint
+}
+
+/**
+ * Encodes a 128-bit integer - two's complement if negative - little-endian
+ */
+function u128ToBuffer(value: bigint): Buffer {
+    const unsigned =
+        value < BigInt(0) ? (BigInt(1) << BigInt(128)) + value : value
+    const buffer = Buffer.alloc(16)
+    buffer.writeBigUInt64LE(unsigned & BigInt('0xffffffffffffffff'), 0)
+    buffer.writeBigUInt64LE(unsigned >> BigInt(64), 8)
+    return buffer
+}
+
+/**
+ * Get the PDA of a mock CLMM pool for two mints
+ */
+export function getClmmPoolAddress(
+    programId: PublicKey,
+    mintA: PublicKey,
+    mintB: PublicKey
+): PublicKey {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from('clmm_pool'), mintA.toBuffer(), mintB.toBuffer()],
+        programId
+    )[0]
+}
+
+/**
+ * Get the PDA of one of a mock CLMM pool's tick arrays
+ */
+export function getTickArrayAddress(
+    programId: PublicKey,
+    pool: PublicKey,
+    id: number
+): PublicKey {
+    return PublicKey.findProgramAddressSync(
+        [Buffer.from('tick_array'), pool.toBuffer(), Buffer.from([id])],
+        programId
+    )[0]
+}
+
+/**
+ *
+ * Creates the instruction to create a mock CLMM pool
+ *
+ * @param programId Mock CLMM program ID
+ * @param payer Pays for the pool account
+ * @param mintA The pool's first mint
+ * @param mintB The pool's second mint
+ * @param vaultA The pool's token account for `mintA`
+ * @param vaultB The pool's token account for `mintB`
+ * @param feeBps The pool's fee, in basis points of the amount paid
+ * @param sqrtPriceX64 The square root of the starting price, in Q64.64
+ * @param liquidity The liquidity active at the starting price
+ * @param tickCurrent The index of the tick at or below the starting price
+ * @returns `TransactionInstruction`
+ */
+export function createInitializeClmmPoolInstruction(
+    programId: PublicKey,
+    payer: PublicKey,
+    mintA: PublicKey,
+    mintB: PublicKey,
+    vaultA: PublicKey,
+    vaultB: PublicKey,
+    feeBps: number,
+    sqrtPriceX64: bigint,
+    liquidity: bigint,
+    tickCurrent: number
+): TransactionInstruction {
+    const fee = Buffer.alloc(2)
+    fee.writeUInt16LE(feeBps)
+    const tick = Buffer.alloc(4)
+    tick.writeInt32LE(tickCurrent)
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: payer, isSigner: true, isWritable: true },
+            defaultAccountMeta(getClmmPoolAddress(programId, mintA, mintB)),
+            { pubkey: mintA, isSigner: false, isWritable: false },
+            { pubkey: mintB, isSigner: false, isWritable: false },
+            { pubkey: vaultA, isSigner: false, isWritable: false },
+            { pubkey: vaultB, isSigner: false, isWritable: false },
+            {
+                pubkey: SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+            },
+        ],
+        programId,
+        data: Buffer.concat([
+            Buffer.from([0]),
+            fee,
+            u128ToBuffer(sqrtPriceX64),
+            u128ToBuffer(liquidity),
+            tick,
+        ]),
+    })
+}
+
+/**
+ *
+ * Creates the instruction to create one of a mock CLMM pool's tick arrays
+ *
+ * @param programId Mock CLMM program ID
+ * @param payer Pays for the tick array account
+ * @param pool The pool the ticks belong to
+ * @param id Which of the pool's tick arrays this is
+ * @param ticks The initialized ticks, sorted by index
+ * @returns `TransactionInstruction`
+ */
+export function createInitializeTickArrayInstruction(
+    programId: PublicKey,
+    payer: PublicKey,
+    pool: PublicKey,
+    id: number,
+    ticks: ClmmTick[]
+): TransactionInstruction {
+    const len = Buffer.alloc(4)
+    len.writeUInt32LE(ticks.length)
+    return new TransactionInstruction({
+        keys: [
+            { pubkey: payer, isSigner: true, isWritable: true },
+            { pubkey: pool, isSigner: false, isWritable: false },
+            defaultAccountMeta(getTickArrayAddress(programId, pool, id)),
+            {
+                pubkey: SystemProgram.programId,
+                isSigner: false,
+                isWritable: false,
+            },
+        ],
+        programId,
+        data: Buffer.concat([
+            Buffer.from([1, id]),
+            len,
+            ...ticks.map((t) => {
+                const index = Buffer.alloc(4)
+                index.writeInt32LE(t.index)
+                return Buffer.concat([
+                    index,
+                    u128ToBuffer(t.sqrtPriceX64),
+                    u128ToBuffer(t.liquidityNet),
+                ])
+            }),
+        ]),
+    })
+}
+
+/**
+ *
+ * Creates the instruction to swap on a mock CLMM pool
+ *
+ * @param programId Mock CLMM program ID
+ * @param pool The pool to swap on
+ * @param authority Owner of the token accounts paid from and received into
+ * @param userPay The authority's token account to pay from
+ * @param userReceive The authority's token account to receive into
+ * @param poolPay The pool's vault to pay into
+ * @param poolReceive The pool's vault to receive from
+ * @param payMint The mint paid
+ * @param receiveMint The mint received
+ * @param tickArrays The pool's tick arrays the swap may cross
+ * @param amountIn How much to pay
+ * @param minimumAmountOut The least the swap may return
+ * @returns `TransactionInstruction`
+ */
+export function createClmmSwapInstruction(
+    programId: PublicKey,
+    pool: PublicKey,
+    authority: PublicKey,
+    userPay: PublicKey,
+    userReceive: PublicKey,
+    poolPay: PublicKey,
+    poolReceive: PublicKey,
+    payMint: PublicKey,
+    receiveMint: PublicKey,
+    tickArrays: PublicKey[],
+    amountIn: bigint,
+    minimumAmountOut: bigint
+): TransactionInstruction {
+    const amounts = Buffer.alloc(16)
+    amounts.writeBigUInt64LE(amountIn, 0)
+    amounts.writeBigUInt64LE(minimumAmountOut, 8)
+    return new TransactionInstruction({
+        keys: [
+            defaultAccountMeta(pool),
+            { pubkey: authority, isSigner: true, isWritable: false },
+            defaultAccountMeta(userPay),
+            defaultAccountMeta(userReceive),
+            defaultAccountMeta(poolPay),
+            defaultAccountMeta(poolReceive),
+            { pubkey: payMint, isSigner: false, isWritable: false },
+            { pubkey: receiveMint, isSigner: false, isWritable: false },
+            { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+            ...tickArrays.map((t) => ({
+                pubkey: t,
+                isSigner: false,
+                isWritable: false,
+            })),
+        ],
+        programId,
+        data: Buffer.concat([Buffer.from([2]), amounts]),
+    })
+}